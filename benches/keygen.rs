@@ -0,0 +1,84 @@
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use rand::rngs::OsRng;
+use rand::RngCore;
+use solana_sdk::signature::{Keypair, SeedDerivable, Signer};
+use solana_vanity_wallet::{
+    address_of, generate_keypair, search_vanity, SearchConfig, SearchProgress, VanityPattern,
+};
+
+fn random_seed() -> [u8; 32] {
+    let mut seed = [0u8; 32];
+    OsRng.fill_bytes(&mut seed);
+    seed
+}
+
+/// Seed -> public key, the only work the fast-mode hot loop needs per candidate
+fn bench_backends(c: &mut Criterion) {
+    let mut group = c.benchmark_group("seed_to_pubkey");
+    let seed = random_seed();
+
+    group.bench_function("solana_keypair (ed25519-dalek 1.x)", |b| {
+        b.iter(|| {
+            let keypair = Keypair::from_seed(black_box(&seed)).unwrap();
+            keypair.pubkey().to_bytes()
+        })
+    });
+
+    #[cfg(feature = "dalek2")]
+    group.bench_function("ed25519-dalek 2.x", |b| {
+        b.iter(|| {
+            let signing_key = ed25519_dalek::SigningKey::from_bytes(black_box(&seed));
+            signing_key.verifying_key().to_bytes()
+        })
+    });
+
+    group.finish();
+}
+
+/// Full candidate generation as the search loop runs it, including Base58 encoding
+fn bench_candidates(c: &mut Criterion) {
+    let mut group = c.benchmark_group("candidate");
+
+    group.bench_function("fast_mode", |b| {
+        b.iter(|| {
+            let (_, keypair) = generate_keypair(false).unwrap();
+            address_of(&keypair)
+        })
+    });
+
+    group.bench_function("mnemonic_mode", |b| {
+        b.iter(|| {
+            let (_, keypair) = generate_keypair(true).unwrap();
+            address_of(&keypair)
+        })
+    });
+
+    group.finish();
+}
+
+/// Whole searches on every core, so changes to the worker loop show up in start-up cost as well
+/// as throughput
+///
+/// Run it before and after any change to the loop in `search_vanity`, on the same machine, and
+/// compare the two reports.
+fn bench_search(c: &mut Criterion) {
+    let mut group = c.benchmark_group("search");
+    group.sample_size(20);
+
+    for prefix in ["A", "Ab"] {
+        group.bench_function(format!("prefix_{prefix}"), |b| {
+            b.iter(|| {
+                let config =
+                    SearchConfig::new(VanityPattern::Prefix(prefix.into()), false, num_cpus::get());
+                search_vanity(&config, &SearchProgress::default())
+                    .stats
+                    .iterations
+            })
+        });
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, bench_backends, bench_candidates, bench_search);
+criterion_main!(benches);
@@ -0,0 +1,226 @@
+use argon2::{Algorithm, Argon2, Params, Version};
+use base64::{engine::general_purpose::STANDARD, Engine as _};
+use chacha20poly1305::aead::{Aead, KeyInit};
+use chacha20poly1305::{Key, XChaCha20Poly1305, XNonce};
+use rand::rngs::OsRng;
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// Version tag written into every blob so the format can evolve
+pub const BLOB_VERSION: u32 = 1;
+
+const SALT_LEN: usize = 16;
+const NONCE_LEN: usize = 24;
+const KEY_LEN: usize = 32;
+
+/// Argon2id cost parameters, stored alongside the ciphertext
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct KdfParams {
+    pub memory_kib: u32,
+    pub iterations: u32,
+    pub parallelism: u32,
+}
+
+impl Default for KdfParams {
+    /// 64 MiB, 3 passes, 1 lane: slow enough to resist offline guessing on a paper backup
+    fn default() -> Self {
+        KdfParams {
+            memory_kib: 64 * 1024,
+            iterations: 3,
+            parallelism: 1,
+        }
+    }
+}
+
+/// Passphrase-encrypted secret: Argon2id key derivation + XChaCha20-Poly1305
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EncryptedBlob {
+    pub version: u32,
+    pub kdf: String,
+    pub kdf_params: KdfParams,
+    pub cipher: String,
+    pub salt: String,
+    pub nonce: String,
+    pub ciphertext: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EncryptionError {
+    /// The KDF rejected its parameters or failed to run
+    Kdf(String),
+    /// The blob declares a version, KDF or cipher this build does not understand
+    Unsupported(String),
+    /// A base64 field, salt or nonce is malformed
+    Malformed(String),
+    /// Wrong passphrase or tampered ciphertext
+    Decryption,
+}
+
+impl fmt::Display for EncryptionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EncryptionError::Kdf(msg) => write!(f, "key derivation failed: {msg}"),
+            EncryptionError::Unsupported(msg) => write!(f, "unsupported blob: {msg}"),
+            EncryptionError::Malformed(msg) => write!(f, "malformed blob: {msg}"),
+            EncryptionError::Decryption => {
+                write!(f, "decryption failed (wrong passphrase or corrupted data)")
+            }
+        }
+    }
+}
+
+impl std::error::Error for EncryptionError {}
+
+fn derive_key(
+    passphrase: &str,
+    salt: &[u8],
+    params: &KdfParams,
+) -> Result<[u8; KEY_LEN], EncryptionError> {
+    let argon_params = Params::new(
+        params.memory_kib,
+        params.iterations,
+        params.parallelism,
+        Some(KEY_LEN),
+    )
+    .map_err(|err| EncryptionError::Kdf(err.to_string()))?;
+    let argon = Argon2::new(Algorithm::Argon2id, Version::V0x13, argon_params);
+
+    let mut key = [0u8; KEY_LEN];
+    argon
+        .hash_password_into(passphrase.as_bytes(), salt, &mut key)
+        .map_err(|err| EncryptionError::Kdf(err.to_string()))?;
+    Ok(key)
+}
+
+fn decode_field(name: &str, value: &str) -> Result<Vec<u8>, EncryptionError> {
+    STANDARD
+        .decode(value)
+        .map_err(|err| EncryptionError::Malformed(format!("{name}: {err}")))
+}
+
+/// Encrypt `plaintext` under `passphrase` with a fresh random salt and nonce
+pub fn encrypt(
+    plaintext: &[u8],
+    passphrase: &str,
+    params: KdfParams,
+) -> Result<EncryptedBlob, EncryptionError> {
+    let mut salt = [0u8; SALT_LEN];
+    let mut nonce = [0u8; NONCE_LEN];
+    OsRng.fill_bytes(&mut salt);
+    OsRng.fill_bytes(&mut nonce);
+
+    let key = derive_key(passphrase, &salt, &params)?;
+    let cipher = XChaCha20Poly1305::new(Key::from_slice(&key));
+    let ciphertext = cipher
+        .encrypt(XNonce::from_slice(&nonce), plaintext)
+        .map_err(|_| EncryptionError::Decryption)?;
+
+    Ok(EncryptedBlob {
+        version: BLOB_VERSION,
+        kdf: "argon2id".to_string(),
+        kdf_params: params,
+        cipher: "xchacha20poly1305".to_string(),
+        salt: STANDARD.encode(salt),
+        nonce: STANDARD.encode(nonce),
+        ciphertext: STANDARD.encode(ciphertext),
+    })
+}
+
+/// Recover the plaintext from `blob` using the KDF parameters recorded inside it
+pub fn decrypt(blob: &EncryptedBlob, passphrase: &str) -> Result<Vec<u8>, EncryptionError> {
+    if blob.version != BLOB_VERSION {
+        return Err(EncryptionError::Unsupported(format!(
+            "version {}",
+            blob.version
+        )));
+    }
+    if blob.kdf != "argon2id" || blob.cipher != "xchacha20poly1305" {
+        return Err(EncryptionError::Unsupported(format!(
+            "{} / {}",
+            blob.kdf, blob.cipher
+        )));
+    }
+
+    let salt = decode_field("salt", &blob.salt)?;
+    let nonce = decode_field("nonce", &blob.nonce)?;
+    let ciphertext = decode_field("ciphertext", &blob.ciphertext)?;
+    if nonce.len() != NONCE_LEN {
+        return Err(EncryptionError::Malformed(format!(
+            "nonce must be {NONCE_LEN} bytes, got {}",
+            nonce.len()
+        )));
+    }
+
+    let key = derive_key(passphrase, &salt, &blob.kdf_params)?;
+    let cipher = XChaCha20Poly1305::new(Key::from_slice(&key));
+    cipher
+        .decrypt(XNonce::from_slice(&nonce), ciphertext.as_slice())
+        .map_err(|_| EncryptionError::Decryption)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Cheap parameters so the tests stay fast in debug builds
+    const TEST_PARAMS: KdfParams = KdfParams {
+        memory_kib: 1024,
+        iterations: 1,
+        parallelism: 1,
+    };
+
+    const MNEMONIC: &str = "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about";
+
+    #[test]
+    fn test_encrypt_decrypt_round_trip() {
+        let blob = encrypt(MNEMONIC.as_bytes(), "correct horse", TEST_PARAMS).unwrap();
+
+        assert_eq!(blob.kdf_params, TEST_PARAMS);
+        assert!(!blob.ciphertext.contains("abandon"));
+
+        let plaintext = decrypt(&blob, "correct horse").unwrap();
+        assert_eq!(String::from_utf8(plaintext).unwrap(), MNEMONIC);
+    }
+
+    #[test]
+    fn test_round_trip_through_json() {
+        let blob = encrypt(MNEMONIC.as_bytes(), "pw", TEST_PARAMS).unwrap();
+        let json = serde_json::to_string(&blob).unwrap();
+        let parsed: EncryptedBlob = serde_json::from_str(&json).unwrap();
+
+        assert_eq!(decrypt(&parsed, "pw").unwrap(), MNEMONIC.as_bytes());
+    }
+
+    #[test]
+    fn test_wrong_passphrase_is_rejected() {
+        let blob = encrypt(MNEMONIC.as_bytes(), "right", TEST_PARAMS).unwrap();
+
+        assert_eq!(decrypt(&blob, "wrong"), Err(EncryptionError::Decryption));
+    }
+
+    #[test]
+    fn test_salt_and_nonce_are_fresh() {
+        let first = encrypt(MNEMONIC.as_bytes(), "pw", TEST_PARAMS).unwrap();
+        let second = encrypt(MNEMONIC.as_bytes(), "pw", TEST_PARAMS).unwrap();
+
+        assert_ne!(first.salt, second.salt);
+        assert_ne!(first.nonce, second.nonce);
+        assert_ne!(first.ciphertext, second.ciphertext);
+    }
+
+    #[test]
+    fn test_tampered_blob_is_rejected() {
+        let mut blob = encrypt(MNEMONIC.as_bytes(), "pw", TEST_PARAMS).unwrap();
+        let mut bytes = STANDARD.decode(&blob.ciphertext).unwrap();
+        bytes[0] ^= 0xff;
+        blob.ciphertext = STANDARD.encode(bytes);
+        assert_eq!(decrypt(&blob, "pw"), Err(EncryptionError::Decryption));
+
+        blob.version = 99;
+        assert!(matches!(
+            decrypt(&blob, "pw"),
+            Err(EncryptionError::Unsupported(_))
+        ));
+    }
+}
@@ -0,0 +1,356 @@
+use rand::rngs::OsRng;
+use rand::RngCore;
+#[cfg(any(test, feature = "test-harness"))]
+use rand::{rngs::StdRng, SeedableRng};
+use std::fmt;
+use std::fs::File;
+use std::io::{self, Read};
+
+/// The kernel's blocking entropy device
+pub const HARDWARE_ENTROPY_PATH: &str = "/dev/random";
+
+/// Where key material for new wallets comes from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum EntropySource {
+    /// The userspace CSPRNG seeded by the OS (`OsRng`)
+    #[default]
+    Os,
+    /// Raw reads from the blocking entropy device, and nothing else
+    Hardware,
+    /// A fixed-seed generator for reproducible tests; never use it for real wallets
+    #[cfg(any(test, feature = "test-harness"))]
+    Seeded(u64),
+}
+
+impl EntropySource {
+    /// A random number generator drawing from this source
+    ///
+    /// Panics if the entropy device can't be opened; check with [`HardwareRng::open`] first to
+    /// report that properly.
+    pub fn rng(self) -> Box<dyn RngCore> {
+        match self {
+            EntropySource::Os => Box::new(OsRng),
+            EntropySource::Hardware => Box::new(
+                HardwareRng::open()
+                    .unwrap_or_else(|err| panic!("can't open {HARDWARE_ENTROPY_PATH}: {err}")),
+            ),
+            #[cfg(any(test, feature = "test-harness"))]
+            EntropySource::Seeded(seed) => Box::new(StdRng::seed_from_u64(seed)),
+        }
+    }
+
+    /// The generator for search worker `worker`, so seeded workers don't repeat each other
+    #[cfg_attr(not(any(test, feature = "test-harness")), allow(unused_variables))]
+    pub fn worker_rng(self, worker: usize) -> Box<dyn RngCore> {
+        match self {
+            #[cfg(any(test, feature = "test-harness"))]
+            EntropySource::Seeded(seed) => {
+                Box::new(StdRng::seed_from_u64(seed.wrapping_add(worker as u64)))
+            }
+            _ => self.rng(),
+        }
+    }
+}
+
+/// Shannon entropy of the byte values in `bytes`, in bits per byte
+pub fn shannon_entropy(bytes: &[u8]) -> f64 {
+    let mut counts = [0usize; 256];
+    for &byte in bytes {
+        counts[byte as usize] += 1;
+    }
+    let len = bytes.len() as f64;
+    counts
+        .iter()
+        .filter(|&&count| count > 0)
+        .map(|&count| {
+            let p = count as f64 / len;
+            -p * p.log2()
+        })
+        .sum()
+}
+
+/// Heuristic for key material that is far too regular to have come from a real RNG
+///
+/// Flags inputs whose byte distribution carries less than half the entropy `bytes.len()`
+/// distinct values would, which catches all-zero, all-`0xff` and short repeating patterns while
+/// random 16- and 32-byte inputs pass essentially always. Keys from such input are trivially
+/// guessable and must never hold funds.
+pub fn looks_low_entropy(bytes: &[u8]) -> bool {
+    if bytes.is_empty() {
+        return true;
+    }
+    let best_possible = (bytes.len().min(256) as f64).log2();
+    shannon_entropy(bytes) < best_possible / 2.0
+}
+
+/// Bytes drawn per block by [`rng_health_check`]
+const HEALTH_CHECK_BLOCK: usize = 2048;
+
+/// Why [`rng_health_check`] rejected a generator
+#[derive(Debug, Clone, PartialEq)]
+pub enum RngError {
+    /// Every byte drawn was the same, e.g. all zeros
+    Constant(u8),
+    /// Two consecutive draws returned identical bytes
+    Repeats,
+    /// Byte values are far from uniform, by Pearson's chi-squared over 256 bins
+    Skewed { chi_squared: f64 },
+}
+
+impl fmt::Display for RngError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RngError::Constant(byte) => write!(f, "every byte was 0x{byte:02x}"),
+            RngError::Repeats => write!(f, "consecutive draws returned identical bytes"),
+            RngError::Skewed { chi_squared } => write!(
+                f,
+                "byte distribution is not uniform (chi-squared {chi_squared:.0}, expected about 255)"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for RngError {}
+
+/// Draw a sample from `rng` and check it looks like a working random source
+///
+/// Catches broken or misconfigured generators (stuck at a constant, replaying the same output,
+/// or plainly biased) before they produce predictable keys. The chi-squared bounds sit more
+/// than five standard deviations out, so a healthy generator practically never fails.
+pub fn rng_health_check(rng: &mut dyn RngCore) -> Result<(), RngError> {
+    let mut first = [0u8; HEALTH_CHECK_BLOCK];
+    let mut second = [0u8; HEALTH_CHECK_BLOCK];
+    rng.fill_bytes(&mut first);
+    rng.fill_bytes(&mut second);
+
+    if first.iter().chain(&second).all(|&byte| byte == first[0]) {
+        return Err(RngError::Constant(first[0]));
+    }
+    if first == second {
+        return Err(RngError::Repeats);
+    }
+
+    let mut counts = [0u32; 256];
+    for &byte in first.iter().chain(&second) {
+        counts[byte as usize] += 1;
+    }
+    let expected = (2 * HEALTH_CHECK_BLOCK) as f64 / 256.0;
+    let chi_squared: f64 = counts
+        .iter()
+        .map(|&count| (count as f64 - expected).powi(2) / expected)
+        .sum();
+    // 255 degrees of freedom: mean 255, standard deviation about 22.6. Too uniform is as
+    // suspicious as too skewed, since a counter passes every other test.
+    if !(150.0..=400.0).contains(&chi_squared) {
+        return Err(RngError::Skewed { chi_squared });
+    }
+    Ok(())
+}
+
+/// Reads every byte straight from an entropy device instead of a userspace generator
+///
+/// There is no fallback: someone who asked for the device shouldn't get keys from anything
+/// else without knowing. A failed read is an error from `try_fill_bytes` and a panic from
+/// `fill_bytes`.
+pub struct HardwareRng {
+    source: Box<dyn Read + Send>,
+}
+
+impl HardwareRng {
+    /// Open [`HARDWARE_ENTROPY_PATH`]
+    pub fn open() -> io::Result<Self> {
+        Ok(HardwareRng::from_reader(Box::new(File::open(
+            HARDWARE_ENTROPY_PATH,
+        )?)))
+    }
+
+    /// Draw entropy from `reader`
+    pub fn from_reader(reader: Box<dyn Read + Send>) -> Self {
+        HardwareRng { source: reader }
+    }
+}
+
+impl RngCore for HardwareRng {
+    fn next_u32(&mut self) -> u32 {
+        let mut bytes = [0u8; 4];
+        self.fill_bytes(&mut bytes);
+        u32::from_le_bytes(bytes)
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        let mut bytes = [0u8; 8];
+        self.fill_bytes(&mut bytes);
+        u64::from_le_bytes(bytes)
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        if let Err(err) = self.try_fill_bytes(dest) {
+            panic!("{err}");
+        }
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), rand::Error> {
+        self.source.read_exact(dest).map_err(|err| {
+            rand::Error::new(io::Error::new(
+                err.kind(),
+                format!("reading {HARDWARE_ENTROPY_PATH} failed: {err}"),
+            ))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    #[test]
+    fn test_reads_from_source() {
+        let bytes: Vec<u8> = (0..40).collect();
+        let mut rng = HardwareRng::from_reader(Box::new(Cursor::new(bytes)));
+
+        let mut seed = [0u8; 32];
+        rng.fill_bytes(&mut seed);
+        assert_eq!(seed.to_vec(), (0..32).collect::<Vec<u8>>());
+        assert_eq!(
+            rng.next_u64(),
+            u64::from_le_bytes([32, 33, 34, 35, 36, 37, 38, 39])
+        );
+    }
+
+    #[test]
+    fn test_fails_when_source_runs_dry() {
+        let mut rng = HardwareRng::from_reader(Box::new(Cursor::new(vec![7u8; 8])));
+
+        let mut seed = [0u8; 32];
+        let err = rng.try_fill_bytes(&mut seed).unwrap_err();
+        assert!(err.to_string().contains(HARDWARE_ENTROPY_PATH), "{err}");
+    }
+
+    #[test]
+    #[should_panic(expected = "reading /dev/random failed")]
+    fn test_fill_bytes_panics_rather_than_fall_back() {
+        let mut rng = HardwareRng::from_reader(Box::new(Cursor::new(Vec::new())));
+        rng.fill_bytes(&mut [0u8; 32]);
+    }
+
+    #[test]
+    fn test_seeded_workers_are_reproducible_and_distinct() {
+        let draw = |worker| EntropySource::Seeded(7).worker_rng(worker).next_u64();
+
+        assert_eq!(draw(0), draw(0));
+        assert_eq!(draw(0), EntropySource::Seeded(7).rng().next_u64());
+        assert_ne!(draw(0), draw(1));
+    }
+
+    #[test]
+    fn test_low_entropy_all_zeros() {
+        assert_eq!(shannon_entropy(&[0u8; 16]), 0.0);
+        assert!(looks_low_entropy(&[0u8; 16]));
+        assert!(looks_low_entropy(&[0u8; 32]));
+        assert!(looks_low_entropy(&[]));
+    }
+
+    #[test]
+    fn test_low_entropy_all_ones() {
+        assert!(looks_low_entropy(&[0xffu8; 16]));
+        assert!(looks_low_entropy(&[0xffu8; 32]));
+
+        // A short repeating pattern is no better
+        let pattern: Vec<u8> = [0x00, 0xff].repeat(16);
+        assert_eq!(shannon_entropy(&pattern), 1.0);
+        assert!(looks_low_entropy(&pattern));
+    }
+
+    #[test]
+    fn test_random_entropy_passes() {
+        for _ in 0..100 {
+            let mut short = [0u8; 16];
+            let mut long = [0u8; 32];
+            OsRng.fill_bytes(&mut short);
+            OsRng.fill_bytes(&mut long);
+            assert!(!looks_low_entropy(&short), "{short:?}");
+            assert!(!looks_low_entropy(&long), "{long:?}");
+        }
+
+        let counting: Vec<u8> = (0..32).collect();
+        assert_eq!(shannon_entropy(&counting), 5.0);
+        assert!(!looks_low_entropy(&counting));
+    }
+
+    /// A generator that keeps handing out the same bytes
+    struct ReplayRng(Vec<u8>);
+
+    impl RngCore for ReplayRng {
+        fn next_u32(&mut self) -> u32 {
+            let mut bytes = [0u8; 4];
+            self.fill_bytes(&mut bytes);
+            u32::from_le_bytes(bytes)
+        }
+
+        fn next_u64(&mut self) -> u64 {
+            let mut bytes = [0u8; 8];
+            self.fill_bytes(&mut bytes);
+            u64::from_le_bytes(bytes)
+        }
+
+        fn fill_bytes(&mut self, dest: &mut [u8]) {
+            for (byte, value) in dest.iter_mut().zip(self.0.iter().cycle()) {
+                *byte = *value;
+            }
+        }
+
+        fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), rand::Error> {
+            self.fill_bytes(dest);
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_health_check_passes_real_rngs() {
+        assert_eq!(rng_health_check(&mut OsRng), Ok(()));
+        assert_eq!(
+            rng_health_check(&mut *EntropySource::Seeded(1).rng()),
+            Ok(())
+        );
+    }
+
+    #[test]
+    fn test_health_check_rejects_broken_rngs() {
+        assert_eq!(
+            rng_health_check(&mut ReplayRng(vec![0])),
+            Err(RngError::Constant(0))
+        );
+        assert_eq!(
+            rng_health_check(&mut ReplayRng(vec![0xff])),
+            Err(RngError::Constant(0xff))
+        );
+
+        // Random-looking output that starts over on every call
+        let mut block = vec![0u8; HEALTH_CHECK_BLOCK];
+        OsRng.fill_bytes(&mut block);
+        assert_eq!(
+            rng_health_check(&mut ReplayRng(block)),
+            Err(RngError::Repeats)
+        );
+
+        // Never produces the top half of the byte range
+        let mut biased = StdRng::seed_from_u64(3);
+        let mut skewed = vec![0u8; 4 * HEALTH_CHECK_BLOCK];
+        biased.fill_bytes(&mut skewed);
+        skewed.iter_mut().for_each(|byte| *byte &= 0x7f);
+        let mut skewed = HardwareRng::from_reader(Box::new(Cursor::new(skewed)));
+        assert!(matches!(
+            rng_health_check(&mut skewed),
+            Err(RngError::Skewed { .. })
+        ));
+
+        // A slow counter never repeats a block but is too uniform to be random
+        let counter: Vec<u8> = (0..2 * HEALTH_CHECK_BLOCK).map(|i| (i / 3) as u8).collect();
+        let mut counter = HardwareRng::from_reader(Box::new(Cursor::new(counter)));
+        assert!(matches!(
+            rng_health_check(&mut counter),
+            Err(RngError::Skewed { chi_squared }) if chi_squared < 150.0
+        ));
+    }
+}
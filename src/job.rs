@@ -0,0 +1,227 @@
+//! Declarative batch jobs read from a JSON file
+//!
+//! A job file is an array of [`JobSpec`]s that `--job` runs one after another, so a team can keep
+//! the wallets it provisions in version control. Every job is checked before any search starts,
+//! so a typo in the last job can't waste the hours spent on the first.
+
+use crate::{validate_prefix, DerivationPath, SearchConfig, SearchStats, VanityPattern};
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use std::str::FromStr;
+
+/// Where `--job` writes the results of a run, inside the output directory
+pub const JOB_RESULTS_FILE: &str = "job_results.json";
+
+/// One entry of a job file: what to search for and where the wallets go
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct JobSpec {
+    /// Text the address must start with; give this or `suffix`
+    #[serde(default)]
+    pub prefix: Option<String>,
+    /// Text the address must end with; give this or `prefix`
+    #[serde(default)]
+    pub suffix: Option<String>,
+    /// Number of wallets to find
+    #[serde(default = "one")]
+    pub count: usize,
+    /// BIP32 path to derive at; implies a mnemonic
+    #[serde(default)]
+    pub derivation: Option<String>,
+    /// Generate a recovery phrase for each wallet
+    #[serde(default)]
+    pub with_mnemonic: bool,
+    /// Directory for this job's wallet files instead of `output`
+    #[serde(default)]
+    pub output: Option<PathBuf>,
+}
+
+fn one() -> usize {
+    1
+}
+
+impl JobSpec {
+    /// Check the pattern, count and derivation path without searching
+    pub fn validate(&self) -> Result<(), String> {
+        let text = match (&self.prefix, &self.suffix) {
+            (Some(text), None) | (None, Some(text)) => text,
+            (Some(_), Some(_)) => return Err("give either prefix or suffix, not both".to_string()),
+            (None, None) => return Err("needs a prefix or a suffix".to_string()),
+        };
+        validate_prefix(text).map_err(|err| format!("invalid pattern '{text}': {err}"))?;
+        if self.count == 0 {
+            return Err("count must be at least 1".to_string());
+        }
+        if let Some(path) = &self.derivation {
+            DerivationPath::from_str(path).map_err(|err| err.to_string())?;
+        }
+        Ok(())
+    }
+
+    /// The pattern to search for; call [`JobSpec::validate`] first
+    pub fn pattern(&self) -> VanityPattern {
+        match (&self.prefix, &self.suffix) {
+            (_, Some(suffix)) => VanityPattern::Suffix(suffix.clone()),
+            (prefix, None) => VanityPattern::Prefix(prefix.clone().unwrap_or_default()),
+        }
+    }
+
+    /// Whether wallets get a recovery phrase, asked for directly or through a derivation path
+    pub fn needs_mnemonic(&self) -> bool {
+        self.with_mnemonic || self.derivation.is_some()
+    }
+
+    /// The derivation path, Solana's default when none is given
+    pub fn derivation_path(&self) -> DerivationPath {
+        self.derivation
+            .as_deref()
+            .and_then(|path| DerivationPath::from_str(path).ok())
+            .unwrap_or_default()
+    }
+
+    /// A search for one of this job's wallets on `threads` threads
+    pub fn search_config(&self, threads: usize) -> SearchConfig {
+        SearchConfig {
+            derivation_path: self.derivation_path(),
+            ..SearchConfig::new(self.pattern(), self.needs_mnemonic(), threads)
+        }
+    }
+
+    /// Short description such as `prefix Sun`, for progress lines and the results file
+    pub fn describe(&self) -> String {
+        match (&self.prefix, &self.suffix) {
+            (_, Some(suffix)) => format!("suffix {suffix}"),
+            (prefix, None) => format!("prefix {}", prefix.as_deref().unwrap_or_default()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum JobError {
+    /// The file isn't a JSON array of jobs
+    Parse(String),
+    /// The file has no jobs in it
+    Empty,
+    /// Job `job` (counting from 1) can't be run
+    Invalid { job: usize, reason: String },
+}
+
+impl std::fmt::Display for JobError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            JobError::Parse(msg) => write!(f, "not a valid job file: {msg}"),
+            JobError::Empty => write!(f, "the job file lists no jobs"),
+            JobError::Invalid { job, reason } => write!(f, "job {job}: {reason}"),
+        }
+    }
+}
+
+impl std::error::Error for JobError {}
+
+/// Parse a job file and validate every job in it
+pub fn parse_jobs(text: &str) -> Result<Vec<JobSpec>, JobError> {
+    let jobs: Vec<JobSpec> =
+        serde_json::from_str(text).map_err(|err| JobError::Parse(err.to_string()))?;
+    if jobs.is_empty() {
+        return Err(JobError::Empty);
+    }
+    for (index, job) in jobs.iter().enumerate() {
+        job.validate().map_err(|reason| JobError::Invalid {
+            job: index + 1,
+            reason,
+        })?;
+    }
+    Ok(jobs)
+}
+
+/// One wallet's entry in the results file; the secrets stay in the wallet's own file
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct JobResult {
+    /// Job number, counting from 1
+    pub job: usize,
+    pub pattern: String,
+    /// `None` if the search hit its limits first
+    pub public_key: Option<String>,
+    pub output_dir: PathBuf,
+    pub iterations: u128,
+    pub elapsed_seconds: f64,
+}
+
+impl JobResult {
+    pub fn new(
+        job: usize,
+        spec: &JobSpec,
+        public_key: Option<String>,
+        output_dir: PathBuf,
+        stats: &SearchStats,
+    ) -> Self {
+        JobResult {
+            job,
+            pattern: spec.describe(),
+            public_key,
+            output_dir,
+            iterations: stats.iterations,
+            elapsed_seconds: stats.elapsed.as_secs_f64(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLE: &str = r#"[
+        {"prefix": "Sun", "count": 2, "output": "treasury"},
+        {"suffix": "pay", "derivation": "m/44'/501'/1'/0'"},
+        {"prefix": "A", "with_mnemonic": true}
+    ]"#;
+
+    #[test]
+    fn test_parse_sample_job_file() {
+        let jobs = parse_jobs(SAMPLE).unwrap();
+        assert_eq!(jobs.len(), 3);
+
+        assert_eq!(jobs[0].pattern(), VanityPattern::Prefix("Sun".to_string()));
+        assert_eq!(jobs[0].count, 2);
+        assert_eq!(jobs[0].output, Some(PathBuf::from("treasury")));
+        assert!(!jobs[0].needs_mnemonic());
+        assert_eq!(jobs[0].describe(), "prefix Sun");
+
+        assert_eq!(jobs[1].pattern(), VanityPattern::Suffix("pay".to_string()));
+        assert_eq!(jobs[1].count, 1);
+        assert!(jobs[1].needs_mnemonic());
+        assert_eq!(jobs[1].derivation_path().to_string(), "m/44'/501'/1'/0'");
+        assert_eq!(jobs[1].describe(), "suffix pay");
+
+        let config = jobs[2].search_config(4);
+        assert!(config.with_mnemonic);
+        assert_eq!(config.threads, 4);
+        assert_eq!(config.derivation_path, DerivationPath::default());
+    }
+
+    #[test]
+    fn test_invalid_jobs_fail_fast() {
+        let invalid = |text: &str| match parse_jobs(text) {
+            Err(JobError::Invalid { job, .. }) => Some(job),
+            _ => None,
+        };
+        assert_eq!(
+            invalid(r#"[{"prefix": "Sun"}, {"prefix": "S0n"}]"#),
+            Some(2)
+        );
+        assert_eq!(invalid(r#"[{"prefix": "Sun", "suffix": "Sun"}]"#), Some(1));
+        assert_eq!(invalid(r#"[{"count": 1}]"#), Some(1));
+        assert_eq!(invalid(r#"[{"prefix": "Sun", "count": 0}]"#), Some(1));
+        assert_eq!(
+            invalid(r#"[{"prefix": "Sun", "derivation": "m/44/501"}]"#),
+            Some(1)
+        );
+
+        assert_eq!(parse_jobs("[]"), Err(JobError::Empty));
+        // A misspelled field is an error rather than silently ignored
+        assert!(matches!(
+            parse_jobs(r#"[{"prefix": "Sun", "cuont": 3}]"#),
+            Err(JobError::Parse(_))
+        ));
+    }
+}
@@ -1,159 +1,7467 @@
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine as _};
 use bip39::{Language, Mnemonic};
+use rand::rngs::OsRng;
+use rand::{RngCore, SeedableRng};
+use rayon::prelude::*;
+use regex_syntax::hir::{Class, Hir, HirKind, Look};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
 use slip10::{derive_key_from_path, BIP32Path};
+use solana_sdk::pubkey::Pubkey;
 use solana_sdk::signature::{Keypair, SeedDerivable, Signer};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet, VecDeque};
+use std::fs;
+use std::io::{self, Write};
+use std::ops::Range;
+use std::path::{Path, PathBuf};
 use std::str::FromStr;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, OnceLock};
+use std::time::{Duration, Instant};
+
+pub mod encryption;
+pub mod entropy;
+pub mod job;
+pub mod metrics;
+pub mod pause;
+#[cfg(feature = "pda")]
+pub mod pda;
+pub mod repl;
+#[cfg(feature = "server")]
+pub mod server;
+
+use encryption::{EncryptedBlob, EncryptionError, KdfParams};
+use entropy::EntropySource;
+
+/// Solana's standard BIP44 path; 501 is Solana's coin type
+pub const SOLANA_DERIVATION_PATH: &str = "m/44'/501'/0'/0'";
+
+/// Solana's registered SLIP-0044 coin type
+pub const SOLANA_COIN_TYPE: u32 = 501;
+
+/// A BIP32 path such as `m/44'/501'/0'/0'` with every level hardened, as SLIP-10 requires for
+/// ed25519
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DerivationPath {
+    text: String,
+    path: BIP32Path,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DerivationPathError {
+    /// Not of the form `m/<index>'/...`
+    Malformed(String),
+    /// Ed25519 keys can only be derived through hardened indexes
+    NotHardened(String),
+    /// A coin type too large to be a hardened index
+    CoinTypeOutOfRange(u32),
+    /// An account number too large to be a hardened index
+    AccountOutOfRange(u32),
+}
+
+impl std::fmt::Display for DerivationPathError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DerivationPathError::Malformed(path) => {
+                write!(
+                    f,
+                    "'{path}' is not a BIP32 path like {SOLANA_DERIVATION_PATH}"
+                )
+            }
+            DerivationPathError::NotHardened(path) => write!(
+                f,
+                "'{path}' has unhardened levels; ed25519 needs every index hardened (e.g. 0')"
+            ),
+            DerivationPathError::CoinTypeOutOfRange(coin_type) => write!(
+                f,
+                "coin type {coin_type} is outside the hardened range 0-{}",
+                slip10::HARDEND - 1
+            ),
+            DerivationPathError::AccountOutOfRange(account) => write!(
+                f,
+                "account {account} is outside the hardened range 0-{}",
+                slip10::HARDEND - 1
+            ),
+        }
+    }
+}
+
+impl std::error::Error for DerivationPathError {}
+
+impl FromStr for DerivationPath {
+    type Err = DerivationPathError;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let path = BIP32Path::from_str(text)
+            .map_err(|_| DerivationPathError::Malformed(text.to_string()))?;
+        if path.depth() == 0 {
+            return Err(DerivationPathError::Malformed(text.to_string()));
+        }
+        let all_hardened = (0..path.depth()).all(|depth| {
+            path.index(depth)
+                .is_some_and(|&index| index >= slip10::HARDEND)
+        });
+        if !all_hardened {
+            return Err(DerivationPathError::NotHardened(text.to_string()));
+        }
+        Ok(DerivationPath {
+            text: text.to_string(),
+            path,
+        })
+    }
+}
+
+impl DerivationPath {
+    /// The standard `m/44'/<coin_type>'/0'/0'` path for a SLIP-0044 coin type other than
+    /// Solana's, e.g. for testnets or experiments with custom derivations
+    pub fn for_coin_type(coin_type: u32) -> Result<Self, DerivationPathError> {
+        if coin_type >= slip10::HARDEND {
+            return Err(DerivationPathError::CoinTypeOutOfRange(coin_type));
+        }
+        format!("m/44'/{coin_type}'/0'/0'").parse()
+    }
+
+    /// Solana's `m/44'/501'/<account>'/0'`, where wallet apps put their second, third, ...
+    /// accounts
+    pub fn for_account(account: u32) -> Result<Self, DerivationPathError> {
+        if account >= slip10::HARDEND {
+            return Err(DerivationPathError::AccountOutOfRange(account));
+        }
+        format!("m/44'/{SOLANA_COIN_TYPE}'/{account}'/0'").parse()
+    }
+
+    /// The index at each level, without the hardened bit
+    pub fn indexes(&self) -> Vec<u32> {
+        (0..self.path.depth())
+            .filter_map(|depth| self.path.index(depth))
+            .map(|index| index - slip10::HARDEND)
+            .collect()
+    }
+}
+
+impl Default for DerivationPath {
+    fn default() -> Self {
+        SOLANA_DERIVATION_PATH
+            .parse()
+            .expect("the standard Solana path is valid")
+    }
+}
+
+impl std::fmt::Display for DerivationPath {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.text)
+    }
+}
 
 /// Derive Solana seed from mnemonic using BIP44 path
 pub fn derive_solana_seed(seed: &[u8]) -> [u8; 32] {
-    // Solana BIP44 derivation path: m/44'/501'/0'/0'
-    // 501 is Solana's coin type in BIP44
-    let path = BIP32Path::from_str("m/44'/501'/0'/0'").unwrap();
+    derive_seed_at(seed, &DerivationPath::default())
+}
+
+/// Derive the ed25519 keypair seed at `path` from a BIP39 `seed`
+pub fn derive_seed_at(seed: &[u8], path: &DerivationPath) -> [u8; 32] {
+    // SLIP10 (BIP32 for Ed25519) can't fail on an all-hardened path
+    derive_key_from_path(seed, slip10::Curve::Ed25519, &path.path)
+        .expect("derivation paths are checked to be hardened")
+        .key
+}
+
+/// PBKDF2-HMAC-SHA512 rounds BIP39 uses to stretch a mnemonic into its seed
+const BIP39_PBKDF2_ROUNDS: u32 = 2048;
+
+/// Put in front of every experimental salt, so none can equal a standard `"mnemonic"` salt
+pub const EXPERIMENTAL_SALT_TAG: &str = "solana-vanity-wallet experimental salt:";
+
+/// What salts the PBKDF2 that stretches a mnemonic into its 64-byte seed
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SeedSalt {
+    /// Standard BIP39: `"mnemonic"` followed by this passphrase, empty for most wallets
+    Bip39(String),
+    /// `--experimental-salt`: [`EXPERIMENTAL_SALT_TAG`] followed by this text. Wallets derived
+    /// this way don't import into Phantom, Solflare, `solana-keygen` or any other standard app.
+    Experimental(String),
+}
+
+impl Default for SeedSalt {
+    fn default() -> Self {
+        SeedSalt::Bip39(String::new())
+    }
+}
+
+impl SeedSalt {
+    /// The 64-byte seed for `mnemonic` under this salt
+    pub fn seed(&self, mnemonic: &Mnemonic) -> [u8; 64] {
+        match self {
+            SeedSalt::Bip39(passphrase) => mnemonic.to_seed(passphrase),
+            SeedSalt::Experimental(salt) => {
+                let mut seed = [0u8; 64];
+                pbkdf2::pbkdf2_hmac::<sha2::Sha512>(
+                    mnemonic.to_string().as_bytes(),
+                    format!("{EXPERIMENTAL_SALT_TAG}{salt}").as_bytes(),
+                    BIP39_PBKDF2_ROUNDS,
+                    &mut seed,
+                );
+                seed
+            }
+        }
+    }
+
+    /// The experimental salt text, or `None` for standard BIP39
+    pub fn experimental(&self) -> Option<&str> {
+        match self {
+            SeedSalt::Bip39(_) => None,
+            SeedSalt::Experimental(salt) => Some(salt),
+        }
+    }
+}
+
+/// Keypair seed for a BIP39 `mnemonic` and `passphrase` at `path`
+///
+/// Wallets that never set a BIP39 passphrase use `""`.
+pub fn derive_with_passphrase(
+    mnemonic: &str,
+    passphrase: &str,
+    path: &DerivationPath,
+) -> Result<[u8; 32], bip39::Error> {
+    derive_with_salt(mnemonic, &SeedSalt::Bip39(passphrase.to_string()), path)
+}
+
+/// Keypair seed for `mnemonic` stretched with `salt` and derived at `path`
+///
+/// The one place a mnemonic becomes a key, shared by generation and every check that re-derives
+/// a saved mnemonic, so the passphrase or salt can't be dropped on one side only.
+pub fn derive_with_salt(
+    mnemonic: &str,
+    salt: &SeedSalt,
+    path: &DerivationPath,
+) -> Result<[u8; 32], bip39::Error> {
+    let mnemonic = Mnemonic::parse_in(Language::English, mnemonic)?;
+    Ok(derive_seed_at(&salt.seed(&mnemonic), path))
+}
+
+/// One stage of turning a mnemonic into an address, as shown by `--explain`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DerivationStep {
+    pub label: String,
+    /// What the stage does and what goes into it, safe to show
+    pub detail: String,
+    /// The stage's output in hex, or the address for the last stage
+    pub output: String,
+    /// Whether `output` is key material that would give away the wallet
+    pub secret: bool,
+}
+
+/// Every stage from `mnemonic` to its address at `path`, with the intermediate bytes
+///
+/// Computed stage by stage (entropy, PBKDF2 seed, SLIP-10 master key, one SLIP-10 step per
+/// path level, public key, address) so each value can be checked against other tools. The last
+/// step's output is the same address [`derive_with_passphrase`] leads to.
+pub fn explain_derivation(
+    mnemonic: &str,
+    passphrase: &str,
+    path: &DerivationPath,
+) -> Result<Vec<DerivationStep>, bip39::Error> {
+    let mnemonic = Mnemonic::parse_in(Language::English, mnemonic)?;
+    let step = |label: String, detail: String, output: String, secret: bool| DerivationStep {
+        label,
+        detail,
+        output,
+        secret,
+    };
+    let entropy = mnemonic.to_entropy();
+    let seed = mnemonic.to_seed(passphrase);
+    let mut steps = vec![
+        step(
+            "Entropy".to_string(),
+            format!(
+                "{} words encode {} bits of entropy plus a checksum",
+                mnemonic.word_count(),
+                entropy.len() * 8
+            ),
+            to_hex(&entropy),
+            true,
+        ),
+        step(
+            "BIP39 seed".to_string(),
+            format!(
+                "PBKDF2-HMAC-SHA512 of the words, 2048 rounds, salt {:?}",
+                format!("mnemonic{passphrase}")
+            ),
+            to_hex(&seed),
+            true,
+        ),
+    ];
+
+    // Derive each prefix of the path in turn; SLIP-10 only goes one level at a time anyway
+    let mut key = [0u8; 32];
+    let mut level = "m".to_string();
+    for depth in 0..=path.indexes().len() {
+        if depth > 0 {
+            level.push_str(&format!("/{}'", path.indexes()[depth - 1]));
+        }
+        let partial: BIP32Path = level.parse().expect("prefixes of a valid path are valid");
+        let derived = derive_key_from_path(&seed, slip10::Curve::Ed25519, &partial)
+            .expect("derivation paths are checked to be hardened");
+        let detail = if depth == 0 {
+            "HMAC-SHA512 of the seed keyed with \"ed25519 seed\"; key, then chain code".to_string()
+        } else {
+            "HMAC-SHA512 of the parent key and hardened index, keyed with the parent chain code"
+                .to_string()
+        };
+        let mut output = to_hex(&derived.key);
+        output.push(' ');
+        output.push_str(&to_hex(&derived.chain_code));
+        steps.push(step(format!("SLIP-10 {level}"), detail, output, true));
+        key = derived.key;
+    }
+
+    let keypair = keypair_from_seed(&key).expect("32-byte seeds always make a keypair");
+    steps.push(step(
+        "Public key".to_string(),
+        format!("Ed25519 public key for the {level} key as the private seed"),
+        to_hex(&keypair.pubkey().to_bytes()),
+        false,
+    ));
+    steps.push(step(
+        "Address".to_string(),
+        "Base58 of the public key".to_string(),
+        address_of(&keypair),
+        false,
+    ));
+    Ok(steps)
+}
+
+/// Ways common Solana wallets turn a mnemonic into a keypair: a label and the BIP32 path, or
+/// `None` for the raw first 32 bytes of the BIP39 seed
+pub const DERIVATION_SCHEMES: &[(&str, Option<&str>)] = &[
+    ("Raw seed (solana-keygen without ?key=)", None),
+    ("BIP44 root", Some("m/44'/501'")),
+    (
+        "BIP44 account 0 (solana-keygen ?key=0)",
+        Some("m/44'/501'/0'"),
+    ),
+    (
+        "BIP44 account 0/0 (Phantom, Solflare, this tool)",
+        Some(SOLANA_DERIVATION_PATH),
+    ),
+    (
+        "BIP44 account 1/0 (second wallet account)",
+        Some("m/44'/501'/1'/0'"),
+    ),
+];
+
+/// The address a mnemonic gives under one of [`DERIVATION_SCHEMES`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SchemeAddress {
+    pub scheme: &'static str,
+    pub path: Option<&'static str>,
+    pub address: String,
+}
+
+/// The address `mnemonic` gives under every scheme in [`DERIVATION_SCHEMES`], for working out
+/// which one an existing wallet uses
+pub fn scheme_addresses(mnemonic: &str) -> Result<Vec<SchemeAddress>, bip39::Error> {
+    let seed = Mnemonic::parse_in(Language::English, mnemonic)?.to_seed("");
+    Ok(DERIVATION_SCHEMES
+        .iter()
+        .map(|&(scheme, path)| {
+            let key = match path {
+                Some(path) => {
+                    let path = path.parse().expect("built-in derivation paths are valid");
+                    derive_seed_at(&seed, &path)
+                }
+                None => seed[..32].try_into().expect("BIP39 seeds are 64 bytes"),
+            };
+            let keypair = keypair_from_seed(&key).expect("32-byte seeds always make a keypair");
+            SchemeAddress {
+                scheme,
+                path,
+                address: address_of(&keypair),
+            }
+        })
+        .collect())
+}
+
+/// The scheme in [`DERIVATION_SCHEMES`] under which `mnemonic` gives `address`, or `None` if no
+/// scheme does
+///
+/// Answers "which derivation does my wallet use" directly, given an address the wallet shows.
+pub fn detect_scheme(mnemonic: &str, address: &str) -> Result<Option<SchemeAddress>, bip39::Error> {
+    let address = address.trim();
+    Ok(scheme_addresses(mnemonic)?
+        .into_iter()
+        .find(|entry| entry.address == address))
+}
+
+/// Most accounts one `--derive-only-account-range` may list
+pub const MAX_ACCOUNT_RANGE: u32 = 1000;
+
+/// Accounts `start` up to but not including `end`, written `START..END`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AccountRange {
+    pub start: u32,
+    pub end: u32,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AccountRangeError {
+    /// Not of the form `START..END`
+    Malformed(String),
+    /// `END` isn't past `START`
+    Empty,
+    /// More accounts than [`MAX_ACCOUNT_RANGE`]
+    TooLarge(u64),
+    /// `END` goes past the last hardened account index
+    OutOfRange(u32),
+}
+
+impl std::fmt::Display for AccountRangeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AccountRangeError::Malformed(text) => {
+                write!(f, "'{text}' is not an account range like 0..10")
+            }
+            AccountRangeError::Empty => write!(f, "the range has no accounts in it"),
+            AccountRangeError::TooLarge(len) => write!(
+                f,
+                "{len} accounts is more than the {MAX_ACCOUNT_RANGE} allowed at once"
+            ),
+            AccountRangeError::OutOfRange(end) => write!(
+                f,
+                "account {} is outside the hardened range 0-{}",
+                end - 1,
+                slip10::HARDEND - 1
+            ),
+        }
+    }
+}
+
+impl std::error::Error for AccountRangeError {}
+
+impl FromStr for AccountRange {
+    type Err = AccountRangeError;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let malformed = || AccountRangeError::Malformed(text.to_string());
+        let (start, end) = text.split_once("..").ok_or_else(malformed)?;
+        let start: u32 = start.trim().parse().map_err(|_| malformed())?;
+        let end: u32 = end.trim().parse().map_err(|_| malformed())?;
+        if end <= start {
+            return Err(AccountRangeError::Empty);
+        }
+        let len = u64::from(end - start);
+        if len > u64::from(MAX_ACCOUNT_RANGE) {
+            return Err(AccountRangeError::TooLarge(len));
+        }
+        if end > slip10::HARDEND {
+            return Err(AccountRangeError::OutOfRange(end));
+        }
+        Ok(AccountRange { start, end })
+    }
+}
+
+/// One account's address, from [`account_addresses`]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct AccountAddress {
+    pub account: u32,
+    pub derivation_path: String,
+    pub address: String,
+}
+
+/// The address of every account in `range` for `mnemonic`, at [`DerivationPath::for_account`]
+///
+/// The BIP39 seed is stretched once and each account is a cheap SLIP-10 step from it, so a full
+/// range costs about as much as one mnemonic wallet.
+pub fn account_addresses(
+    mnemonic: &str,
+    range: AccountRange,
+) -> Result<Vec<AccountAddress>, bip39::Error> {
+    let seed = SeedSalt::default().seed(&Mnemonic::parse_in(Language::English, mnemonic)?);
+    Ok((range.start..range.end)
+        .map(|account| {
+            let path = DerivationPath::for_account(account)
+                .expect("account ranges stay within the hardened indexes");
+            let keypair = keypair_from_seed(&derive_seed_at(&seed, &path))
+                .expect("32-byte seeds always make a keypair");
+            AccountAddress {
+                account,
+                derivation_path: path.to_string(),
+                address: address_of(&keypair),
+            }
+        })
+        .collect())
+}
+
+/// Wallet apps a mnemonic can be generated for with `--target-wallet`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TargetWallet {
+    Phantom,
+    Solflare,
+    Backpack,
+}
+
+/// Each wallet app with its name, the path its first account is derived at, and how to import
+///
+/// The apps agree on the path. `solana-keygen` isn't one of them: a bare `prompt://` takes the
+/// raw BIP39 seed, which no derivation path gives, so it only finds these wallets with `?key=0/0`.
+pub const TARGET_WALLETS: &[(TargetWallet, &str, &str, &str)] = &[
+    (
+        TargetWallet::Phantom,
+        "Phantom",
+        SOLANA_DERIVATION_PATH,
+        "Add / Connect Wallet > Import Recovery Phrase; the address is the first account",
+    ),
+    (
+        TargetWallet::Solflare,
+        "Solflare",
+        SOLANA_DERIVATION_PATH,
+        "Access existing wallet > Recovery phrase, and keep the m/44'/501'/0'/0' derivation",
+    ),
+    (
+        TargetWallet::Backpack,
+        "Backpack",
+        SOLANA_DERIVATION_PATH,
+        "Import Wallet > Solana > Secret recovery phrase; the address is the first account",
+    ),
+];
+
+impl TargetWallet {
+    fn entry(self) -> &'static (TargetWallet, &'static str, &'static str, &'static str) {
+        TARGET_WALLETS
+            .iter()
+            .find(|entry| entry.0 == self)
+            .expect("every target wallet has an entry")
+    }
+
+    /// The app's own name for display
+    pub fn name(self) -> &'static str {
+        self.entry().1
+    }
+
+    /// The path the app derives its first account at
+    pub fn derivation_path(self) -> DerivationPath {
+        self.entry()
+            .2
+            .parse()
+            .expect("built-in derivation paths are valid")
+    }
+
+    /// How to import the mnemonic so the app shows the generated address
+    pub fn import_hint(self) -> &'static str {
+        self.entry().3
+    }
+}
+
+impl std::fmt::Display for TargetWallet {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for TargetWallet {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "phantom" => Ok(TargetWallet::Phantom),
+            "solflare" => Ok(TargetWallet::Solflare),
+            "backpack" => Ok(TargetWallet::Backpack),
+            "cli" | "solana-keygen" => Err(
+                "solana-keygen's bare prompt:// uses the raw seed, which a search can't target; \
+                 any of these wallets recovers there with 'prompt://?key=0/0'"
+                    .to_string(),
+            ),
+            other => Err(format!(
+                "'{other}' is not one of phantom, solflare or backpack"
+            )),
+        }
+    }
+}
+
+/// A seed could not be turned into an ed25519 keypair
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum KeygenError {
+    InvalidSeed(String),
+}
+
+impl std::fmt::Display for KeygenError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            KeygenError::InvalidSeed(msg) => write!(f, "invalid keypair seed: {msg}"),
+        }
+    }
+}
+
+impl std::error::Error for KeygenError {}
+
+/// Keypair for an ed25519 seed; seeds shorter than 32 bytes are rejected
+pub fn keypair_from_seed(seed: &[u8]) -> Result<Keypair, KeygenError> {
+    Keypair::from_seed(seed).map_err(|err| KeygenError::InvalidSeed(err.to_string()))
+}
+
+/// The 32-byte ed25519 seed behind `keypair`, which alone rebuilds it
+///
+/// `Keypair::to_bytes()` gives 64 bytes, the seed followed by the public key; that's what the
+/// `Secret Key` and keypair JSON hold, and what Phantom and `solana-keygen` import. Tools that ask
+/// for a 32-byte private key want just this seed, and reject the 64-byte form.
+pub fn secret_seed_bytes(keypair: &Keypair) -> [u8; 32] {
+    *keypair.secret_bytes()
+}
+
+/// Public key bytes for an ed25519 seed
+///
+/// Uses ed25519-dalek 1.x through `solana-sdk` by default. The `dalek2` feature switches to
+/// ed25519-dalek 2.x, which is faster for this step (see `benches/keygen.rs`). Both produce
+/// identical keys, so only the search speed changes.
+pub fn pubkey_from_seed(seed: &[u8]) -> Result<[u8; 32], KeygenError> {
+    #[cfg(feature = "dalek2")]
+    {
+        let seed: &[u8; 32] = seed
+            .get(..32)
+            .and_then(|bytes| bytes.try_into().ok())
+            .ok_or_else(|| {
+                KeygenError::InvalidSeed(format!("need 32 bytes, got {}", seed.len()))
+            })?;
+        Ok(ed25519_dalek::SigningKey::from_bytes(seed)
+            .verifying_key()
+            .to_bytes())
+    }
+    #[cfg(not(feature = "dalek2"))]
+    {
+        Ok(keypair_from_seed(seed)?.pubkey().to_bytes())
+    }
+}
+
+/// A fresh 12-word English mnemonic from 16 bytes of `rng`
+fn random_mnemonic(rng: &mut dyn RngCore) -> String {
+    let mut entropy = [0u8; 16];
+    rng.fill_bytes(&mut entropy);
+    Mnemonic::from_entropy_in(Language::English, &entropy)
+        .expect("16 bytes is valid BIP39 entropy")
+        .to_string()
+}
+
+/// Random 32-byte keypair seed, plus the mnemonic it was derived from in mnemonic mode
+fn random_keypair_seed(
+    rng: &mut dyn RngCore,
+    with_mnemonic: bool,
+    path: &DerivationPath,
+    salt: &SeedSalt,
+) -> (Option<String>, [u8; 32]) {
+    if with_mnemonic {
+        // Generate mnemonic and derive keypair (compatible with wallets), the same way a saved
+        // mnemonic is re-derived
+        let mnemonic = random_mnemonic(rng);
+        let seed = derive_with_salt(&mnemonic, salt, path).expect("generated mnemonics parse");
+        (Some(mnemonic), seed)
+    } else {
+        // Fast mode: Generate keypair directly from random seed
+        let mut seed = [0u8; 32];
+        rng.fill_bytes(&mut seed);
+        (None, seed)
+    }
+}
+
+/// Time spent in each step of making a wallet, summed over a profiling run
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct KeygenProfile {
+    pub iterations: u64,
+    pub with_mnemonic: bool,
+    /// Drawing random bytes
+    pub entropy: Duration,
+    /// Building the BIP39 mnemonic from the entropy
+    pub mnemonic: Duration,
+    /// PBKDF2 stretching of the mnemonic into the 64-byte BIP39 seed
+    pub seed: Duration,
+    /// SLIP10 derivation down the BIP32 path
+    pub derivation: Duration,
+    /// Ed25519 public key from the keypair seed
+    pub public_key: Duration,
+    /// Base58 encoding of the public key
+    pub encoding: Duration,
+}
+
+impl KeygenProfile {
+    /// Labelled stage timings in pipeline order; the mnemonic-only stages are left out in fast mode
+    pub fn stages(&self) -> Vec<(&'static str, Duration)> {
+        let mut stages = vec![("Entropy", self.entropy)];
+        if self.with_mnemonic {
+            stages.push(("Mnemonic", self.mnemonic));
+            stages.push(("PBKDF2 seed", self.seed));
+            stages.push(("SLIP10 derivation", self.derivation));
+        }
+        stages.push(("Public key", self.public_key));
+        stages.push(("Base58 encoding", self.encoding));
+        stages
+    }
+
+    /// Time across all stages
+    pub fn total(&self) -> Duration {
+        self.stages().iter().map(|(_, time)| *time).sum()
+    }
+
+    /// Average time for one wallet
+    pub fn per_wallet(&self) -> Duration {
+        self.total().div_f64(self.iterations.max(1) as f64)
+    }
+}
+
+/// Make `iterations` wallets the way the search does, timing each step separately
+///
+/// The timers are plain [`Instant`] reads between steps, which cost tens of nanoseconds and so
+/// barely move the figures. Runs on the calling thread only.
+pub fn profile_keygen(
+    rng: &mut dyn RngCore,
+    with_mnemonic: bool,
+    path: &DerivationPath,
+    iterations: u64,
+) -> KeygenProfile {
+    let mut profile = KeygenProfile {
+        iterations,
+        with_mnemonic,
+        ..KeygenProfile::default()
+    };
+    for _ in 0..iterations {
+        let mut clock = Instant::now();
+        let mut lap = |stage: &mut Duration| {
+            let now = Instant::now();
+            *stage += now - clock;
+            clock = now;
+        };
+
+        let key_seed = if with_mnemonic {
+            let mut entropy = [0u8; 16];
+            rng.fill_bytes(&mut entropy);
+            lap(&mut profile.entropy);
+            let mnemonic = Mnemonic::from_entropy_in(Language::English, &entropy).unwrap();
+            lap(&mut profile.mnemonic);
+            let seed = mnemonic.to_seed("");
+            lap(&mut profile.seed);
+            let key_seed = derive_seed_at(&seed, path);
+            lap(&mut profile.derivation);
+            key_seed
+        } else {
+            let mut key_seed = [0u8; 32];
+            rng.fill_bytes(&mut key_seed);
+            lap(&mut profile.entropy);
+            key_seed
+        };
+        let pubkey = pubkey_from_seed(&key_seed).expect("32-byte seeds always make a keypair");
+        lap(&mut profile.public_key);
+        std::hint::black_box(encode_address(&pubkey));
+        lap(&mut profile.encoding);
+    }
+    profile
+}
+
+/// `mnemonic` one word per line with its 1-based position ("1. abandon"), for writing down by hand
+pub fn numbered_mnemonic(mnemonic: &str) -> String {
+    mnemonic
+        .split_whitespace()
+        .enumerate()
+        .map(|(index, word)| format!("{}. {word}\n", index + 1))
+        .collect()
+}
+
+/// Hex encoding of the BIP39 entropy behind an English `mnemonic`
+///
+/// Lets auditors check the mnemonic → entropy → seed chain with independent tools. This is as
+/// sensitive as the mnemonic itself.
+pub fn mnemonic_entropy_hex(mnemonic: &str) -> Result<String, bip39::Error> {
+    let mnemonic = Mnemonic::parse_in(Language::English, mnemonic)?;
+    Ok(to_hex(&mnemonic.to_entropy()))
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{b:02x}")).collect()
+}
+
+/// Generate a keypair with optional mnemonic
+pub fn generate_keypair(with_mnemonic: bool) -> Result<(Option<String>, Keypair), KeygenError> {
+    generate_keypair_from(&mut OsRng, with_mnemonic, &DerivationPath::default())
+}
+
+/// Generate a keypair with optional mnemonic, drawing entropy from `rng`
+///
+/// In mnemonic mode the keypair is derived at `path`.
+pub fn generate_keypair_from(
+    rng: &mut dyn RngCore,
+    with_mnemonic: bool,
+    path: &DerivationPath,
+) -> Result<(Option<String>, Keypair), KeygenError> {
+    let (mnemonic, seed) = if with_mnemonic {
+        let mnemonic = random_mnemonic(rng);
+        // No BIP39 passphrase, derived exactly as restoring the mnemonic would
+        let seed = derive_with_passphrase(&mnemonic, "", path).expect("generated mnemonics parse");
+        (Some(mnemonic), seed)
+    } else {
+        random_keypair_seed(rng, false, path, &SeedSalt::default())
+    };
+    Ok((mnemonic, keypair_from_seed(&seed)?))
+}
+
+/// Generate `n` keypairs in parallel with no vanity constraint, e.g. to pre-fill a wallet pool
+///
+/// Each keypair comes from its own draw of OS entropy on rayon's thread pool. Nothing checks
+/// that they're distinct; two equal 256-bit seeds are as likely as guessing a key outright.
+pub fn generate_many(n: usize, with_mnemonic: bool) -> Vec<(Option<String>, Keypair)> {
+    (0..n)
+        .into_par_iter()
+        .map(|_| generate_keypair(with_mnemonic).expect("32-byte seeds always make a keypair"))
+        .collect()
+}
+
+/// Base58 address of `keypair`, as shown by wallets and explorers
+pub fn address_of(keypair: &Keypair) -> String {
+    encode_address(&keypair.pubkey().to_bytes())
+}
+
+/// Base58 address for raw public key bytes; the one place addresses are encoded
+pub(crate) fn encode_address(pubkey: &[u8; 32]) -> String {
+    bs58::encode(pubkey).into_string()
+}
+
+/// Check that `keypair` really owns the Base58 public key `pubkey_b58`
+pub fn keypair_matches_pubkey(keypair: &Keypair, pubkey_b58: &str) -> bool {
+    address_of(keypair) == pubkey_b58
+}
+
+/// Why a prefix can't appear in a Base58 address
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PrefixError {
+    Empty,
+    /// Multibyte input, such as emoji or accented letters
+    NonAscii(char),
+    /// Control characters such as tabs or newlines, usually pasted in by accident
+    ControlChar(char),
+    /// ASCII that Base58 leaves out, such as 0, O, I, l or punctuation
+    InvalidChar(char),
+    /// More characters than any address has, so the search could never end
+    TooLong(usize),
+}
+
+impl std::fmt::Display for PrefixError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PrefixError::Empty => write!(f, "there are no characters to match"),
+            PrefixError::NonAscii(c) => write!(
+                f,
+                "'{}' (U+{:04X}) is not plain ASCII; addresses only use Base58 letters and digits",
+                c.escape_debug(),
+                *c as u32
+            ),
+            PrefixError::ControlChar(c) => write!(
+                f,
+                "'{}' (U+{:04X}) is a control character; addresses only use Base58 letters and digits",
+                c.escape_debug(),
+                *c as u32
+            ),
+            PrefixError::InvalidChar(c) => write!(f, "'{c}' is not a Base58 character"),
+            PrefixError::TooLong(len) => write!(
+                f,
+                "{len} characters is longer than any address ({MAX_ADDRESS_LEN} at most), so it \
+                 can never match"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for PrefixError {}
+
+/// Longest Base58 encoding of a 32-byte public key
+pub const MAX_ADDRESS_LEN: usize = 44;
+
+/// Check that every character of `prefix` can appear in a Base58 address, and that it fits in one
+pub fn validate_prefix(prefix: &str) -> Result<(), PrefixError> {
+    validate_base58_chars(prefix)?;
+    check_fits_address(prefix)
+}
+
+/// The character checks of [`validate_prefix`] without the length limit, for character sets
+fn validate_base58_chars(text: &str) -> Result<(), PrefixError> {
+    // Base58 alphabet: 123456789ABCDEFGHJKLMNPQRSTUVWXYZabcdefghijkmnopqrstuvwxyz
+    // Notable exclusions: 0, O, I, l (to avoid confusion)
+    const BASE58_ALPHABET: &str = "123456789ABCDEFGHJKLMNPQRSTUVWXYZabcdefghijkmnopqrstuvwxyz";
+
+    if text.is_empty() {
+        return Err(PrefixError::Empty);
+    }
+
+    // Reject control and non-ASCII characters first so later byte-based logic never sees them
+    if let Some(c) = text.chars().find(|c| c.is_control()) {
+        return Err(PrefixError::ControlChar(c));
+    }
+    if let Some(c) = text.chars().find(|c| !c.is_ascii()) {
+        return Err(PrefixError::NonAscii(c));
+    }
+
+    if let Some(c) = text.chars().find(|c| !BASE58_ALPHABET.contains(*c)) {
+        return Err(PrefixError::InvalidChar(c));
+    }
+    Ok(())
+}
+
+fn check_fits_address(text: &str) -> Result<(), PrefixError> {
+    match text.len() {
+        len if len > MAX_ADDRESS_LEN => Err(PrefixError::TooLong(len)),
+        _ => Ok(()),
+    }
+}
+
+pub fn is_valid_base58_prefix(prefix: &str) -> bool {
+    validate_prefix(prefix).is_ok()
+}
+
+/// The first `n` characters of `text`, never splitting a multibyte character
+pub fn leading_chars(text: &str, n: usize) -> &str {
+    match text.char_indices().nth(n) {
+        Some((index, _)) => &text[..index],
+        None => text,
+    }
+}
+
+/// Characters of the public key that start every result file name
+pub const FILE_NAME_KEY_CHARS: usize = 10;
+
+/// File name for a wallet's output, e.g. `BbGW5Yqtsa_output.json` for suffix `output.json`
+///
+/// Takes whole characters, so an unusual or very short key can't split a character or panic.
+pub fn wallet_file_name(public_key: &str, suffix: &str) -> String {
+    let stem = NameTemplate::default().render(&NameFields::new(public_key, None));
+    format!("{stem}_{suffix}")
+}
+
+/// What a [`NameTemplate`] can put in a file name
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NameFields<'a> {
+    pub public_key: &'a str,
+    /// The text searched for, if it was a plain Base58 prefix
+    pub prefix: Option<&'a str>,
+    pub date: chrono::NaiveDate,
+}
+
+impl<'a> NameFields<'a> {
+    /// Fields for `public_key` found today (UTC)
+    pub fn new(public_key: &'a str, prefix: Option<&'a str>) -> Self {
+        NameFields {
+            public_key,
+            prefix,
+            date: chrono::Utc::now().date_naive(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum NamePart {
+    Literal(String),
+    Prefix,
+    Date,
+    /// The first `n` characters of the public key, or all of it
+    PublicKey(Option<usize>),
+}
+
+/// File name stem with placeholders, set by `--name-template`, e.g. `{prefix}-{date}-{pubkey8}`
+///
+/// Placeholders are `{prefix}`, `{date}` (UTC, `YYYY-MM-DD`), `{pubkey}` and `{pubkeyN}` for the
+/// first N characters of the key. Everything else must be letters, digits, `-`, `_` or `.`, so
+/// every expansion is a plain file name on any platform. The default is `{pubkey10}`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NameTemplate {
+    parts: Vec<NamePart>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum NameTemplateError {
+    Empty,
+    /// A `{...}` that isn't one of the known placeholders
+    UnknownPlaceholder(String),
+    /// A `{` without its `}`, or a stray `}`
+    Unbalanced,
+    /// A character that isn't safe in file names on every platform, such as `/` or `:`
+    UnsafeChar(char),
+    /// Names starting with `.` are hidden, and `.` or `..` alone aren't file names at all
+    LeadingDot,
+}
+
+impl std::fmt::Display for NameTemplateError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            NameTemplateError::Empty => write!(f, "the template is empty"),
+            NameTemplateError::UnknownPlaceholder(name) => write!(
+                f,
+                "unknown placeholder {{{name}}}; use {{prefix}}, {{date}}, {{pubkey}} or \
+                 {{pubkeyN}}"
+            ),
+            NameTemplateError::Unbalanced => write!(f, "unbalanced {{ or }}"),
+            NameTemplateError::UnsafeChar(c) => write!(
+                f,
+                "'{}' isn't allowed in file names; use letters, digits, -, _ or .",
+                c.escape_debug()
+            ),
+            NameTemplateError::LeadingDot => write!(f, "file names can't start with '.'"),
+        }
+    }
+}
+
+impl std::error::Error for NameTemplateError {}
+
+impl FromStr for NameTemplate {
+    type Err = NameTemplateError;
+
+    fn from_str(template: &str) -> Result<Self, Self::Err> {
+        if template.is_empty() {
+            return Err(NameTemplateError::Empty);
+        }
+        if template.starts_with('.') {
+            return Err(NameTemplateError::LeadingDot);
+        }
+        let mut parts = Vec::new();
+        let mut rest = template;
+        while !rest.is_empty() {
+            if let Some(after) = rest.strip_prefix('{') {
+                let (name, tail) = after.split_once('}').ok_or(NameTemplateError::Unbalanced)?;
+                parts.push(match name {
+                    "prefix" => NamePart::Prefix,
+                    "date" => NamePart::Date,
+                    "pubkey" => NamePart::PublicKey(None),
+                    _ => match name.strip_prefix("pubkey").map(str::parse::<usize>) {
+                        Some(Ok(len)) if len > 0 => NamePart::PublicKey(Some(len)),
+                        _ => return Err(NameTemplateError::UnknownPlaceholder(name.to_string())),
+                    },
+                });
+                rest = tail;
+            } else {
+                let end = rest.find('{').unwrap_or(rest.len());
+                let literal = &rest[..end];
+                if let Some(c) = literal
+                    .chars()
+                    .find(|c| !(c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.')))
+                {
+                    return Err(match c {
+                        '}' => NameTemplateError::Unbalanced,
+                        c => NameTemplateError::UnsafeChar(c),
+                    });
+                }
+                parts.push(NamePart::Literal(literal.to_string()));
+                rest = &rest[end..];
+            }
+        }
+        Ok(NameTemplate { parts })
+    }
+}
+
+impl Default for NameTemplate {
+    /// `{pubkey10}`, the first [`FILE_NAME_KEY_CHARS`] characters of the key
+    fn default() -> Self {
+        NameTemplate {
+            parts: vec![NamePart::PublicKey(Some(FILE_NAME_KEY_CHARS))],
+        }
+    }
+}
+
+impl NameTemplate {
+    /// The file name stem for `fields`, or `wallet` if everything in it came out empty
+    pub fn render(&self, fields: &NameFields) -> String {
+        let mut stem = String::new();
+        for part in &self.parts {
+            match part {
+                NamePart::Literal(text) => stem.push_str(text),
+                // Only plain Base58 prefixes are passed in, but never let one add a separator
+                NamePart::Prefix => stem.extend(
+                    fields
+                        .prefix
+                        .unwrap_or_default()
+                        .chars()
+                        .filter(char::is_ascii_alphanumeric),
+                ),
+                NamePart::Date => stem.push_str(&fields.date.format("%Y-%m-%d").to_string()),
+                NamePart::PublicKey(None) => stem.push_str(fields.public_key),
+                NamePart::PublicKey(Some(len)) => {
+                    stem.push_str(leading_chars(fields.public_key, *len))
+                }
+            }
+        }
+        if stem.is_empty() {
+            "wallet".to_string()
+        } else {
+            stem
+        }
+    }
+}
+
+/// `stem`, or `stem-2`, `stem-3`, ... if files named `{stem}_...` already exist in `dir`
+///
+/// A template without the full key, such as `treasury` or `{prefix}-{pubkey3}`, renders the same
+/// stem for many wallets; without this each new one would silently replace the last.
+pub fn unused_stem(dir: &Path, stem: &str) -> String {
+    let taken = |candidate: &str| {
+        let start = format!("{candidate}_");
+        fs::read_dir(dir).is_ok_and(|entries| {
+            entries
+                .flatten()
+                .any(|entry| entry.file_name().to_string_lossy().starts_with(&start))
+        })
+    };
+    if !taken(stem) {
+        return stem.to_string();
+    }
+    (2u64..)
+        .map(|n| format!("{stem}-{n}"))
+        .find(|candidate| !taken(candidate))
+        .expect("some numbered stem is free")
+}
+
+/// Average candidates needed to match `literal` at fixed positions: 58^len / 2, saturating at
+/// `u128::MAX` for texts far too long to ever find
+pub(crate) fn literal_expected_iterations(literal: &str) -> u128 {
+    // u64 would overflow at 11 characters; u128 holds 58^21, and anything longer is out of reach
+    u32::try_from(literal.chars().count())
+        .ok()
+        .and_then(|len| 58u128.checked_pow(len))
+        .map_or(u128::MAX, |odds| odds / 2)
+}
+
+/// Chance that a random wallet's Base58 address starts with `prefix`
+///
+/// Addresses encode a uniform 256-bit number, so this is not simply `58^-len`: each leading `1`
+/// stands for a zero byte (1 in 256), and the first real digit is skewed because 2^256 is not a
+/// power of 58 (most addresses are 44 characters and start with `2`-`J`).
+pub fn prefix_probability(prefix: &str) -> f64 {
+    const BASE58_ALPHABET: &str = "123456789ABCDEFGHJKLMNPQRSTUVWXYZabcdefghijkmnopqrstuvwxyz";
+
+    let rest = prefix.trim_start_matches('1');
+    let zero_bytes = (prefix.len() - rest.len()) as i32;
+    if zero_bytes >= 32 {
+        return if rest.is_empty() {
+            0.5f64.powi(256)
+        } else {
+            0.0
+        };
+    }
+    let zero_bytes_probability = 0.5f64.powi(8 * zero_bytes);
+    if rest.is_empty() {
+        return zero_bytes_probability;
+    }
+
+    // After the zero bytes comes a number whose first byte is non-zero: [2^(bits-8), 2^bits)
+    let bits = 256 - 8 * zero_bytes;
+    let low = 2f64.powi(bits - 8);
+    let high = 2f64.powi(bits);
+
+    let mut value = 0.0;
+    for c in rest.chars() {
+        match BASE58_ALPHABET.find(c) {
+            Some(digit) => value = value * 58.0 + digit as f64,
+            None => return 0.0,
+        }
+    }
+
+    // Numbers whose L-digit encoding starts with `rest` form one interval per length L
+    let digits = rest.chars().count() as i32;
+    let mut covered = 0.0;
+    let mut length = digits;
+    while 58f64.powi(length - 1) < high {
+        let scale = 58f64.powi(length - digits);
+        let start = (value * scale).max(low);
+        let end = ((value + 1.0) * scale).min(high);
+        covered += (end - start).max(0.0);
+        length += 1;
+    }
+
+    zero_bytes_probability * covered / high
+}
+
+/// Difficulty of `prefix` in bits: log2 of the expected number of candidates per match
+pub fn prefix_difficulty_bits(prefix: &str) -> f64 {
+    -prefix_probability(prefix).log2()
+}
+
+/// Search power of `rate` candidates per second, in the same bits as [`prefix_difficulty_bits`]
+///
+/// A rate of `b` bits per second covers a `b`-bit prefix in about a second on average, so
+/// machines can be compared independently of the pattern being searched for.
+pub fn search_bits_per_second(rate: f64) -> f64 {
+    // Fewer than one candidate a second is no search power at all
+    if rate > 1.0 {
+        rate.log2()
+    } else {
+        0.0
+    }
+}
+
+/// How many uniform Base58 characters `bits` of difficulty amount to
+pub fn bits_as_base58_chars(bits: f64) -> f64 {
+    bits / 58f64.log2()
+}
+
+/// Everyday odds to compare a difficulty with: the most bits each one covers and what it's like
+const DIFFICULTY_REFERENCES: &[(f64, &str)] = &[
+    (7.0, "guessing a number from 1 to 100"),
+    (10.5, "guessing a 3-digit PIN"),
+    (14.0, "guessing a 4-digit PIN"),
+    (20.5, "guessing a 6-digit PIN"),
+    (24.0, "guessing a 7-digit phone number"),
+    (29.0, "winning a lottery jackpot with one ticket"),
+    (34.0, "picking one chosen person out of everyone on Earth"),
+    (
+        48.0,
+        "guessing a random 8-character password of letters and digits",
+    ),
+    (57.0, "winning two lottery jackpots in a row"),
+];
+
+/// A plain-language comparison for a difficulty of `bits`, e.g. "about as hard as guessing a
+/// 4-digit PIN (a 13-bit search)"
+pub fn difficulty_comparison(bits: f64) -> String {
+    let reference = DIFFICULTY_REFERENCES
+        .iter()
+        .find(|(max_bits, _)| bits <= *max_bits)
+        .map_or_else(
+            || "beyond practical: harder than cracking a 56-bit DES key".to_string(),
+            |(_, odds)| format!("about as hard as {odds}"),
+        );
+    format!("{reference} (a {:.0}-bit search)", bits.max(0.0))
+}
+
+/// Random addresses encoded to estimate [`address_length_distribution`]
+const LENGTH_SAMPLES: usize = 10_000;
+
+/// Share of random addresses at each Base58 length, estimated once from a fixed-seed sample
+///
+/// About 94% of addresses are 44 characters and most of the rest 43; shorter ones need a
+/// number well below 2^256 or a leading zero byte. The sample is deterministic, so every call
+/// sees the same estimate.
+pub fn address_length_distribution() -> &'static BTreeMap<usize, f64> {
+    static DISTRIBUTION: OnceLock<BTreeMap<usize, f64>> = OnceLock::new();
+    DISTRIBUTION.get_or_init(|| {
+        let mut rng = rand::rngs::StdRng::seed_from_u64(0);
+        let mut counts = BTreeMap::new();
+        for _ in 0..LENGTH_SAMPLES {
+            let mut bytes = [0u8; 32];
+            rng.fill_bytes(&mut bytes);
+            *counts.entry(encode_address(&bytes).len()).or_insert(0) += 1;
+        }
+        counts
+            .into_iter()
+            .map(|(len, count)| (len, count as f64 / LENGTH_SAMPLES as f64))
+            .collect()
+    })
+}
+
+/// Random addresses encoded to measure [`FirstCharSample::standard`]
+pub const FIRST_CHAR_SAMPLES: usize = 20_000;
+
+/// How often each character led a sample of addresses
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FirstCharSample {
+    counts: BTreeMap<char, usize>,
+    samples: usize,
+}
+
+impl FirstCharSample {
+    /// Count the first characters of `samples` addresses drawn from `sampler`
+    pub fn collect(samples: usize, mut sampler: impl FnMut() -> String) -> Self {
+        let mut counts = BTreeMap::new();
+        for _ in 0..samples {
+            if let Some(first) = sampler().chars().next() {
+                *counts.entry(first).or_insert(0) += 1;
+            }
+        }
+        FirstCharSample { counts, samples }
+    }
+
+    /// [`FIRST_CHAR_SAMPLES`] random addresses from a fixed seed, measured once
+    ///
+    /// The rarest first characters lead about 0.08% of addresses, some 16 in this sample, so
+    /// every Base58 character shows up; a threshold of 0 only catches a broken alphabet.
+    pub fn standard() -> &'static FirstCharSample {
+        static SAMPLE: OnceLock<FirstCharSample> = OnceLock::new();
+        SAMPLE.get_or_init(|| {
+            let mut rng = rand::rngs::StdRng::seed_from_u64(0);
+            FirstCharSample::collect(FIRST_CHAR_SAMPLES, || {
+                let mut bytes = [0u8; 32];
+                rng.fill_bytes(&mut bytes);
+                encode_address(&bytes)
+            })
+        })
+    }
+
+    pub fn samples(&self) -> usize {
+        self.samples
+    }
+
+    /// Number of sampled addresses that started with `c`
+    pub fn count(&self, c: char) -> usize {
+        self.counts.get(&c).copied().unwrap_or(0)
+    }
+
+    /// Share of sampled addresses that started with `c`
+    pub fn share(&self, c: char) -> f64 {
+        if self.samples == 0 {
+            return 0.0;
+        }
+        self.count(c) as f64 / self.samples as f64
+    }
+
+    /// Refuse `prefix` if at most `threshold` of the sample started with its first character
+    ///
+    /// A prefix whose first character never leads an address would search forever, so a
+    /// threshold of 0 catches exactly those.
+    pub fn check_prefix(&self, prefix: &str, threshold: f64) -> Result<(), InfeasiblePrefix> {
+        let Some(first) = prefix.chars().next() else {
+            return Ok(());
+        };
+        if self.share(first) > threshold {
+            return Ok(());
+        }
+        Err(InfeasiblePrefix {
+            first,
+            seen: self.count(first),
+            samples: self.samples,
+            threshold,
+        })
+    }
+}
+
+/// A prefix whose first character is too rare to lead an address, from
+/// [`FirstCharSample::check_prefix`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct InfeasiblePrefix {
+    pub first: char,
+    /// Sampled addresses that started with `first`
+    pub seen: usize,
+    pub samples: usize,
+    pub threshold: f64,
+}
+
+impl std::fmt::Display for InfeasiblePrefix {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.seen == 0 {
+            write!(
+                f,
+                "no address in a sample of {} started with '{}', so the search would never finish",
+                format_number(self.samples as u128),
+                self.first
+            )
+        } else {
+            write!(
+                f,
+                "only {} of {} sampled addresses started with '{}', at or below the threshold of \
+                 {}",
+                self.seen,
+                format_number(self.samples as u128),
+                self.first,
+                self.threshold
+            )
+        }
+    }
+}
+
+impl std::error::Error for InfeasiblePrefix {}
+
+/// Chance that a random wallet's Base58 address ends with `suffix`
+///
+/// Unlike a prefix, the last characters are the low digits of the number, which are uniform
+/// whatever the address length, so each costs log2(58) bits. Length only matters when the
+/// suffix would reach the skewed leading digit; the model simply counts addresses no longer than
+/// the suffix as non-matches, weighted by [`address_length_distribution`]. For any practical
+/// suffix this is `58^-len` to within the sampling error.
+pub fn suffix_probability(suffix: &str) -> f64 {
+    if suffix.is_empty() {
+        return 1.0;
+    }
+    if !is_valid_base58_prefix(suffix) {
+        return 0.0;
+    }
+    let len = suffix.chars().count();
+    let longer: f64 = address_length_distribution()
+        .range(len + 1..)
+        .map(|(_, share)| share)
+        .sum();
+    longer * 58f64.powi(-(len as i32))
+}
+
+/// Difficulty of `suffix` in bits: log2 of the expected number of candidates per match
+pub fn suffix_difficulty(suffix: &str) -> f64 {
+    -suffix_probability(suffix).log2()
+}
+
+/// Expected wall-clock time and energy in kWh to find `prefix` at `rate` candidates per second
+/// on a machine drawing `watts`
+///
+/// The power model is deliberately simple: the whole machine draws a constant `watts` for the
+/// entire search, so energy is just power times time. The time is the mean of the search, one
+/// over the chance per candidate; any single run can be much luckier or unluckier. A zero rate
+/// or an impossible prefix never finishes, giving `Duration::MAX` and infinite energy.
+pub fn cost_estimate(prefix: &str, rate: f64, watts: f64) -> (Duration, f64) {
+    let seconds = 1.0 / prefix_probability(prefix) / rate;
+    match Duration::try_from_secs_f64(seconds) {
+        Ok(duration) => (duration, watts * seconds / 3600.0 / 1000.0),
+        _ => (Duration::MAX, f64::INFINITY),
+    }
+}
+
+/// Chance that a search would have found a match within `iterations` candidates, when a match
+/// takes `expected` candidates on average
+///
+/// Uses the exponential approximation `1 - exp(-iterations / expected)`, which is exact in the
+/// limit of rare matches. A non-positive `expected` means every candidate matches.
+pub fn probability_found_by(iterations: u128, expected: f64) -> f64 {
+    if expected <= 0.0 {
+        return 1.0;
+    }
+    // exp_m1 keeps precision when only a sliver of the expected work is done
+    -(-(iterations as f64) / expected).exp_m1()
+}
+
+/// Candidates needed for a `probability` chance of at least one match, when each candidate
+/// matches with chance `match_chance`
+///
+/// Solves `1 - (1 - match_chance)^n >= probability` for n. Returns `None` if no number of
+/// candidates can get there (a zero `match_chance`, or `probability` outside `0..1`).
+pub fn iterations_for_probability(match_chance: f64, probability: f64) -> Option<u128> {
+    let valid_chance = match_chance > 0.0 && match_chance <= 1.0;
+    if !valid_chance || !(0.0..1.0).contains(&probability) {
+        return None;
+    }
+    if match_chance == 1.0 || probability == 0.0 {
+        return Some(u128::from(probability > 0.0));
+    }
+    // ln_1p keeps precision for the tiny per-candidate chances of long prefixes
+    let iterations = (-probability).ln_1p() / (-match_chance).ln_1p();
+    Some(iterations.ceil() as u128)
+}
+
+/// How many bits of difficulty each character of `prefix` adds
+///
+/// A uniform character would add log2(58) ≈ 5.86 bits; the first character and any leading
+/// `1`s differ from that. The contributions sum to [`prefix_difficulty_bits`].
+pub fn difficulty_breakdown(prefix: &str) -> Vec<(char, f64)> {
+    let mut previous_bits = 0.0;
+    prefix
+        .char_indices()
+        .map(|(index, c)| {
+            let bits = prefix_difficulty_bits(&prefix[..index + c.len_utf8()]);
+            let contribution = bits - previous_bits;
+            previous_bits = bits;
+            (c, contribution)
+        })
+        .collect()
+}
+
+/// SPL Token program, the owner of classic token accounts
+pub const TOKEN_PROGRAM_ID: Pubkey =
+    Pubkey::from_str_const("TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA");
+
+/// SPL Associated Token Account program
+pub const ASSOCIATED_TOKEN_PROGRAM_ID: Pubkey =
+    Pubkey::from_str_const("ATokenGPvbdGVxr1b2hvZbsiqW5xWH25efTNsLJA8knL");
+
+/// Derive the associated token account address `wallet` would use for `mint`
+///
+/// Mirrors `spl_associated_token_account::get_associated_token_address` without the dependency.
+pub fn associated_token_address(wallet: &Pubkey, mint: &Pubkey) -> Pubkey {
+    let seeds: [&[u8]; 3] = [wallet.as_ref(), TOKEN_PROGRAM_ID.as_ref(), mint.as_ref()];
+    Pubkey::find_program_address(&seeds, &ASSOCIATED_TOKEN_PROGRAM_ID).0
+}
+
+/// What a candidate address has to look like to count as a match
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum VanityPattern {
+    /// The address starts with the text
+    Prefix(String),
+    /// The address ends with the text
+    Suffix(String),
+    /// The address starts or ends with the text
+    EitherEnd(String),
+    /// The address starts with the mask, where `?` stands for any character
+    Mask(String),
+}
+
+/// Wildcard in a [`VanityPattern::Mask`]
+pub const MASK_WILDCARD: char = '?';
+
+/// Whether `address` starts with `mask`, treating `?` as any character
+pub fn mask_matches(address: &str, mask: &str) -> bool {
+    let mut address_chars = address.chars();
+    mask.chars().all(|m| match address_chars.next() {
+        Some(a) => m == MASK_WILDCARD || m == a,
+        None => false,
+    })
+}
+
+/// The literal characters of `mask`, which are all that make it hard to match
+pub fn mask_fixed_chars(mask: &str) -> String {
+    mask.chars().filter(|&c| c != MASK_WILDCARD).collect()
+}
+
+/// Check that `mask` has at least one literal and that every literal is Base58
+pub fn validate_mask(mask: &str) -> Result<(), PrefixError> {
+    validate_prefix(&mask_fixed_chars(mask))?;
+    // Wildcards take up address positions too
+    check_fits_address(mask)
+}
+
+impl VanityPattern {
+    pub fn matches(&self, address: &str) -> bool {
+        match self {
+            VanityPattern::Prefix(prefix) => address.starts_with(prefix.as_str()),
+            VanityPattern::Suffix(suffix) => address.ends_with(suffix.as_str()),
+            VanityPattern::EitherEnd(text) => {
+                address.starts_with(text.as_str()) || address.ends_with(text.as_str())
+            }
+            VanityPattern::Mask(mask) => mask_matches(address, mask),
+        }
+    }
+
+    /// Number of characters that have to match, the most [`Self::closeness`] can return
+    pub fn target_len(&self) -> usize {
+        match self {
+            VanityPattern::Prefix(text)
+            | VanityPattern::Suffix(text)
+            | VanityPattern::EitherEnd(text) => text.chars().count(),
+            VanityPattern::Mask(mask) => mask_fixed_chars(mask).chars().count(),
+        }
+    }
+
+    /// How many target characters `address` already has in place, for best-effort ranking
+    pub fn closeness(&self, address: &str) -> usize {
+        match self {
+            VanityPattern::Prefix(prefix) => common_prefix_len(address, prefix),
+            VanityPattern::Suffix(suffix) => common_suffix_len(address, suffix),
+            VanityPattern::EitherEnd(text) => {
+                common_prefix_len(address, text).max(common_suffix_len(address, text))
+            }
+            VanityPattern::Mask(mask) => mask
+                .chars()
+                .zip(address.chars())
+                .filter(|&(m, a)| m != MASK_WILDCARD && m == a)
+                .count(),
+        }
+    }
+
+    /// Byte ranges of `address` that satisfy the pattern, empty unless it matches
+    pub fn match_ranges(&self, address: &str) -> Vec<Range<usize>> {
+        if !self.matches(address) {
+            return Vec::new();
+        }
+        let text = match self {
+            VanityPattern::Mask(mask) => {
+                return mask
+                    .char_indices()
+                    .filter(|&(_, c)| c != MASK_WILDCARD)
+                    .map(|(i, c)| i..i + c.len_utf8())
+                    .collect();
+            }
+            VanityPattern::Prefix(text)
+            | VanityPattern::Suffix(text)
+            | VanityPattern::EitherEnd(text) => text,
+        };
+        let at_start = match self {
+            VanityPattern::Suffix(_) => false,
+            _ => address.starts_with(text.as_str()),
+        };
+        let start = if at_start {
+            0
+        } else {
+            address.len() - text.len()
+        };
+        let range = start..start + text.len();
+        vec![range]
+    }
+
+    pub fn expected_iterations(&self) -> u128 {
+        match self {
+            VanityPattern::Prefix(text) => literal_expected_iterations(text),
+            // Same convention as a prefix, but from the address-length model
+            VanityPattern::Suffix(text) => {
+                // Float-to-int casts saturate, so an impossible suffix gives u128::MAX
+                (suffix_difficulty(text).exp2() / 2.0).round() as u128
+            }
+            // Two chances per address, so roughly half the work of a strict prefix
+            VanityPattern::EitherEnd(text) => (literal_expected_iterations(text) / 2).max(1),
+            // Wildcards match anything, so only the literal characters count
+            VanityPattern::Mask(mask) => literal_expected_iterations(&mask_fixed_chars(mask)),
+        }
+    }
+}
+
+/// Decides whether a candidate address is a match
+///
+/// Built-in patterns implement this through [`VanityPattern`]; new kinds of pattern implement
+/// it directly and register a factory with [`MatcherRegistry::register`].
+pub trait Matcher: Send + Sync + std::fmt::Debug {
+    fn matches(&self, address: &str) -> bool;
+
+    /// How many target characters `address` already has in place, for best-effort ranking
+    fn closeness(&self, _address: &str) -> usize {
+        0
+    }
+
+    /// Average number of candidates per match, if it can be worked out
+    fn expected_iterations(&self) -> Option<u128> {
+        None
+    }
+
+    /// Byte ranges of a matching `address` that the pattern pinned down, for highlighting
+    fn match_ranges(&self, _address: &str) -> Vec<Range<usize>> {
+        Vec::new()
+    }
+}
+
+impl Matcher for VanityPattern {
+    fn matches(&self, address: &str) -> bool {
+        VanityPattern::matches(self, address)
+    }
+
+    fn closeness(&self, address: &str) -> usize {
+        VanityPattern::closeness(self, address)
+    }
+
+    fn expected_iterations(&self) -> Option<u128> {
+        Some(VanityPattern::expected_iterations(self))
+    }
+
+    fn match_ranges(&self, address: &str) -> Vec<Range<usize>> {
+        VanityPattern::match_ranges(self, address)
+    }
+}
+
+/// Matches addresses against a regular expression
+#[derive(Debug)]
+pub struct RegexMatcher(regex::Regex);
+
+impl Matcher for RegexMatcher {
+    fn matches(&self, address: &str) -> bool {
+        self.0.is_match(address)
+    }
+
+    fn match_ranges(&self, address: &str) -> Vec<Range<usize>> {
+        self.0
+            .find(address)
+            .map(|m| m.range())
+            .into_iter()
+            .collect()
+    }
+
+    /// Worked out for a fixed run of positions at the start and/or end, like `^Sun`, `9$` or
+    /// `^[A-C]{2}.*9$`, or a fixed run anywhere, like `Sun`; anything more involved has none
+    fn expected_iterations(&self) -> Option<u128> {
+        regex_expected_iterations(self.0.as_str())
+    }
+}
+
+/// How many Base58 characters each position of a fixed-length piece of regex accepts
+fn regex_position_sizes(hir: &Hir) -> Option<Vec<usize>> {
+    const BASE58_ALPHABET: &str = "123456789ABCDEFGHJKLMNPQRSTUVWXYZabcdefghijkmnopqrstuvwxyz";
+
+    match hir.kind() {
+        HirKind::Literal(literal) => Some(
+            literal
+                .0
+                .iter()
+                .map(|byte| usize::from(BASE58_ALPHABET.as_bytes().contains(byte)))
+                .collect(),
+        ),
+        HirKind::Class(Class::Unicode(class)) => Some(vec![BASE58_ALPHABET
+            .chars()
+            .filter(|&c| {
+                class
+                    .ranges()
+                    .iter()
+                    .any(|r| r.start() <= c && c <= r.end())
+            })
+            .count()]),
+        HirKind::Class(Class::Bytes(class)) => Some(vec![BASE58_ALPHABET
+            .bytes()
+            .filter(|&b| {
+                class
+                    .ranges()
+                    .iter()
+                    .any(|r| r.start() <= b && b <= r.end())
+            })
+            .count()]),
+        HirKind::Repetition(repetition) if repetition.max == Some(repetition.min) => {
+            Some(regex_position_sizes(&repetition.sub)?.repeat(repetition.min as usize))
+        }
+        HirKind::Capture(capture) => regex_position_sizes(&capture.sub),
+        HirKind::Concat(parts) => parts
+            .iter()
+            .map(regex_position_sizes)
+            .collect::<Option<Vec<_>>>()
+            .map(|sizes| sizes.concat()),
+        _ => None,
+    }
+}
+
+/// Whether a piece of regex is a gap like `.*` that lets any run of characters through
+fn regex_is_gap(hir: &Hir) -> bool {
+    match hir.kind() {
+        HirKind::Repetition(repetition) => {
+            repetition.min == 0
+                && repetition.max.is_none()
+                && regex_position_sizes(&repetition.sub) == Some(vec![58])
+        }
+        HirKind::Capture(capture) => regex_is_gap(&capture.sub),
+        _ => false,
+    }
+}
+
+/// Average candidates per match of `pattern`, on the same halved convention as a prefix
+///
+/// Each pinned position passes with chance `allowed / 58`. A run with no anchor gets one chance
+/// per place it could start. Regexes that don't reduce to that shape have no estimate.
+fn regex_expected_iterations(pattern: &str) -> Option<u128> {
+    let hir = regex_syntax::parse(pattern).ok()?;
+    let mut parts: &[Hir] = match hir.kind() {
+        HirKind::Concat(parts) => parts,
+        _ => std::slice::from_ref(&hir),
+    };
+    let is_look = |part: &Hir, look: Look| *part.kind() == HirKind::Look(look);
+
+    let anchored_start = parts.first().is_some_and(|part| is_look(part, Look::Start));
+    if anchored_start {
+        parts = &parts[1..];
+    }
+    let anchored_end = parts.last().is_some_and(|part| is_look(part, Look::End));
+    if anchored_end {
+        parts = &parts[..parts.len() - 1];
+    }
+
+    let mut sizes = Vec::new();
+    let mut starts = 1;
+    if anchored_start || anchored_end {
+        while let Some(run) = parts
+            .first()
+            .filter(|_| anchored_start)
+            .and_then(regex_position_sizes)
+        {
+            sizes.extend(run);
+            parts = &parts[1..];
+        }
+        while let Some(run) = parts
+            .last()
+            .filter(|_| anchored_end)
+            .and_then(regex_position_sizes)
+        {
+            sizes.extend(run);
+            parts = &parts[..parts.len() - 1];
+        }
+        // Both anchors with nothing between them would pin the address length as well
+        let has_gap = !parts.is_empty() && parts.iter().all(regex_is_gap);
+        if !has_gap && (anchored_start && anchored_end || !parts.is_empty()) {
+            return None;
+        }
+    } else {
+        sizes = regex_position_sizes(&hir)?;
+        starts = (MAX_ADDRESS_LEN + 1).saturating_sub(sizes.len()).max(1);
+    }
+
+    // An impossible position makes the odds infinite, which the cast saturates to u128::MAX
+    let odds: f64 = sizes.iter().map(|&size| 58.0 / size as f64).product();
+    Some(((odds / starts as f64 / 2.0) as u128).max(1))
+}
+
+/// Restricts chosen address positions to sets of characters, e.g. position 0 in `ABC`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CharsetMatcher {
+    /// Zero-based position and the characters allowed there, sorted by position
+    positions: Vec<(usize, Vec<char>)>,
+}
+
+impl CharsetMatcher {
+    /// Build from `position=CHARS` specs such as `0=ABC`
+    pub fn from_specs<S: AsRef<str>>(specs: &[S]) -> Result<Self, MatcherError> {
+        let mut positions: Vec<(usize, Vec<char>)> = Vec::new();
+        for spec in specs {
+            let spec = spec.as_ref();
+            let invalid = |msg: String| MatcherError::InvalidArgument(format!("'{spec}': {msg}"));
+
+            let (position, chars) = spec
+                .split_once('=')
+                .ok_or_else(|| invalid("expected POSITION=CHARS, e.g. 0=ABC".to_string()))?;
+            let position: usize = position
+                .trim()
+                .parse()
+                .map_err(|_| invalid("position must be a number".to_string()))?;
+            if position >= MAX_ADDRESS_LEN {
+                return Err(invalid(format!(
+                    "addresses are at most {MAX_ADDRESS_LEN} characters"
+                )));
+            }
+            // A set is any number of alternatives, so only its characters are checked
+            validate_base58_chars(chars).map_err(|err| invalid(err.to_string()))?;
+            if positions.iter().any(|(p, _)| *p == position) {
+                return Err(invalid(format!("position {position} is given twice")));
+            }
+
+            let mut chars: Vec<char> = chars.chars().collect();
+            chars.sort_unstable();
+            chars.dedup();
+            positions.push((position, chars));
+        }
+        if positions.is_empty() {
+            return Err(MatcherError::InvalidArgument(
+                "no positions to match".to_string(),
+            ));
+        }
+        positions.sort_unstable_by_key(|(position, _)| *position);
+        Ok(CharsetMatcher { positions })
+    }
+
+    /// Number of constrained positions
+    pub fn len(&self) -> usize {
+        self.positions.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.positions.is_empty()
+    }
+
+    /// Difficulty in bits from the allowed sets; each position passes with chance `set size / 58`
+    pub fn difficulty_bits(&self) -> f64 {
+        self.positions
+            .iter()
+            .map(|(_, allowed)| (58.0 / allowed.len() as f64).log2())
+            .sum()
+    }
+
+    fn satisfied(&self, address: &str) -> impl Iterator<Item = bool> + '_ {
+        let chars: Vec<char> = address.chars().collect();
+        self.positions.iter().map(move |(position, allowed)| {
+            chars
+                .get(*position)
+                .is_some_and(|c| allowed.binary_search(c).is_ok())
+        })
+    }
+}
+
+impl Matcher for CharsetMatcher {
+    fn matches(&self, address: &str) -> bool {
+        self.satisfied(address).all(|ok| ok)
+    }
+
+    fn closeness(&self, address: &str) -> usize {
+        self.satisfied(address).filter(|&ok| ok).count()
+    }
+
+    fn match_ranges(&self, address: &str) -> Vec<Range<usize>> {
+        if !self.matches(address) {
+            return Vec::new();
+        }
+        self.positions
+            .iter()
+            .filter_map(|(position, _)| address.char_indices().nth(*position))
+            .map(|(i, c)| i..i + c.len_utf8())
+            .collect()
+    }
+
+    /// Each position passes with chance `set size / 58`; halved like the prefix estimate
+    fn expected_iterations(&self) -> Option<u128> {
+        let odds: f64 = self
+            .positions
+            .iter()
+            .map(|(_, allowed)| 58.0 / allowed.len() as f64)
+            .product();
+        Some(((odds / 2.0) as u128).max(1))
+    }
+}
+
+/// Why a prefix in a multi-prefix search adds nothing to it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PrefixOverlap {
+    /// The same prefix was given more than once
+    Duplicate(String),
+    /// `redundant` starts with `by`, so any address matching it already matches `by`
+    Subsumed { redundant: String, by: String },
+}
+
+impl std::fmt::Display for PrefixOverlap {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PrefixOverlap::Duplicate(prefix) => write!(f, "'{prefix}' is given more than once"),
+            PrefixOverlap::Subsumed { redundant, by } => {
+                write!(
+                    f,
+                    "'{by}' subsumes '{redundant}', so '{redundant}' is redundant"
+                )
+            }
+        }
+    }
+}
+
+/// Every prefix in `prefixes` that is a repeat of, or starts with, another one
+pub fn prefix_overlaps<S: AsRef<str>>(prefixes: &[S]) -> Vec<PrefixOverlap> {
+    let mut overlaps = Vec::new();
+    for (i, prefix) in prefixes.iter().map(AsRef::as_ref).enumerate() {
+        let earlier = &prefixes[..i];
+        if earlier.iter().any(|other| other.as_ref() == prefix) {
+            overlaps.push(PrefixOverlap::Duplicate(prefix.to_string()));
+            continue;
+        }
+        // Shortest first, so `So` rather than `Sol` is named for `Sola`
+        let by = prefixes
+            .iter()
+            .map(AsRef::as_ref)
+            .filter(|other| other.len() < prefix.len() && prefix.starts_with(other))
+            .min_by_key(|other| other.len());
+        if let Some(by) = by {
+            overlaps.push(PrefixOverlap::Subsumed {
+                redundant: prefix.to_string(),
+                by: by.to_string(),
+            });
+        }
+    }
+    overlaps
+}
+
+/// `prefixes` in their original order without repeats or prefixes subsumed by a shorter one
+///
+/// What's left can't overlap, so the chance of matching any of them is simply the sum.
+pub fn dedup_prefixes<S: AsRef<str>>(prefixes: &[S]) -> Vec<String> {
+    let overlaps = prefix_overlaps(prefixes);
+    let mut kept: Vec<String> = Vec::new();
+    for prefix in prefixes.iter().map(AsRef::as_ref) {
+        let subsumed = overlaps.iter().any(
+            |overlap| matches!(overlap, PrefixOverlap::Subsumed { redundant, .. } if redundant == prefix),
+        );
+        if !subsumed && !kept.iter().any(|other| other == prefix) {
+            kept.push(prefix.to_string());
+        }
+    }
+    kept
+}
+
+/// Matches addresses starting with any one of several prefixes
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AnyPrefixMatcher {
+    prefixes: Vec<String>,
+}
+
+impl AnyPrefixMatcher {
+    /// Build from Base58 prefixes, dropping repeats and subsumed prefixes
+    pub fn new<S: AsRef<str>>(prefixes: &[S]) -> Result<Self, MatcherError> {
+        for prefix in prefixes {
+            base58_text(prefix.as_ref())?;
+        }
+        let prefixes = dedup_prefixes(prefixes);
+        if prefixes.is_empty() {
+            return Err(MatcherError::InvalidArgument(
+                "no prefixes to match".to_string(),
+            ));
+        }
+        Ok(AnyPrefixMatcher { prefixes })
+    }
+
+    /// The prefixes left after deduplication
+    pub fn prefixes(&self) -> &[String] {
+        &self.prefixes
+    }
+
+    fn matched(&self, address: &str) -> Option<&str> {
+        self.prefixes
+            .iter()
+            .find(|prefix| address.starts_with(prefix.as_str()))
+            .map(String::as_str)
+    }
+}
+
+impl Matcher for AnyPrefixMatcher {
+    fn matches(&self, address: &str) -> bool {
+        self.matched(address).is_some()
+    }
+
+    fn closeness(&self, address: &str) -> usize {
+        self.prefixes
+            .iter()
+            .map(|prefix| common_prefix_len(prefix, address))
+            .max()
+            .unwrap_or(0)
+    }
+
+    fn match_ranges(&self, address: &str) -> Vec<Range<usize>> {
+        self.matched(address)
+            .map(|prefix| 0..prefix.len())
+            .into_iter()
+            .collect()
+    }
+
+    /// The deduplicated prefixes are disjoint, so their odds add up; halved like a single prefix
+    fn expected_iterations(&self) -> Option<u128> {
+        let chance: f64 = self
+            .prefixes
+            .iter()
+            .map(|prefix| 58f64.powi(-(prefix.len() as i32)))
+            .sum();
+        Some(((1.0 / chance / 2.0) as u128).max(1))
+    }
+}
+
+/// The spellings a smart-case search accepts for `prefix`: as typed and capitalized
+///
+/// Capitalizing only touches the first letter. Repeats and spellings that aren't valid Base58
+/// (`Ice`, `lol`) are left out, in that order.
+pub fn case_variants(prefix: &str) -> Vec<String> {
+    let mut chars = prefix.chars();
+    let capitalized: String = chars
+        .next()
+        .map(|first| first.to_ascii_uppercase())
+        .into_iter()
+        .chain(chars)
+        .collect();
+    let mut variants: Vec<String> = Vec::new();
+    for variant in [prefix.to_string(), capitalized] {
+        if is_valid_base58_prefix(&variant) && !variants.contains(&variant) {
+            variants.push(variant);
+        }
+    }
+    variants
+}
+
+/// Matches addresses starting with a prefix in its typed case or capitalized
+///
+/// Narrower than a case-insensitive search: `sun` finds `sun` and `Sun` but not `sUN`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CaseVariantMatcher {
+    variants: AnyPrefixMatcher,
+}
+
+impl CaseVariantMatcher {
+    /// Build from a prefix made only of letters
+    pub fn new(prefix: &str) -> Result<Self, MatcherError> {
+        if prefix.is_empty() || !prefix.chars().all(|c| c.is_ascii_alphabetic()) {
+            return Err(MatcherError::InvalidArgument(format!(
+                "smart case needs a prefix of letters only, got '{prefix}'"
+            )));
+        }
+        let variants = case_variants(prefix);
+        if variants.is_empty() {
+            return Err(MatcherError::InvalidArgument(format!(
+                "no case variant of '{prefix}' is valid Base58"
+            )));
+        }
+        Ok(CaseVariantMatcher {
+            variants: AnyPrefixMatcher::new(&variants)?,
+        })
+    }
+
+    /// The spellings being searched for
+    pub fn variants(&self) -> &[String] {
+        self.variants.prefixes()
+    }
+
+    /// Which spelling `address` starts with, if any
+    pub fn variant_of(&self, address: &str) -> Option<&str> {
+        self.variants.matched(address)
+    }
+}
+
+impl Matcher for CaseVariantMatcher {
+    fn matches(&self, address: &str) -> bool {
+        self.variants.matches(address)
+    }
+
+    fn closeness(&self, address: &str) -> usize {
+        self.variants.closeness(address)
+    }
+
+    fn match_ranges(&self, address: &str) -> Vec<Range<usize>> {
+        self.variants.match_ranges(address)
+    }
+
+    /// Variants all have the same length, so each one adds the odds of a single prefix
+    fn expected_iterations(&self) -> Option<u128> {
+        self.variants.expected_iterations()
+    }
+}
+
+/// Whether `address` starts with `prefix`, in the same time however early they differ
+///
+/// Every byte of the prefix is compared and the differences folded together instead of
+/// branching on the first one, so the time taken doesn't tell how close a candidate came. Only
+/// the lengths, which are public, affect the timing.
+pub fn constant_time_starts_with(address: &str, prefix: &str) -> bool {
+    let address = address.as_bytes();
+    let mut diff = u8::from(address.len() < prefix.len());
+    for (index, &expected) in prefix.as_bytes().iter().enumerate() {
+        diff |= address.get(index).copied().unwrap_or(0) ^ expected;
+    }
+    // Keep the optimizer from turning the fold back into an early exit
+    std::hint::black_box(diff) == 0
+}
+
+/// Matches addresses starting with a prefix using [`constant_time_starts_with`]
+///
+/// For `--constant-time-match`. Closeness is counted the same way, over the whole prefix.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConstantTimePrefixMatcher {
+    prefix: String,
+}
+
+impl ConstantTimePrefixMatcher {
+    pub fn new(prefix: &str) -> Result<Self, MatcherError> {
+        validate_prefix(prefix).map_err(|err| MatcherError::InvalidArgument(err.to_string()))?;
+        Ok(ConstantTimePrefixMatcher {
+            prefix: prefix.to_string(),
+        })
+    }
+}
+
+impl Matcher for ConstantTimePrefixMatcher {
+    fn matches(&self, address: &str) -> bool {
+        constant_time_starts_with(address, &self.prefix)
+    }
+
+    fn closeness(&self, address: &str) -> usize {
+        let address = address.as_bytes();
+        let mut still_matching = 1usize;
+        let mut matched = 0;
+        for (index, &expected) in self.prefix.as_bytes().iter().enumerate() {
+            let same = address.get(index).is_some_and(|&actual| actual == expected);
+            still_matching &= usize::from(same);
+            matched += still_matching;
+        }
+        std::hint::black_box(matched)
+    }
+
+    fn expected_iterations(&self) -> Option<u128> {
+        Some(VanityPattern::Prefix(self.prefix.clone()).expected_iterations())
+    }
+
+    fn match_ranges(&self, address: &str) -> Vec<Range<usize>> {
+        VanityPattern::Prefix(self.prefix.clone()).match_ranges(address)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MatcherError {
+    /// No matcher is registered under this name
+    Unknown(String),
+    /// The matcher rejected its pattern argument
+    InvalidArgument(String),
+}
+
+impl std::fmt::Display for MatcherError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MatcherError::Unknown(name) => write!(f, "unknown matcher '{name}'"),
+            MatcherError::InvalidArgument(msg) => write!(f, "invalid pattern: {msg}"),
+        }
+    }
+}
+
+impl std::error::Error for MatcherError {}
+
+/// Builds a matcher from the `--pattern` argument
+pub type MatcherFactory = fn(&str) -> Result<Box<dyn Matcher>, MatcherError>;
+
+fn base58_text(arg: &str) -> Result<String, MatcherError> {
+    validate_prefix(arg).map_err(|err| MatcherError::InvalidArgument(err.to_string()))?;
+    Ok(arg.to_string())
+}
+
+fn prefix_matcher(arg: &str) -> Result<Box<dyn Matcher>, MatcherError> {
+    Ok(Box::new(VanityPattern::Prefix(base58_text(arg)?)))
+}
+
+fn suffix_matcher(arg: &str) -> Result<Box<dyn Matcher>, MatcherError> {
+    Ok(Box::new(VanityPattern::Suffix(base58_text(arg)?)))
+}
+
+fn either_end_matcher(arg: &str) -> Result<Box<dyn Matcher>, MatcherError> {
+    Ok(Box::new(VanityPattern::EitherEnd(base58_text(arg)?)))
+}
+
+fn mask_matcher(arg: &str) -> Result<Box<dyn Matcher>, MatcherError> {
+    validate_mask(arg).map_err(|err| MatcherError::InvalidArgument(err.to_string()))?;
+    Ok(Box::new(VanityPattern::Mask(arg.to_string())))
+}
+
+fn charset_matcher(arg: &str) -> Result<Box<dyn Matcher>, MatcherError> {
+    let specs: Vec<&str> = arg.split(',').collect();
+    Ok(Box::new(CharsetMatcher::from_specs(&specs)?))
+}
+
+fn any_prefix_matcher(arg: &str) -> Result<Box<dyn Matcher>, MatcherError> {
+    let prefixes: Vec<&str> = arg.split(',').map(str::trim).collect();
+    Ok(Box::new(AnyPrefixMatcher::new(&prefixes)?))
+}
+
+fn case_variant_matcher(arg: &str) -> Result<Box<dyn Matcher>, MatcherError> {
+    Ok(Box::new(CaseVariantMatcher::new(arg)?))
+}
+
+fn regex_matcher(arg: &str) -> Result<Box<dyn Matcher>, MatcherError> {
+    let regex =
+        regex::Regex::new(arg).map_err(|err| MatcherError::InvalidArgument(err.to_string()))?;
+    Ok(Box::new(RegexMatcher(regex)))
+}
+
+/// Named matcher factories, so `--matcher <NAME> --pattern <ARG>` can pick any of them
+#[derive(Debug, Clone)]
+pub struct MatcherRegistry {
+    factories: HashMap<&'static str, MatcherFactory>,
+}
+
+impl MatcherRegistry {
+    /// A registry with no matchers at all
+    pub fn empty() -> Self {
+        MatcherRegistry {
+            factories: HashMap::new(),
+        }
+    }
+
+    /// Add or replace the matcher called `name`
+    pub fn register(&mut self, name: &'static str, factory: MatcherFactory) {
+        self.factories.insert(name, factory);
+    }
+
+    /// Build the matcher called `name` for `arg`
+    pub fn build(&self, name: &str, arg: &str) -> Result<Box<dyn Matcher>, MatcherError> {
+        let factory = self
+            .factories
+            .get(name)
+            .ok_or_else(|| MatcherError::Unknown(name.to_string()))?;
+        factory(arg)
+    }
+
+    /// Registered names in alphabetical order
+    pub fn names(&self) -> Vec<&'static str> {
+        let mut names: Vec<_> = self.factories.keys().copied().collect();
+        names.sort_unstable();
+        names
+    }
+}
+
+impl Default for MatcherRegistry {
+    /// The built-in matchers: prefix, suffix, either, mask, regex, charset, any-prefix and
+    /// smart-case
+    fn default() -> Self {
+        let mut registry = MatcherRegistry::empty();
+        registry.register("prefix", prefix_matcher);
+        registry.register("suffix", suffix_matcher);
+        registry.register("either", either_end_matcher);
+        registry.register("mask", mask_matcher);
+        registry.register("regex", regex_matcher);
+        registry.register("charset", charset_matcher);
+        registry.register("any-prefix", any_prefix_matcher);
+        registry.register("smart-case", case_variant_matcher);
+        registry
+    }
+}
+
+/// A wallet whose public key satisfied the requested pattern
+pub struct VanityWallet {
+    pub mnemonic: Option<String>,
+    pub public_key: String,
+    pub keypair: Keypair,
+}
+
+impl VanityWallet {
+    /// The random input behind the wallet: the BIP39 entropy if it has a mnemonic, else the
+    /// 32-byte keypair seed
+    pub fn key_entropy(&self) -> Vec<u8> {
+        self.mnemonic
+            .as_deref()
+            .and_then(|mnemonic| Mnemonic::parse_in(Language::English, mnemonic).ok())
+            .map(|mnemonic| mnemonic.to_entropy())
+            .unwrap_or_else(|| secret_seed_bytes(&self.keypair).to_vec())
+    }
+}
+
+/// Statistics for a completed vanity search
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SearchStats {
+    pub iterations: u128,
+    pub elapsed: Duration,
+    pub rate: f64,
+    pub expected: u128,
+    pub luck_factor: f64,
+    pub threads: usize,
+    /// How many non-matching candidates came close, when the search counted them
+    pub near_misses: Option<NearMisses>,
+}
+
+impl SearchStats {
+    pub fn new(iterations: u128, elapsed: Duration, expected: u128, threads: usize) -> Self {
+        let elapsed_seconds = elapsed.as_secs_f64();
+        // Guard both ratios so a zero-length run never produces NaN or infinity
+        let rate = if elapsed_seconds > 0.0 {
+            iterations as f64 / elapsed_seconds
+        } else {
+            0.0
+        };
+        let luck_factor = if iterations > 0 {
+            expected as f64 / iterations as f64
+        } else {
+            0.0
+        };
+
+        SearchStats {
+            iterations,
+            elapsed,
+            rate,
+            expected,
+            luck_factor,
+            threads,
+            near_misses: None,
+        }
+    }
+
+    /// Whether the match took fewer iterations than statistically expected
+    pub fn is_lucky(&self) -> bool {
+        self.iterations < self.expected
+    }
+}
+
+/// Deepest near miss [`NearMisses`] counts: candidates sharing 1, 2 and 3 target characters
+pub const NEAR_MISS_DEPTH: usize = 3;
+
+/// Non-matching candidates that had the first 1, 2, ... [`NEAR_MISS_DEPTH`] target characters
+/// in place
+///
+/// Each worker counts into its own copy and the copies are merged once the search ends, so
+/// counting costs no contention between threads.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct NearMisses {
+    shared: [u64; NEAR_MISS_DEPTH],
+}
+
+impl NearMisses {
+    /// Count a candidate with `matched` target characters in place
+    pub fn record(&mut self, matched: usize) {
+        for count in self.shared.iter_mut().take(matched) {
+            *count += 1;
+        }
+    }
+
+    /// Add the counts of `other`, e.g. another worker's
+    pub fn merge(&mut self, other: &NearMisses) {
+        for (count, theirs) in self.shared.iter_mut().zip(other.shared) {
+            *count += theirs;
+        }
+    }
+
+    /// Candidates that shared at least the first `chars` target characters
+    pub fn shared(&self, chars: usize) -> u64 {
+        chars
+            .checked_sub(1)
+            .and_then(|index| self.shared.get(index))
+            .copied()
+            .unwrap_or(0)
+    }
+
+    /// One line per depth, such as `Shared first 2 chars: 1.2K`, for the statistics section
+    pub fn histogram_lines(&self) -> Vec<String> {
+        (1..=NEAR_MISS_DEPTH)
+            .map(|chars| {
+                let plural = if chars == 1 { "char" } else { "chars" };
+                format!(
+                    "Shared first {chars} {plural}: {}",
+                    format_number(self.shared(chars).into())
+                )
+            })
+            .collect()
+    }
+}
+
+/// Iterations per second across a series of `(elapsed_seconds, total_iterations)` samples
+pub fn windowed_rate(samples: &[(f64, u64)]) -> f64 {
+    match (samples.first(), samples.last()) {
+        (Some(&(first_time, first_count)), Some(&(last_time, last_count)))
+            if last_time > first_time =>
+        {
+            last_count.saturating_sub(first_count) as f64 / (last_time - first_time)
+        }
+        _ => 0.0,
+    }
+}
+
+/// Sliding window over the most recent progress samples for a smoother live rate
+#[derive(Debug, Clone)]
+pub struct RateWindow {
+    samples: VecDeque<(f64, u64)>,
+    intervals: usize,
+}
+
+impl RateWindow {
+    /// Average over the last `intervals` sample-to-sample intervals (at least one)
+    pub fn new(intervals: usize) -> Self {
+        let intervals = intervals.max(1);
+        RateWindow {
+            samples: VecDeque::with_capacity(intervals + 1),
+            intervals,
+        }
+    }
+
+    /// Record a sample and return the rate across the current window
+    pub fn push(&mut self, elapsed_seconds: f64, total_iterations: u64) -> f64 {
+        if self.samples.len() > self.intervals {
+            self.samples.pop_front();
+        }
+        self.samples.push_back((elapsed_seconds, total_iterations));
+        windowed_rate(self.samples.make_contiguous())
+    }
+}
+
+/// Default share of the peak rate below which [`RateDropGuard`] starts counting
+pub const DEFAULT_RATE_DROP_SHARE: f64 = 0.4;
+
+/// Default time the rate has to stay below that share before [`RateDropGuard`] trips
+pub const DEFAULT_RATE_DROP_SECONDS: u64 = 30;
+
+/// Watches the live rate for a sustained fall from its peak, as when a laptop throttles
+///
+/// A single slow sample doesn't trip it: the rate has to stay under `share` of the best rate
+/// seen for a whole `sustain` before [`RateDropGuard::observe`] reports a drop.
+#[derive(Debug, Clone)]
+pub struct RateDropGuard {
+    /// Share of the peak rate below which the rate counts as dropped
+    pub share: f64,
+    /// How long the rate has to stay dropped
+    pub sustain: Duration,
+    peak: f64,
+    below_since: Option<f64>,
+}
+
+impl RateDropGuard {
+    pub fn new(share: f64, sustain: Duration) -> Self {
+        RateDropGuard {
+            share,
+            sustain,
+            peak: 0.0,
+            below_since: None,
+        }
+    }
+
+    /// Record the rate at `elapsed_seconds` and return whether it has now been low for too long
+    pub fn observe(&mut self, elapsed_seconds: f64, rate: f64) -> bool {
+        self.peak = self.peak.max(rate);
+        if rate >= self.peak * self.share {
+            self.below_since = None;
+            return false;
+        }
+        let since = *self.below_since.get_or_insert(elapsed_seconds);
+        elapsed_seconds - since >= self.sustain.as_secs_f64()
+    }
+
+    /// Best rate observed so far
+    pub fn peak(&self) -> f64 {
+        self.peak
+    }
+}
+
+/// Chance of having found a match by now past which [`GiveUpAdvisor`] speaks up
+pub const SUSPICIOUS_PROBABILITY: f64 = 0.99;
+
+/// Notices when a search has run far longer than it almost certainly should have
+///
+/// Once [`probability_found_by`] the current count passes `threshold` with no match, bad luck is
+/// a less likely explanation than a mistyped prefix or a broken RNG or derivation, so
+/// [`GiveUpAdvisor::observe`] says so, once per search.
+#[derive(Debug, Clone)]
+pub struct GiveUpAdvisor {
+    pub threshold: f64,
+    /// Candidates per match on average: twice the matcher's expected iterations
+    average: f64,
+    advised: bool,
+}
+
+impl GiveUpAdvisor {
+    /// An advisor for a matcher that reports `expected_iterations`, like the banner's figure
+    pub fn new(expected_iterations: u128, threshold: f64) -> Self {
+        GiveUpAdvisor {
+            threshold,
+            average: expected_iterations as f64 * 2.0,
+            advised: false,
+        }
+    }
+
+    /// The chance a match should have turned up within `iterations`, the first time it reaches
+    /// the threshold; `None` before then and every time after
+    pub fn observe(&mut self, iterations: u128) -> Option<f64> {
+        if self.advised {
+            return None;
+        }
+        let probability = probability_found_by(iterations, self.average);
+        self.advised = probability >= self.threshold;
+        self.advised.then_some(probability)
+    }
+}
+
+/// Human-readable duration with one decimal, e.g. `42.0s`, `1.5m`, `3.2h`, `2.0d`
+pub fn format_duration(seconds: f64) -> String {
+    if seconds < 60.0 {
+        format!("{seconds:.1}s")
+    } else if seconds < 3600.0 {
+        format!("{:.1}m", seconds / 60.0)
+    } else if seconds < 86400.0 {
+        format!("{:.1}h", seconds / 3600.0)
+    } else {
+        format!("{:.1}d", seconds / 86400.0)
+    }
+}
+
+/// `share` as a percentage with at most three decimals and no trailing zeros, e.g. `90%` or
+/// `99.9%`, so float noise like `0.07 * 100 = 7.000000000000001` never shows
+pub fn format_percent(share: f64) -> String {
+    let formatted = format!("{:.3}", share * 100.0);
+    let trimmed = formatted.trim_end_matches('0').trim_end_matches('.');
+    format!("{trimmed}%")
+}
+
+/// Human-readable count with one decimal and a K/M/B/T suffix, e.g. `1.5K`
+///
+/// Takes a `u128` so totals and difficulty figures past `u64::MAX` are formatted rather than
+/// wrapped; beyond a thousand trillion the T figure just grows.
+pub fn format_number(num: u128) -> String {
+    if num < 1_000 {
+        format!("{num}")
+    } else if num < 1_000_000 {
+        format!("{:.1}K", num as f64 / 1_000.0)
+    } else if num < 1_000_000_000 {
+        format!("{:.1}M", num as f64 / 1_000_000.0)
+    } else if num < 1_000_000_000_000 {
+        format!("{:.1}B", num as f64 / 1_000_000_000.0)
+    } else {
+        format!("{:.1}T", num as f64 / 1_000_000_000_000.0)
+    }
+}
+
+/// How search rates are displayed
+///
+/// [`RateUnit::Auto`] scales each figure like [`format_number`], so the suffix changes as the
+/// rate grows; a fixed unit always divides by the same amount, which keeps logged rates
+/// comparable from line to line.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub enum RateUnit {
+    #[default]
+    Auto,
+    /// Divide every rate by `divisor` and append `suffix`
+    Fixed { divisor: u64, suffix: String },
+}
+
+impl RateUnit {
+    /// `rate` in this unit, without the `/s`
+    ///
+    /// Raw keys are whole numbers; scaled units keep one decimal, as [`format_number`] does.
+    pub fn format(&self, rate: f64) -> String {
+        match self {
+            RateUnit::Auto => format_number(rate as u128),
+            RateUnit::Fixed { divisor: 1, suffix } => format!("{:.0}{suffix}", rate.floor()),
+            RateUnit::Fixed { divisor, suffix } => {
+                format!("{:.1}{suffix}", rate / *divisor as f64)
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RateUnitError {
+    /// Not `auto`, `keys`, `K`, `M` or `SUFFIX=DIVISOR`
+    Unknown(String),
+    /// A custom unit has to divide by at least 1
+    ZeroDivisor,
+}
+
+impl std::fmt::Display for RateUnitError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RateUnitError::Unknown(text) => write!(
+                f,
+                "unknown rate unit '{text}'; use auto, keys, K, M or SUFFIX=DIVISOR such as kH=1000"
+            ),
+            RateUnitError::ZeroDivisor => write!(f, "a custom rate unit can't divide by 0"),
+        }
+    }
+}
+
+impl std::error::Error for RateUnitError {}
+
+impl FromStr for RateUnit {
+    type Err = RateUnitError;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let fixed = |divisor, suffix: &str| RateUnit::Fixed {
+            divisor,
+            suffix: suffix.to_string(),
+        };
+        match text {
+            "auto" => return Ok(RateUnit::Auto),
+            "keys" => return Ok(fixed(1, "")),
+            "K" | "k" | "thousands" => return Ok(fixed(1_000, "K")),
+            "M" | "m" | "millions" => return Ok(fixed(1_000_000, "M")),
+            _ => {}
+        }
+        let unknown = || RateUnitError::Unknown(text.to_string());
+        let (suffix, divisor) = text.split_once('=').ok_or_else(unknown)?;
+        let divisor: u64 = divisor.trim().parse().map_err(|_| unknown())?;
+        if divisor == 0 {
+            return Err(RateUnitError::ZeroDivisor);
+        }
+        Ok(fixed(divisor, suffix.trim()))
+    }
+}
+
+/// Counters shared between the search workers and any progress reporter
+#[derive(Debug, Default)]
+pub struct SearchProgress {
+    /// Candidates drawn by this search so far. Stable Rust has no 128-bit atomic, but one search
+    /// would need centuries at a billion candidates a second to fill this; [`SearchStats`] and
+    /// everything that adds searches together count in `u128`.
+    pub iterations: AtomicU64,
+    /// Set once the search stops, whether or not it found a match
+    pub done: AtomicBool,
+    /// Candidates dropped because their seed couldn't be turned into a keypair
+    pub skipped: AtomicU64,
+    /// Apparent matches discarded because re-deriving the keypair didn't confirm them
+    pub rejected: AtomicU64,
+    /// Set when a progress reporter stopped the search because the rate fell off
+    pub rate_dropped: AtomicBool,
+    /// While set, workers stop drawing candidates until it is cleared or the search is done;
+    /// change it with [`SearchProgress::set_paused`] so the pause is left out of the clock
+    pub paused: AtomicBool,
+    /// Matches passed over because the seen set already had their address
+    pub duplicates: AtomicU64,
+    /// When the current pause began, if one is under way, and the total of earlier pauses
+    pause_clock: parking_lot::Mutex<(Option<Instant>, Duration)>,
+}
+
+impl SearchProgress {
+    /// Pause or resume the workers, keeping count of the time spent paused
+    pub fn set_paused(&self, paused: bool) {
+        let mut clock = self.pause_clock.lock();
+        match (paused, clock.0) {
+            (true, None) => clock.0 = Some(Instant::now()),
+            (false, Some(since)) => *clock = (None, clock.1 + since.elapsed()),
+            _ => {}
+        }
+        self.paused.store(paused, Ordering::Relaxed);
+    }
+
+    /// Total time spent paused, including a pause still under way
+    pub fn paused_time(&self) -> Duration {
+        let (since, total) = *self.pause_clock.lock();
+        total + since.map_or(Duration::ZERO, |since| since.elapsed())
+    }
+
+    /// Time since `start` that the search was running, for time limits, rates and ETAs
+    pub fn active_elapsed(&self, start: Instant) -> Duration {
+        start.elapsed().saturating_sub(self.paused_time())
+    }
+}
+
+/// How long to wait for a progress reporter to notice `done`; it checks once a second
+pub const REPORTER_JOIN_TIMEOUT: Duration = Duration::from_secs(3);
+
+/// Why [`join_within`] came back without the thread's result
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum JoinError {
+    /// The thread was still running when the timeout ran out; it's left detached
+    TimedOut,
+    /// The thread panicked
+    Panicked,
+}
+
+impl std::fmt::Display for JoinError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            JoinError::TimedOut => write!(f, "didn't stop in time"),
+            JoinError::Panicked => write!(f, "panicked"),
+        }
+    }
+}
+
+impl std::error::Error for JoinError {}
+
+/// Join `handle`, giving up after `timeout` so a stuck thread can't hang the caller
+pub fn join_within<T>(
+    handle: std::thread::JoinHandle<T>,
+    timeout: Duration,
+) -> Result<T, JoinError> {
+    let deadline = Instant::now() + timeout;
+    while !handle.is_finished() {
+        if Instant::now() >= deadline {
+            return Err(JoinError::TimedOut);
+        }
+        std::thread::sleep(Duration::from_millis(10));
+    }
+    handle.join().map_err(|_| JoinError::Panicked)
+}
+
+/// Run [`search_vanity`] while `reporter` shows its progress, then give the reporter up to
+/// `timeout` to stop
+///
+/// The search result comes back whatever the reporter does; one that hangs or panics only shows
+/// up in the second value, so it can't hold up or lose a found wallet.
+pub fn search_with_reporter(
+    config: &SearchConfig,
+    progress: &SearchProgress,
+    reporter: std::thread::JoinHandle<()>,
+    timeout: Duration,
+) -> (SearchResult, Result<(), JoinError>) {
+    let result = search_vanity(config, progress);
+    (result, join_within(reporter, timeout))
+}
+
+/// Everything that controls a vanity search
+#[derive(Debug, Clone)]
+pub struct SearchConfig {
+    pub pattern: Arc<dyn Matcher>,
+    pub with_mnemonic: bool,
+    pub threads: usize,
+    /// Give up after roughly this many candidates
+    pub max_iterations: Option<u64>,
+    /// Give up after roughly this much wall-clock time
+    pub max_duration: Option<Duration>,
+    /// Keep the closest candidate seen so it can be reported if no exact match turns up
+    pub track_best: bool,
+    /// Where candidate key material comes from
+    pub entropy: EntropySource,
+    /// Path mnemonic candidates are derived at
+    pub derivation_path: DerivationPath,
+    /// How mnemonic candidates are stretched into seeds; standard BIP39 unless changed
+    pub seed_salt: SeedSalt,
+    /// Keep collecting matches for a moment after the first and return the rarest of them
+    pub prefer_rarest: Option<PreferRarest>,
+    /// Count how many non-matching candidates had the first few target characters in place
+    pub collect_near_misses: bool,
+    /// Addresses earlier runs produced; a match on one of them is passed over
+    pub seen: Option<Arc<SeenSet>>,
+}
+
+/// How long to keep collecting matches after the first, and how to rank them
+#[derive(Debug, Clone)]
+pub struct PreferRarest {
+    /// Matches rank by how many of this matcher's target characters they have in place
+    pub target: Arc<dyn Matcher>,
+    pub window: Duration,
+}
+
+/// Default grace window for [`PreferRarest`]: short enough not to be noticed at the end of a run
+pub const RAREST_GRACE_WINDOW: Duration = Duration::from_millis(250);
+
+impl SearchConfig {
+    pub fn new<M: Matcher + 'static>(pattern: M, with_mnemonic: bool, threads: usize) -> Self {
+        Self::from_matcher(Arc::new(pattern), with_mnemonic, threads)
+    }
+
+    /// Like [`SearchConfig::new`], for a matcher that's already shared, such as one from the registry
+    pub fn from_matcher(pattern: Arc<dyn Matcher>, with_mnemonic: bool, threads: usize) -> Self {
+        SearchConfig {
+            pattern,
+            with_mnemonic,
+            threads,
+            max_iterations: None,
+            max_duration: None,
+            track_best: false,
+            entropy: EntropySource::default(),
+            derivation_path: DerivationPath::default(),
+            seed_salt: SeedSalt::default(),
+            prefer_rarest: None,
+            collect_near_misses: false,
+            seen: None,
+        }
+    }
+
+    /// Cap each search at `timeout` as well as any existing duration limit
+    pub fn with_timeout(self, timeout: Option<Duration>) -> Self {
+        let max_duration = match (self.max_duration, timeout) {
+            (Some(max), Some(timeout)) => Some(max.min(timeout)),
+            (max, timeout) => max.or(timeout),
+        };
+        SearchConfig {
+            max_duration,
+            ..self
+        }
+    }
+}
+
+/// Number of leading characters `a` and `b` have in common
+pub fn common_prefix_len(a: &str, b: &str) -> usize {
+    a.chars().zip(b.chars()).take_while(|(x, y)| x == y).count()
+}
+
+/// How far past the requested prefix a found address happens to keep matching
+///
+/// There's no single right continuation of a prefix, so the extra characters are counted against
+/// a longer target: the one from `--extend-target` or `--prefer-rarest` when it starts with the
+/// prefix, otherwise the prefix repeated. `AAA` finding `AAAA…` or `Sun` finding `SunS…` scores
+/// one bonus character on its own; `Sol` finding `Sole…` only does against a target like `Sole`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PrefixBonus {
+    /// Leading characters that match the target, the requested ones included
+    pub matched: usize,
+    /// Of those, how many go beyond the requested prefix
+    pub bonus: usize,
+}
+
+impl PrefixBonus {
+    /// Measure `address` against `prefix` and its `extension`; `None` if it doesn't start with
+    /// `prefix` at all
+    pub fn measure(prefix: &str, address: &str, extension: Option<&str>) -> Option<Self> {
+        if prefix.is_empty() || !address.starts_with(prefix) {
+            return None;
+        }
+        let requested = prefix.chars().count();
+        let target = match extension {
+            Some(extension) if extension.starts_with(prefix) => extension.to_string(),
+            // Enough repeats to cover any address
+            _ => prefix.repeat(MAX_ADDRESS_LEN.div_ceil(requested)),
+        };
+        let matched = common_prefix_len(address, &target);
+        Some(PrefixBonus {
+            matched,
+            bonus: matched - requested,
+        })
+    }
+}
+
+impl std::fmt::Display for PrefixBonus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "matched {} chars ({} bonus)", self.matched, self.bonus)
+    }
+}
+
+/// Number of trailing characters `a` and `b` have in common
+pub fn common_suffix_len(a: &str, b: &str) -> usize {
+    a.chars()
+        .rev()
+        .zip(b.chars().rev())
+        .take_while(|(x, y)| x == y)
+        .count()
+}
+
+/// Candidate with the most target characters in place when no exact match was found
+pub struct ClosestMatch {
+    pub wallet: VanityWallet,
+    pub matched_chars: usize,
+}
+
+/// Result of a search: an exact match, or possibly the closest candidate when limits ran out
+pub struct SearchResult {
+    pub wallet: Option<VanityWallet>,
+    pub closest: Option<ClosestMatch>,
+    pub stats: SearchStats,
+}
+
+/// Seed and mnemonic of a candidate, kept so its keypair can be rebuilt later
+type Candidate = (Option<String>, [u8; 32], String);
+
+/// Matches the workers of one search report, in the order they arrived
+///
+/// Workers only ever append, under one lock, so two that match at the same moment both keep
+/// their find and neither overwrites the other. Nothing is read back until the workers are done
+/// and [`Results::into_inner`] has the storage to itself, which leaves no window for a read to
+/// race a push.
+#[derive(Debug)]
+pub struct Results<T> {
+    found: parking_lot::Mutex<Vec<T>>,
+}
+
+impl<T> Default for Results<T> {
+    fn default() -> Self {
+        Results {
+            found: parking_lot::Mutex::new(Vec::new()),
+        }
+    }
+}
+
+impl<T> Results<T> {
+    pub fn push(&self, item: T) {
+        self.found.lock().push(item);
+    }
+
+    pub fn len(&self) -> usize {
+        self.found.lock().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Every result pushed, earliest first
+    pub fn into_inner(self) -> Vec<T> {
+        self.found.into_inner()
+    }
+}
+
+/// The highest-scoring of `matches`, taking the earliest on a tie
+fn rarest<T>(matches: Vec<T>, score: impl Fn(&T) -> usize) -> Option<T> {
+    matches
+        .into_iter()
+        .rev()
+        .max_by_key(|candidate| score(candidate))
+}
+
+/// Independently confirm a claimed match before it's reported
+///
+/// The keypair rebuilt from the seed must own `public_key`, a mnemonic must derive to the seed
+/// at `config.derivation_path` the way wallet imports do, and the address must satisfy the
+/// pattern.
+fn verify_candidate((mnemonic, seed, public_key): &Candidate, config: &SearchConfig) -> bool {
+    let owns_key =
+        keypair_from_seed(seed).is_ok_and(|keypair| keypair_matches_pubkey(&keypair, public_key));
+    let derives_seed = match mnemonic {
+        Some(mnemonic) => derive_with_salt(mnemonic, &config.seed_salt, &config.derivation_path)
+            .is_ok_and(|derived| derived == *seed),
+        None => true,
+    };
+    owns_key && derives_seed && config.pattern.matches(public_key)
+}
+
+fn candidate_wallet((mnemonic, seed, public_key): Candidate) -> Result<VanityWallet, KeygenError> {
+    let keypair = keypair_from_seed(&seed)?;
+    // The search derives public keys on a fast path; make sure it agrees with the real keypair
+    debug_assert!(keypair_matches_pubkey(&keypair, &public_key));
+    Ok(VanityWallet {
+        mnemonic,
+        public_key,
+        keypair,
+    })
+}
+
+/// Base58 address for a candidate seed, or `None` after counting it as skipped if no keypair can
+/// be made from it
+fn candidate_address(seed: &[u8], progress: &SearchProgress) -> Option<String> {
+    match pubkey_from_seed(seed) {
+        Ok(pubkey) => Some(encode_address(&pubkey)),
+        // Can't happen for 32-byte seeds today, but one bad candidate shouldn't end the search
+        Err(_) => {
+            progress.skipped.fetch_add(1, Ordering::Relaxed);
+            None
+        }
+    }
+}
+
+/// Stops the whole search when the worker holding it panics, e.g. on a failed entropy device
+/// read, instead of leaving the other workers running
+struct StopOnPanic<'a>(&'a AtomicBool);
+
+impl Drop for StopOnPanic<'_> {
+    fn drop(&mut self) {
+        if std::thread::panicking() {
+            self.0.store(true, Ordering::Relaxed);
+        }
+    }
+}
+
+/// Search for a wallet whose Base58 public key matches `config.pattern`
+///
+/// Runs one worker per thread until a match is found or a limit is hit. `progress` is
+/// updated as the search runs so callers can report live statistics from another thread.
+pub fn search_vanity(config: &SearchConfig, progress: &SearchProgress) -> SearchResult {
+    let start_time = Instant::now();
+    // Every verified match; without prefer_rarest the first one ends the search, but a worker or
+    // two may finish the candidate they were on and match too
+    let matches = Results::<Candidate>::default();
+    // With prefer_rarest, the time the first match turned up
+    let first_match = OnceLock::<Instant>::new();
+    let closest = parking_lot::Mutex::new(None::<(usize, Candidate)>);
+    let near_misses = parking_lot::Mutex::new(NearMisses::default());
+
+    // Mnemonic candidates are ~100x slower, so flush (and check limits) more often
+    let batch: u64 = if config.with_mnemonic { 10 } else { 1000 };
+
+    // One long-lived worker per thread: candidates are independent, so no thread can fall behind
+    // the others and there is nothing to rebalance (see the `search` benchmark)
+    (0..config.threads).into_par_iter().for_each(|worker| {
+        let mut local_iterations = 0u64;
+        let mut local_best: Option<(usize, Candidate)> = None;
+        let mut local_near_misses = NearMisses::default();
+        let mut rng = config.entropy.worker_rng(worker);
+        let _stop_on_panic = StopOnPanic(&progress.done);
+
+        while !progress.done.load(Ordering::Relaxed) {
+            // Only the public key is needed to test a candidate; the keypair is built on a match
+            let (mnemonic, seed) = random_keypair_seed(
+                &mut *rng,
+                config.with_mnemonic,
+                &config.derivation_path,
+                &config.seed_salt,
+            );
+
+            local_iterations += 1;
+
+            if let Some(address) = candidate_address(&seed, progress) {
+                let candidate = (mnemonic, seed, address);
+
+                if config.pattern.matches(&candidate.2) {
+                    let seen = config
+                        .seen
+                        .as_ref()
+                        .is_some_and(|seen| seen.contains(&candidate.2));
+                    // An address an earlier run produced doesn't count. Otherwise rebuild the
+                    // real keypair before claiming the match, so a fast-path or derivation
+                    // bug can never advertise an address the saved key doesn't own
+                    if seen {
+                        progress.duplicates.fetch_add(1, Ordering::Relaxed);
+                    } else if !verify_candidate(&candidate, config) {
+                        progress.rejected.fetch_add(1, Ordering::Relaxed);
+                    } else if config.prefer_rarest.is_some() {
+                        // Keep going until the grace window closes, then pick among them
+                        first_match.get_or_init(Instant::now);
+                        matches.push(candidate);
+                    } else {
+                        progress.done.store(true, Ordering::Relaxed);
+                        matches.push(candidate);
+                        break;
+                    }
+                } else if config.track_best || config.collect_near_misses {
+                    let matched = config.pattern.closeness(&candidate.2);
+                    if config.collect_near_misses {
+                        local_near_misses.record(matched);
+                    }
+                    if config.track_best
+                        && local_best.as_ref().is_none_or(|(best, _)| matched > *best)
+                    {
+                        local_best = Some((matched, candidate));
+                    }
+                }
+            }
+
+            // Update global counter in batches to reduce contention
+            if local_iterations.is_multiple_of(batch) {
+                let total = progress.iterations.fetch_add(batch, Ordering::Relaxed) + batch;
+                let out_of_iterations = config.max_iterations.is_some_and(|max| total >= max);
+                let out_of_time = config
+                    .max_duration
+                    .is_some_and(|max| progress.active_elapsed(start_time) >= max);
+                if out_of_iterations || out_of_time {
+                    progress.done.store(true, Ordering::Relaxed);
+                }
+                while progress.paused.load(Ordering::Relaxed)
+                    && !progress.done.load(Ordering::Relaxed)
+                {
+                    std::thread::sleep(pause::PAUSE_POLL);
+                }
+            }
+            if let (Some(found_at), Some(prefer)) = (first_match.get(), &config.prefer_rarest) {
+                if found_at.elapsed() >= prefer.window {
+                    progress.done.store(true, Ordering::Relaxed);
+                }
+            }
+        }
+
+        progress
+            .iterations
+            .fetch_add(local_iterations % batch, Ordering::Relaxed);
+        if config.collect_near_misses {
+            near_misses.lock().merge(&local_near_misses);
+        }
+
+        if let Some((matched, candidate)) = local_best {
+            let mut closest = closest.lock();
+            if closest.as_ref().is_none_or(|(best, _)| matched > *best) {
+                *closest = Some((matched, candidate));
+            }
+        }
+    });
+
+    let build = |candidate| match candidate_wallet(candidate) {
+        Ok(wallet) => Some(wallet),
+        Err(_) => {
+            progress.skipped.fetch_add(1, Ordering::Relaxed);
+            None
+        }
+    };
+    let matches = matches.into_inner();
+    let found = match &config.prefer_rarest {
+        Some(prefer) => rarest(matches, |(_, _, public_key)| {
+            prefer.target.closeness(public_key)
+        }),
+        None => matches.into_iter().next(),
+    };
+    let wallet = found.and_then(build);
+    let closest = match wallet {
+        Some(_) => None,
+        None => closest.into_inner().and_then(|(matched_chars, candidate)| {
+            build(candidate).map(|wallet| ClosestMatch {
+                wallet,
+                matched_chars,
+            })
+        }),
+    };
+    let stats = SearchStats {
+        near_misses: config.collect_near_misses.then(|| near_misses.into_inner()),
+        ..SearchStats::new(
+            progress.iterations.load(Ordering::Relaxed).into(),
+            progress.active_elapsed(start_time),
+            config.pattern.expected_iterations().unwrap_or(0),
+            config.threads,
+        )
+    };
+
+    SearchResult {
+        wallet,
+        closest,
+        stats,
+    }
+}
+
+/// Run the full search pipeline on one thread with a fixed-seed generator
+///
+/// The same `pattern`, mode and `seed` always find the same wallet after the same number of
+/// iterations, so end-to-end tests can assert on exact results without flakiness.
+#[cfg(any(test, feature = "test-harness"))]
+pub fn search_seeded<M: Matcher + 'static>(
+    pattern: M,
+    with_mnemonic: bool,
+    seed: u64,
+) -> SearchResult {
+    let mut config = SearchConfig::new(pattern, with_mnemonic, 1);
+    config.entropy = EntropySource::Seeded(seed);
+    search_vanity(&config, &SearchProgress::default())
+}
+
+/// Candidates per second `config` achieves on this machine, measured over roughly `sample`
+pub fn measure_rate(config: &SearchConfig, sample: Duration) -> f64 {
+    // Ten leading z's is about a 2^-60 chance per candidate, so the sample never ends in a match
+    let config = SearchConfig {
+        pattern: Arc::new(VanityPattern::Prefix("z".repeat(10))),
+        max_iterations: None,
+        max_duration: Some(sample),
+        track_best: false,
+        prefer_rarest: None,
+        collect_near_misses: false,
+        ..config.clone()
+    };
+    search_vanity(&config, &SearchProgress::default())
+        .stats
+        .rate
+}
+
+/// Peak and sustained candidates per second of one configuration, from [`bench_rates`]
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct BenchResult {
+    /// `fast` or `mnemonic`
+    pub mode: String,
+    /// The kind of pattern matched, e.g. `prefix`
+    pub matcher: String,
+    /// Best rate over any one sampling window
+    pub peak_rate: f64,
+    /// Rate over the whole run
+    pub sustained_rate: f64,
+    pub iterations: u128,
+    pub elapsed_seconds: f64,
+}
+
+impl BenchResult {
+    pub fn new(with_mnemonic: bool, matcher: &str, peak_rate: f64, stats: &SearchStats) -> Self {
+        BenchResult {
+            mode: if with_mnemonic { "mnemonic" } else { "fast" }.to_string(),
+            matcher: matcher.to_string(),
+            peak_rate,
+            sustained_rate: stats.rate,
+            iterations: stats.iterations,
+            elapsed_seconds: stats.elapsed.as_secs_f64(),
+        }
+    }
+}
+
+/// How often [`bench_rates`] samples the candidate counter for the peak rate
+pub const BENCH_WINDOW: Duration = Duration::from_secs(1);
+
+/// One pattern per match type for `bench`, each ten characters long so nothing matches
+///
+/// Ten fixed characters is about a 2^-60 chance per candidate, as in [`measure_rate`].
+pub fn bench_patterns() -> Vec<(&'static str, VanityPattern)> {
+    let target = "z".repeat(10);
+    vec![
+        ("prefix", VanityPattern::Prefix(target.clone())),
+        ("suffix", VanityPattern::Suffix(target.clone())),
+        ("either-end", VanityPattern::EitherEnd(target.clone())),
+        (
+            "mask",
+            VanityPattern::Mask(target.chars().flat_map(|c| [c, MASK_WILDCARD]).collect()),
+        ),
+    ]
+}
+
+/// Best rate a running candidate count reached over any one window, for [`bench_rates`]
+#[derive(Debug)]
+struct PeakRate {
+    window: Duration,
+    /// When the current window started, and the count then
+    last: (Instant, u64),
+    peak: f64,
+}
+
+impl PeakRate {
+    fn new(window: Duration, start: Instant) -> Self {
+        PeakRate {
+            window,
+            last: (start, 0),
+            peak: 0.0,
+        }
+    }
+
+    /// Note that `count` candidates were done by `now`, closing the window if it's long enough
+    fn sample(&mut self, now: Instant, count: u64) {
+        let elapsed = now.duration_since(self.last.0);
+        if elapsed < self.window {
+            return;
+        }
+        let rate = count.saturating_sub(self.last.1) as f64 / elapsed.as_secs_f64();
+        self.peak = self.peak.max(rate);
+        self.last = (now, count);
+    }
+}
+
+/// Run `config` for `duration` and measure its peak and sustained rate
+///
+/// The candidate counter is read every `window`, and the peak is the best rate over one window;
+/// the sustained rate is the whole run's, `stats.rate`. Workers report in batches, so windows
+/// much shorter than a second make the peak noisy. Returns the peak and the run's stats.
+pub fn bench_rates(
+    config: &SearchConfig,
+    duration: Duration,
+    window: Duration,
+) -> (f64, SearchStats) {
+    let config = SearchConfig {
+        max_iterations: None,
+        max_duration: Some(duration),
+        track_best: false,
+        prefer_rarest: None,
+        collect_near_misses: false,
+        ..config.clone()
+    };
+    let progress = SearchProgress::default();
+    std::thread::scope(|scope| {
+        let search = scope.spawn(|| search_vanity(&config, &progress));
+        let mut peak = PeakRate::new(window, Instant::now());
+        while !search.is_finished() {
+            std::thread::sleep(window.min(Duration::from_millis(50)));
+            peak.sample(Instant::now(), progress.iterations.load(Ordering::Relaxed));
+        }
+        let stats = search
+            .join()
+            .expect("the benchmark search doesn't panic")
+            .stats;
+        // A run shorter than one window still has its overall rate
+        (peak.peak.max(stats.rate), stats)
+    })
+}
+
+/// Expected wall-clock time for a search checking `rate` candidates per second in total
+///
+/// `rate` is what [`measure_rate`] reports for the full search, every thread included, so the
+/// thread count is already in it. `None` when nothing is checked at all.
+pub fn estimate_wall_clock(expected_iterations: u128, rate: f64) -> Option<Duration> {
+    if rate <= 0.0 || !rate.is_finite() {
+        return None;
+    }
+    Duration::try_from_secs_f64(expected_iterations as f64 / rate).ok()
+}
+
+/// Searches expected to run longer than this ask for confirmation first
+pub const LONG_RUN_THRESHOLD: Duration = Duration::from_secs(60 * 60);
+
+/// Whether to ask before a search expected to take `estimated_seconds`
+///
+/// Only interactive sessions are asked; piped or scripted runs proceed, since nobody is there
+/// to answer and batch jobs shouldn't hang on a prompt.
+pub fn needs_confirmation(
+    estimated_seconds: f64,
+    threshold: Duration,
+    assume_yes: bool,
+    interactive: bool,
+) -> bool {
+    !assume_yes && interactive && estimated_seconds > threshold.as_secs_f64()
+}
+
+/// Whether a result's secret key and mnemonic may be printed to stdout
+///
+/// A terminal is read by the person who ran the tool; a pipe or redirect tends to end up in logs
+/// and CI output, so there the secrets stay in the result file unless explicitly allowed.
+pub fn secrets_on_stdout(stdout_is_terminal: bool, allow_secret_stdout: bool) -> bool {
+    stdout_is_terminal || allow_secret_stdout
+}
+
+/// Whether a prompt answer means yes; anything else, including an empty line, is no
+pub fn is_affirmative(answer: &str) -> bool {
+    matches!(answer.trim().to_ascii_lowercase().as_str(), "y" | "yes")
+}
+
+/// When to color terminal output
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ColorMode {
+    /// Color only when writing to a terminal and `NO_COLOR` isn't set
+    #[default]
+    Auto,
+    Always,
+    Never,
+}
+
+impl ColorMode {
+    /// Whether to emit color codes, given if stdout is a terminal and if `NO_COLOR` is set
+    pub fn enabled(self, is_terminal: bool, no_color: bool) -> bool {
+        match self {
+            ColorMode::Auto => is_terminal && !no_color,
+            ColorMode::Always => true,
+            ColorMode::Never => false,
+        }
+    }
+}
+
+impl FromStr for ColorMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "auto" => Ok(ColorMode::Auto),
+            "always" => Ok(ColorMode::Always),
+            "never" => Ok(ColorMode::Never),
+            other => Err(format!("'{other}' is not one of auto, always or never")),
+        }
+    }
+}
+
+/// How much of the startup header a search prints before it starts
+///
+/// The header is the block under the title: the pattern, mode, threads and difficulty. Progress
+/// lines, warnings and the result are printed whatever this says.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Banner {
+    Hidden,
+    Standard,
+    /// The standard header plus the per-character difficulty breakdown
+    Detailed,
+}
+
+impl Banner {
+    /// `--no-banner` hides the header even with `--verbose`, which only adds to the header
+    pub fn choose(no_banner: bool, verbose: bool) -> Self {
+        match (no_banner, verbose) {
+            (true, _) => Banner::Hidden,
+            (false, true) => Banner::Detailed,
+            (false, false) => Banner::Standard,
+        }
+    }
+
+    pub fn is_shown(self) -> bool {
+        self != Banner::Hidden
+    }
+}
+
+/// Keep searching for up to `budget` after a match, looking for an address closer to the longer
+/// target in `config.pattern` than `found`
+///
+/// Returns whichever of `found` and the best new candidate has more target characters in place,
+/// along with statistics for the extra search alone. A candidate only replaces `found` if it
+/// still satisfies `original`, the pattern `found` was searched for.
+pub fn extend_search(
+    found: VanityWallet,
+    original: &dyn Matcher,
+    config: &SearchConfig,
+    budget: Duration,
+    progress: &SearchProgress,
+) -> (ClosestMatch, SearchStats) {
+    let config = SearchConfig {
+        max_iterations: None,
+        max_duration: Some(budget),
+        track_best: true,
+        ..config.clone()
+    };
+    let baseline = config.pattern.closeness(&found.public_key);
+    let result = search_vanity(&config, progress);
+
+    let candidate = match result.wallet {
+        Some(wallet) => Some(ClosestMatch {
+            matched_chars: config.pattern.closeness(&wallet.public_key),
+            wallet,
+        }),
+        None => result.closest,
+    };
+    let found = ClosestMatch {
+        wallet: found,
+        matched_chars: baseline,
+    };
+
+    (closer_match(found, candidate, original), result.stats)
+}
+
+/// `candidate` if it has more target characters than `found` and still matches `original`
+///
+/// With an either-end target the closest candidate can match at the other end; an address ending
+/// in `nRise` is closer to `SunRise` than one starting with `Sun`, but it isn't a `Sun` address.
+fn closer_match(
+    found: ClosestMatch,
+    candidate: Option<ClosestMatch>,
+    original: &dyn Matcher,
+) -> ClosestMatch {
+    match candidate {
+        Some(candidate)
+            if candidate.matched_chars > found.matched_chars
+                && original.matches(&candidate.wallet.public_key) =>
+        {
+            candidate
+        }
+        _ => found,
+    }
+}
+
+/// A match and the statistics of the search that found it
+pub struct LuckyFind {
+    pub wallet: VanityWallet,
+    pub stats: SearchStats,
+}
+
+/// Search again and again until `budget` runs out, keeping the match with the best luck factor
+///
+/// Every find is its own search, so its luck factor compares the iterations it took with the
+/// expected count just as a single search would. Returns the luckiest find (the earliest on a
+/// tie) and the statistics of every find in order; a search cut short by the budget doesn't count.
+pub fn luckiest_search(
+    config: &SearchConfig,
+    budget: Duration,
+) -> (Option<LuckyFind>, Vec<SearchStats>) {
+    let started = Instant::now();
+    let mut best: Option<LuckyFind> = None;
+    let mut finds = Vec::new();
+    while let Some(remaining) = budget.checked_sub(started.elapsed()) {
+        let trial = config.clone().with_timeout(Some(remaining));
+        let result = search_vanity(&trial, &SearchProgress::default());
+        let Some(wallet) = result.wallet else {
+            break;
+        };
+        finds.push(result.stats);
+        if best
+            .as_ref()
+            .is_none_or(|best| result.stats.luck_factor > best.stats.luck_factor)
+        {
+            best = Some(LuckyFind {
+                wallet,
+                stats: result.stats,
+            });
+        }
+    }
+    (best, finds)
+}
+
+/// The spelling of a normalized pattern that a found address literally starts with
+///
+/// A `--smart-case` search for `sun` also accepts `Sun`, so the saved address needn't start
+/// with what was typed. Result files carry this next to the address to say why it matched.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct NormalizedMatch {
+    /// The pattern as given on the command line
+    pub requested_pattern: String,
+    /// The accepted spelling the address starts with
+    pub matched_pattern: String,
+    pub actual_address: String,
+}
+
+impl NormalizedMatch {
+    /// Which of `matcher`'s spellings of `requested` `address` starts with
+    ///
+    /// `None` means the address doesn't literally start with any of them, i.e. the search
+    /// reported a false match, and the wallet must not be saved as one.
+    pub fn for_case_variant(
+        requested: &str,
+        matcher: &CaseVariantMatcher,
+        address: &str,
+    ) -> Option<Self> {
+        let matched = matcher.variant_of(address)?;
+        address.starts_with(matched).then(|| NormalizedMatch {
+            requested_pattern: requested.to_string(),
+            matched_pattern: matched.to_string(),
+            actual_address: address.to_string(),
+        })
+    }
+}
+
+/// Version recorded in every result file, to trace a wallet to the code that derived it
+pub const TOOL_VERSION: &str = env!("CARGO_PKG_VERSION");
+
+/// Serializable form of a found wallet and the statistics of its search
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct WalletRecord {
+    pub mnemonic: Option<String>,
+    pub public_key: String,
+    pub secret_key: String,
+    pub keypair_json: Vec<u8>,
+    /// BIP32 path the keypair was derived at from the mnemonic
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub derivation_path: Option<String>,
+    /// Wallet app the mnemonic was generated for with `--target-wallet`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub target_wallet: Option<String>,
+    /// Non-standard PBKDF2 salt from `--experimental-salt`, needed to recover the mnemonic
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub experimental_salt: Option<String>,
+    /// For searches that accept more than one spelling, which one the address really starts with
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pattern_match: Option<NormalizedMatch>,
+    /// When the wallet was made, RFC 3339 in UTC; absent in files from older versions
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub generated_at: Option<String>,
+    /// Version of this tool that made the wallet; absent in files from older versions
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tool_version: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub statistics: Option<StatisticsRecord>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StatisticsRecord {
+    pub iterations: u128,
+    pub elapsed_seconds: f64,
+    pub iterations_per_second: f64,
+    pub expected_iterations: u128,
+    pub luck_factor: f64,
+    /// Worker threads the search ran on; absent in files from older versions
+    #[serde(default)]
+    pub threads: usize,
+}
+
+impl WalletRecord {
+    pub fn new(wallet: &VanityWallet, stats: &SearchStats) -> Self {
+        WalletRecord {
+            statistics: Some(StatisticsRecord {
+                iterations: stats.iterations,
+                elapsed_seconds: stats.elapsed.as_secs_f64(),
+                iterations_per_second: stats.rate,
+                expected_iterations: stats.expected,
+                luck_factor: stats.luck_factor,
+                threads: stats.threads,
+            }),
+            ..WalletRecord::from_wallet(wallet)
+        }
+    }
+
+    /// Record for a wallet that was not found by a search, e.g. from `generate`
+    pub fn from_wallet(wallet: &VanityWallet) -> Self {
+        WalletRecord {
+            mnemonic: wallet.mnemonic.clone(),
+            public_key: wallet.public_key.clone(),
+            secret_key: bs58::encode(wallet.keypair.to_bytes()).into_string(),
+            keypair_json: wallet.keypair.to_bytes().to_vec(),
+            derivation_path: None,
+            target_wallet: None,
+            experimental_salt: None,
+            pattern_match: None,
+            generated_at: Some(
+                chrono::Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Secs, true),
+            ),
+            tool_version: Some(TOOL_VERSION.to_string()),
+            statistics: None,
+        }
+    }
+
+    /// Note the path the keypair was derived at; only mnemonic wallets have one
+    pub fn with_derivation_path(self, path: &DerivationPath) -> Self {
+        WalletRecord {
+            derivation_path: self.mnemonic.as_ref().map(|_| path.to_string()),
+            ..self
+        }
+    }
+
+    /// Note the wallet app the mnemonic is meant for, if one was chosen
+    pub fn with_target_wallet(self, target: Option<TargetWallet>) -> Self {
+        WalletRecord {
+            target_wallet: target.map(|target| target.name().to_string()),
+            ..self
+        }
+    }
+
+    /// Note a non-standard salt the mnemonic was stretched with; only mnemonic wallets have one
+    pub fn with_seed_salt(self, salt: &SeedSalt) -> Self {
+        WalletRecord {
+            experimental_salt: self
+                .mnemonic
+                .as_ref()
+                .and(salt.experimental())
+                .map(str::to_string),
+            ..self
+        }
+    }
+
+    /// Note the spelling of a normalized pattern the address matched, from
+    /// [`NormalizedMatch::for_case_variant`]
+    pub fn with_pattern_match(self, pattern_match: Option<NormalizedMatch>) -> Self {
+        WalletRecord {
+            pattern_match,
+            ..self
+        }
+    }
+
+    /// The key lines of the text format, showing `public_key` in place of the real one
+    ///
+    /// `public_key` lets the console version carry color codes that stay out of the saved file.
+    pub fn details(&self, public_key: &str) -> String {
+        let mnemonic = self.mnemonic.as_deref().unwrap_or(NO_MNEMONIC_PLACEHOLDER);
+        let path_line = self
+            .derivation_path
+            .as_deref()
+            .map(|path| format!("Derivation Path: {path}\n"))
+            .unwrap_or_default();
+        let target_line = self
+            .target_wallet
+            .as_deref()
+            .map(|target| format!("Target Wallet: {target}\n"))
+            .unwrap_or_default();
+        let salt_line = self
+            .experimental_salt
+            .as_deref()
+            .map(|salt| format!("Experimental Salt: {salt}\n"))
+            .unwrap_or_default();
+        let match_lines = self
+            .pattern_match
+            .as_ref()
+            .map(|found| {
+                format!(
+                    "Requested Pattern: {}\nMatched Pattern: {}\nActual Address: {}\n",
+                    found.requested_pattern, found.matched_pattern, found.actual_address
+                )
+            })
+            .unwrap_or_default();
+        let keypair_json = self
+            .keypair_json
+            .iter()
+            .map(|b| b.to_string())
+            .collect::<Vec<_>>()
+            .join(", ");
+        let provenance: String = [
+            ("Generated At", &self.generated_at),
+            ("Tool Version", &self.tool_version),
+        ]
+        .into_iter()
+        .filter_map(|(name, value)| Some(format!("\n{name}: {}", value.as_deref()?)))
+        .collect();
+        format!(
+            "Mnemonic: {mnemonic}\n{path_line}{target_line}{salt_line}{match_lines}Public Key: {public_key}\nSecret Key: {}\nKeypair JSON: [{keypair_json}]{provenance}",
+            self.secret_key
+        )
+    }
+
+    /// Contents of the text result file, with an optional statistics section at the end
+    pub fn to_text_file(&self, stats_section: Option<&str>) -> String {
+        let mut text = format!(
+            "Solana Vanity Wallet Generated\n\
+            ==============================\n\
+            {}\n",
+            self.details(&self.public_key)
+        );
+        if let Some(stats_section) = stats_section {
+            text.push('\n');
+            text.push_str(stats_section);
+        }
+        text
+    }
+
+    /// `export` lines for the `env` format, ready for a shell to `eval`
+    ///
+    /// `SOLANA_PUBKEY` and the provenance (`SOLANA_GENERATED_AT`, `SOLANA_TOOL_VERSION`), plus
+    /// `SOLANA_SECRET` (the Base58 secret key) if `export_secret` is set. None of the values
+    /// contain quotes, so they need no escaping inside single quotes.
+    pub fn to_env_exports(&self, export_secret: bool) -> String {
+        let mut exports = format!("export SOLANA_PUBKEY='{}'\n", self.public_key);
+        for (var, value) in [
+            ("SOLANA_GENERATED_AT", &self.generated_at),
+            ("SOLANA_TOOL_VERSION", &self.tool_version),
+        ] {
+            if let Some(value) = value {
+                exports.push_str(&format!("export {var}='{value}'\n"));
+            }
+        }
+        if export_secret {
+            exports.push_str(&format!("export SOLANA_SECRET='{}'\n", self.secret_key));
+        }
+        exports
+    }
+}
+
+/// Shown in the text format's mnemonic line for fast-mode wallets
+const NO_MNEMONIC_PLACEHOLDER: &str = "[Not generated - use --with-mnemonic flag]";
+
+/// Why a saved result file failed [`verify_saved_wallet`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RecoveryError {
+    /// The file is missing a field or a field can't be parsed
+    Unreadable(String),
+    /// The stored secret key is not the keypair that was found
+    SecretMismatch,
+    /// The stored public key doesn't belong to the stored secret key
+    PublicKeyMismatch,
+    /// The stored mnemonic doesn't derive the keypair at the stored path
+    MnemonicMismatch,
+}
+
+impl std::fmt::Display for RecoveryError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RecoveryError::Unreadable(msg) => write!(f, "unreadable result file: {msg}"),
+            RecoveryError::SecretMismatch => write!(f, "the saved secret key is a different key"),
+            RecoveryError::PublicKeyMismatch => {
+                write!(f, "the saved public key doesn't match the saved secret key")
+            }
+            RecoveryError::MnemonicMismatch => {
+                write!(f, "the saved mnemonic doesn't derive the saved keypair")
+            }
+        }
+    }
+}
+
+impl std::error::Error for RecoveryError {}
+
+/// Read a result file back as a record, whether it was saved as JSON or text
+fn parse_saved_wallet(contents: &str) -> Result<WalletRecord, RecoveryError> {
+    if contents.trim_start().starts_with('{') {
+        return serde_json::from_str(contents)
+            .map_err(|err| RecoveryError::Unreadable(err.to_string()));
+    }
+
+    let field = |name: &str| {
+        contents
+            .lines()
+            .find_map(|line| line.strip_prefix(name)?.strip_prefix(": "))
+            .map(str::to_string)
+    };
+    let required = |name: &str| {
+        field(name).ok_or_else(|| RecoveryError::Unreadable(format!("no {name} line")))
+    };
+    Ok(WalletRecord {
+        mnemonic: field("Mnemonic").filter(|mnemonic| mnemonic != NO_MNEMONIC_PLACEHOLDER),
+        public_key: required("Public Key")?,
+        secret_key: required("Secret Key")?,
+        keypair_json: Vec::new(),
+        derivation_path: field("Derivation Path"),
+        target_wallet: field("Target Wallet"),
+        experimental_salt: field("Experimental Salt"),
+        pattern_match: field("Requested Pattern").and_then(|requested_pattern| {
+            Some(NormalizedMatch {
+                requested_pattern,
+                matched_pattern: field("Matched Pattern")?,
+                actual_address: field("Actual Address")?,
+            })
+        }),
+        generated_at: field("Generated At"),
+        tool_version: field("Tool Version"),
+        statistics: None,
+    })
+}
+
+/// Check that the result file `contents` recovers exactly `keypair`
+///
+/// Rebuilds the keypair from the saved secret key and, when there is one, from the saved
+/// mnemonic at the saved derivation path (the default path if none is recorded), using the
+/// crate's own derivation. Run after writing a file to catch serialization or derivation bugs
+/// before anyone relies on it.
+pub fn verify_saved_wallet(contents: &str, keypair: &Keypair) -> Result<(), RecoveryError> {
+    let record = parse_saved_wallet(contents)?;
+
+    let secret = bs58::decode(&record.secret_key)
+        .into_vec()
+        .map_err(|err| RecoveryError::Unreadable(format!("secret key: {err}")))?;
+    let saved = Keypair::try_from(secret.as_slice())
+        .map_err(|err| RecoveryError::Unreadable(format!("secret key: {err}")))?;
+    if saved.to_bytes() != keypair.to_bytes() {
+        return Err(RecoveryError::SecretMismatch);
+    }
+    if !record.keypair_json.is_empty() && record.keypair_json != keypair.to_bytes() {
+        return Err(RecoveryError::SecretMismatch);
+    }
+    if !keypair_matches_pubkey(&saved, &record.public_key) {
+        return Err(RecoveryError::PublicKeyMismatch);
+    }
+
+    if let Some(mnemonic) = &record.mnemonic {
+        let path = match &record.derivation_path {
+            Some(path) => path
+                .parse()
+                .map_err(|err| RecoveryError::Unreadable(format!("derivation path: {err}")))?,
+            None => DerivationPath::default(),
+        };
+        let salt = record
+            .experimental_salt
+            .clone()
+            .map_or_else(SeedSalt::default, SeedSalt::Experimental);
+        let derived = derive_with_salt(mnemonic, &salt, &path)
+            .map_err(|err| err.to_string())
+            .and_then(|seed| keypair_from_seed(&seed).map_err(|err| err.to_string()))
+            .map_err(|err| RecoveryError::Unreadable(format!("mnemonic: {err}")))?;
+        if derived.to_bytes() != keypair.to_bytes() {
+            return Err(RecoveryError::MnemonicMismatch);
+        }
+    }
+    Ok(())
+}
+
+/// Public keys of the wallets saved in `dir` that match `pattern`, for `--resume`
+///
+/// Reads every `*_output.txt` and `*_output.json`, whatever name template wrote it, and skips
+/// files that don't parse or hold some other pattern's wallet. A wallet saved in both formats
+/// counts once. A directory that doesn't exist yet holds none.
+pub fn saved_matches(dir: &Path, pattern: &dyn Matcher) -> io::Result<BTreeSet<String>> {
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(BTreeSet::new()),
+        Err(err) => return Err(err),
+    };
+    let mut keys = BTreeSet::new();
+    for entry in entries {
+        let path = entry?.path();
+        let is_result = path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .is_some_and(|name| name.ends_with("_output.txt") || name.ends_with("_output.json"));
+        if !is_result {
+            continue;
+        }
+        let Ok(contents) = fs::read_to_string(&path) else {
+            continue;
+        };
+        if let Ok(record) = parse_saved_wallet(&contents) {
+            if pattern.matches(&record.public_key) {
+                keys.insert(record.public_key);
+            }
+        }
+    }
+    Ok(keys)
+}
+
+/// Public keys produced by earlier runs, read from and appended to a newline-delimited file
+///
+/// Searches given one through [`SearchConfig::seen`] pass over matches it already holds, so a
+/// batch run again with the same file never reports the same address twice. The set is shared
+/// with the running search, and [`SeenSet::record`] adds each new find to it and the file.
+#[derive(Debug, Default)]
+pub struct SeenSet {
+    keys: parking_lot::RwLock<HashSet<String>>,
+}
+
+impl SeenSet {
+    /// Read the addresses in `path`, one per line; a file that doesn't exist yet holds none
+    pub fn load(path: &Path) -> io::Result<Self> {
+        let text = match fs::read_to_string(path) {
+            Ok(text) => text,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => String::new(),
+            Err(err) => return Err(err),
+        };
+        Ok(SeenSet::from_keys(text.lines()))
+    }
+
+    /// A set holding `keys`, ignoring surrounding whitespace and blank lines
+    pub fn from_keys<'a>(keys: impl IntoIterator<Item = &'a str>) -> Self {
+        let keys = keys
+            .into_iter()
+            .map(str::trim)
+            .filter(|key| !key.is_empty())
+            .map(str::to_string)
+            .collect();
+        SeenSet {
+            keys: parking_lot::RwLock::new(keys),
+        }
+    }
+
+    pub fn contains(&self, public_key: &str) -> bool {
+        self.keys.read().contains(public_key)
+    }
+
+    pub fn len(&self) -> usize {
+        self.keys.read().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.keys.read().is_empty()
+    }
+
+    /// Add `public_key` and append it to the file at `path`
+    ///
+    /// Returns `false`, writing nothing, if the set already had it.
+    pub fn record(&self, path: &Path, public_key: &str) -> io::Result<bool> {
+        let mut keys = self.keys.write();
+        if keys.contains(public_key) {
+            return Ok(false);
+        }
+        let mut file = fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)?;
+        writeln!(file, "{public_key}")?;
+        keys.insert(public_key.to_string());
+        Ok(true)
+    }
+}
+
+/// Why text couldn't be read as a secret key
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SecretKeyError {
+    /// Neither Base58 nor a JSON array of bytes
+    Unrecognized,
+    /// Decoded to this many bytes rather than the 64 of a keypair
+    WrongLength(usize),
+    /// The last 32 bytes aren't the public key of the first 32
+    PublicKeyMismatch,
+}
+
+impl std::fmt::Display for SecretKeyError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SecretKeyError::Unrecognized => {
+                write!(f, "not a Base58 secret key or a JSON array of bytes")
+            }
+            SecretKeyError::WrongLength(len) => write!(f, "{len} bytes instead of 64"),
+            SecretKeyError::PublicKeyMismatch => {
+                write!(f, "the public half doesn't belong to the secret half")
+            }
+        }
+    }
+}
+
+impl std::error::Error for SecretKeyError {}
+
+/// Read a 64-byte secret key as written by this tool, Phantom or `solana-keygen`: Base58 text
+/// or a JSON array of bytes
+pub fn parse_secret_key(text: &str) -> Result<Keypair, SecretKeyError> {
+    let text = text.trim();
+    let bytes = if text.starts_with('[') {
+        serde_json::from_str::<Vec<u8>>(text).map_err(|_| SecretKeyError::Unrecognized)?
+    } else {
+        bs58::decode(text)
+            .into_vec()
+            .map_err(|_| SecretKeyError::Unrecognized)?
+    };
+    keypair_from_secret_bytes(&bytes)
+}
+
+/// The keypair behind 64 secret key bytes, checking the length and the stored public half
+fn keypair_from_secret_bytes(bytes: &[u8]) -> Result<Keypair, SecretKeyError> {
+    if bytes.len() != 64 {
+        return Err(SecretKeyError::WrongLength(bytes.len()));
+    }
+    // The public half is stored, not derived, so a corrupted one would otherwise go unnoticed
+    let keypair = keypair_from_seed(&bytes[..32]).map_err(|_| SecretKeyError::Unrecognized)?;
+    if keypair.pubkey().to_bytes()[..] != bytes[32..] {
+        return Err(SecretKeyError::PublicKeyMismatch);
+    }
+    Ok(keypair)
+}
+
+/// The bytes of a Base58 secret key, as Phantom exports it, for a `solana-keygen` keypair file
+///
+/// Serialize the result with `serde_json` to get the file's `[12,34,...]` array.
+pub fn secret_base58_to_json_array(b58: &str) -> Result<Vec<u8>, SecretKeyError> {
+    let bytes = bs58::decode(b58.trim())
+        .into_vec()
+        .map_err(|_| SecretKeyError::Unrecognized)?;
+    keypair_from_secret_bytes(&bytes)?;
+    Ok(bytes)
+}
+
+/// The Base58 secret key wallets import, for the bytes of a `solana-keygen` keypair file
+pub fn secret_json_array_to_base58(bytes: &[u8]) -> Result<String, SecretKeyError> {
+    keypair_from_secret_bytes(bytes)?;
+    Ok(bs58::encode(bytes).into_string())
+}
+
+/// A key in a key file whose address matched
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct KeyFileMatch {
+    /// Line of the key file, counting from 1
+    pub line: usize,
+    pub public_key: String,
+}
+
+/// What [`filter_secret_keys`] found in a key file
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct KeyFileScan {
+    /// Number of lines that held a secret key
+    pub keys: usize,
+    pub matches: Vec<KeyFileMatch>,
+    /// Lines that weren't blank, comments or secret keys, counting from 1
+    pub unreadable: Vec<(usize, SecretKeyError)>,
+}
+
+/// Check every secret key in `text`, one per line, against `matcher`
+///
+/// Blank lines and lines starting with `#` are skipped, so a key file can carry notes.
+pub fn filter_secret_keys(text: &str, matcher: &dyn Matcher) -> KeyFileScan {
+    let mut scan = KeyFileScan::default();
+    for (index, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        match parse_secret_key(line) {
+            Ok(keypair) => {
+                scan.keys += 1;
+                let public_key = address_of(&keypair);
+                if matcher.matches(&public_key) {
+                    scan.matches.push(KeyFileMatch {
+                        line: index + 1,
+                        public_key,
+                    });
+                }
+            }
+            Err(err) => scan.unreadable.push((index + 1, err)),
+        }
+    }
+    scan
+}
+
+/// Directory result files are written to, relative to where the command runs
+pub const OUTPUT_DIR: &str = "output";
+
+/// Create `dir` and any missing parents, treating an existing directory as success
+pub fn ensure_output_dir(dir: &Path) -> io::Result<()> {
+    match fs::create_dir_all(dir) {
+        Ok(()) => Ok(()),
+        // Another process may have created it between our check and the create call
+        Err(err) if err.kind() == io::ErrorKind::AlreadyExists && dir.is_dir() => Ok(()),
+        Err(err) => Err(err),
+    }
+}
+
+/// Check that files can be created in `dir` by writing and removing a probe file
+///
+/// Catches read-only mounts and missing permissions before a long search rather than after.
+pub fn check_writable(dir: &Path) -> io::Result<()> {
+    let probe = dir.join(format!(".write_test_{}", std::process::id()));
+    fs::write(&probe, b"")?;
+    fs::remove_file(&probe)
+}
+
+/// Save `contents` to `path`; if that fails, write them to `fallback` so they aren't lost
+///
+/// Used for result files, where losing the output after a long search would lose the wallet.
+/// The file is written with [`write_atomic`], so in a batch every wallet already reported is on
+/// disk, whole, even if a later one crashes the run. The original error is still returned.
+pub fn save_or_dump(path: &Path, contents: &str, fallback: &mut dyn Write) -> io::Result<()> {
+    let Err(err) = write_atomic(path, contents.as_bytes()) else {
+        return Ok(());
+    };
+    // Best effort: if the fallback fails too there is nothing more to try
+    let _ = writeln!(
+        fallback,
+        "⚠️  Unable to save {}: {err}\nCopy the result below somewhere safe:\n\n{contents}",
+        path.display()
+    );
+    Err(err)
+}
+
+/// Write `bytes` to `path` so readers only ever see the old or the new contents
+///
+/// The data goes to a temporary file next to `path`, is synced, then renamed over it, so a crash
+/// or a full disk part way through leaves the previous file, or none, rather than a truncated one
+/// that looks valid. Every file this tool writes goes through here except the seen set, which
+/// [`SeenSet::record`] appends to a line at a time, and the rescue file the CLI creates when a
+/// result can't be saved at all.
+///
+/// A file that already exists keeps its permissions; a new one is readable only by its owner.
+pub fn write_atomic(path: &Path, bytes: &[u8]) -> io::Result<()> {
+    let temp_path = write_temp(path, bytes)?;
+    if let Err(err) = fs::rename(&temp_path, path) {
+        let _ = fs::remove_file(&temp_path);
+        return Err(err);
+    }
+    // Make the rename itself durable; not every platform or filesystem can sync a directory
+    #[cfg(unix)]
+    if let Ok(dir) = fs::File::open(parent_dir(path)) {
+        let _ = dir.sync_all();
+    }
+    Ok(())
+}
+
+/// [`write_atomic`], but refusing to replace an existing `path` unless `overwrite` is set
+///
+/// For files the user names, such as a keypair file, where replacing one loses a wallet.
+pub fn write_new(path: &Path, bytes: &[u8], overwrite: bool) -> io::Result<()> {
+    if !overwrite && path.exists() {
+        return Err(io::Error::new(
+            io::ErrorKind::AlreadyExists,
+            "the file already exists (--force replaces it)",
+        ));
+    }
+    write_atomic(path, bytes)
+}
+
+/// Temporary files this process has started, so concurrent writes never share one
+static TEMP_FILES: AtomicU64 = AtomicU64::new(0);
+
+/// The first half of [`write_atomic`]: `bytes` synced to a temporary file next to `path`
+///
+/// The temporary name is new to this process and call, and the file is created fresh, so two
+/// processes writing the same path can't mix their contents before the rename.
+fn write_temp(path: &Path, bytes: &[u8]) -> io::Result<PathBuf> {
+    let mut temp_name = path.file_name().unwrap_or_default().to_os_string();
+    temp_name.push(format!(
+        ".{}-{}.tmp",
+        std::process::id(),
+        TEMP_FILES.fetch_add(1, Ordering::Relaxed)
+    ));
+    let temp_path: PathBuf = path.with_file_name(temp_name);
+
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+    let written = options.open(&temp_path).and_then(|mut file| {
+        // The rename replaces the old file, so carry over any permissions it was given
+        if let Ok(existing) = fs::metadata(path) {
+            file.set_permissions(existing.permissions())?;
+        }
+        file.write_all(bytes)?;
+        file.sync_all()
+    });
+    if let Err(err) = written {
+        // A partial temporary file is no use to anyone
+        let _ = fs::remove_file(&temp_path);
+        return Err(err);
+    }
+    Ok(temp_path)
+}
+
+/// The directory holding `path`, `.` for a bare file name
+fn parent_dir(path: &Path) -> &Path {
+    match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    }
+}
+
+/// Lowercase hex SHA-256 digest of `bytes`
+pub fn sha256_hex(bytes: &[u8]) -> String {
+    to_hex(&Sha256::digest(bytes))
+}
+
+/// Sidecar path holding the checksum for `path`, e.g. `wallet.json.sha256`
+pub fn checksum_path(path: &Path) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(".sha256");
+    path.with_file_name(name)
+}
+
+/// Write a `sha256sum`-compatible sidecar covering `bytes`, the exact contents of `path`
+pub fn write_checksum_file(path: &Path, bytes: &[u8]) -> io::Result<PathBuf> {
+    let file_name = path.file_name().unwrap_or_default().to_string_lossy();
+    let sidecar = checksum_path(path);
+    write_atomic(
+        &sidecar,
+        format!("{}  {file_name}\n", sha256_hex(bytes)).as_bytes(),
+    )?;
+    Ok(sidecar)
+}
+
+/// Recompute the digest of `path` and compare it with its `.sha256` sidecar
+pub fn verify_checksum_file(path: &Path) -> io::Result<bool> {
+    let sidecar = fs::read_to_string(checksum_path(path))?;
+    let expected = sidecar
+        .split_whitespace()
+        .next()
+        .filter(|digest| digest.len() == 64 && digest.chars().all(|c| c.is_ascii_hexdigit()))
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "malformed checksum file"))?;
+
+    Ok(sha256_hex(&fs::read(path)?) == expected.to_ascii_lowercase())
+}
+
+/// Append `record` to the JSON array manifest at `path`, creating it if needed
+pub fn append_to_manifest(path: &Path, record: WalletRecord) -> io::Result<usize> {
+    let mut records: Vec<WalletRecord> = if path.exists() {
+        serde_json::from_str(&fs::read_to_string(path)?)?
+    } else {
+        Vec::new()
+    };
+    records.push(record);
+
+    let manifest = serde_json::to_string_pretty(&records)?;
+    write_atomic(path, manifest.as_bytes())?;
+
+    Ok(records.len())
+}
+
+/// Public keys only, one per line: the artifact to hand out for an airdrop allowlist
+pub fn pubkey_list(records: &[WalletRecord]) -> String {
+    records
+        .iter()
+        .map(|record| format!("{}\n", record.public_key))
+        .collect()
+}
+
+/// Encrypt the full records, secrets included, as one passphrase-protected bundle
+pub fn encrypt_wallet_bundle(
+    records: &[WalletRecord],
+    passphrase: &str,
+    params: KdfParams,
+) -> Result<EncryptedBlob, EncryptionError> {
+    let json = serde_json::to_vec(records).expect("wallet records always serialize");
+    encryption::encrypt(&json, passphrase, params)
+}
+
+/// Recover the records from a bundle made by [`encrypt_wallet_bundle`]
+pub fn decrypt_wallet_bundle(
+    blob: &EncryptedBlob,
+    passphrase: &str,
+) -> Result<Vec<WalletRecord>, EncryptionError> {
+    let json = encryption::decrypt(blob, passphrase)?;
+    serde_json::from_slice(&json).map_err(|err| EncryptionError::Malformed(err.to_string()))
+}
+
+/// Write `records` as one encrypted bundle on a single line: the base64 of the bundle's JSON
+///
+/// Meant for stdout in automated pipelines, so nothing but ciphertext is ever written.
+pub fn write_encrypted_bundle(
+    records: &[WalletRecord],
+    passphrase: &str,
+    params: KdfParams,
+    out: &mut dyn Write,
+) -> io::Result<()> {
+    let blob = encrypt_wallet_bundle(records, passphrase, params).map_err(io::Error::other)?;
+    let json = serde_json::to_vec(&blob)?;
+    writeln!(out, "{}", BASE64.encode(json))?;
+    out.flush()
+}
+
+/// Parse an encrypted blob saved as JSON, or as the base64 line [`write_encrypted_bundle`] makes
+pub fn parse_encrypted_blob(text: &str) -> Result<EncryptedBlob, EncryptionError> {
+    let text = text.trim();
+    let json = if text.starts_with('{') {
+        text.as_bytes().to_vec()
+    } else {
+        BASE64
+            .decode(text)
+            .map_err(|err| EncryptionError::Malformed(format!("base64: {err}")))?
+    };
+    serde_json::from_slice(&json).map_err(|err| EncryptionError::Malformed(err.to_string()))
+}
+
+/// Printable cold-storage sheet: the public key in clear plus the encrypted mnemonic as text and QR
+pub fn paper_backup_sheet(
+    public_key: &str,
+    blob: &EncryptedBlob,
+) -> Result<String, qrcode::types::QrError> {
+    let encoded = serde_json::to_string(blob).expect("encrypted blob always serializes");
+    let qr = qrcode::QrCode::new(encoded.as_bytes())?
+        .render::<qrcode::render::unicode::Dense1x2>()
+        .build();
+    let params = &blob.kdf_params;
+
+    Ok(format!(
+        "Solana Paper Backup\n\
+        ===================\n\
+        Public Key: {public_key}\n\
+        \n\
+        Encrypted Mnemonic:\n\
+        {encoded}\n\
+        \n\
+        KDF: {} (memory {} KiB, iterations {}, parallelism {})\n\
+        Cipher: {}\n\
+        \n\
+        {qr}\n\
+        \n\
+        To restore, save the encrypted line (or the scanned QR text) to a file and run:\n  \
+        solana-vanity-wallet --decrypt-backup <FILE>\n",
+        blob.kdf, params.memory_kib, params.iterations, params.parallelism, blob.cipher
+    ))
+}
+
+/// `solana-keygen` keypair URI that prompts for the seed phrase and derives at `path`
+///
+/// A bare `prompt://` makes `solana-keygen` use the BIP39 seed without any derivation, which
+/// gives a different key, so the path is always spelled out: `?key=<account>/<change>` for
+/// paths under `m/44'/501'`, `?full-path=` otherwise.
+pub fn keygen_prompt_uri(path: &DerivationPath) -> String {
+    match path.indexes().as_slice() {
+        [44, 501, account] => format!("prompt://?key={account}"),
+        [44, 501, account, change] => format!("prompt://?key={account}/{change}"),
+        indexes => {
+            let levels: Vec<String> = indexes.iter().map(|index| index.to_string()).collect();
+            format!("prompt://?full-path=m/{}", levels.join("/"))
+        }
+    }
+}
+
+/// Plain-text paper wallet: the mnemonic, its derivation path and the `solana-keygen` commands
+/// that recover it
+///
+/// Unlike [`paper_backup_sheet`] nothing is encrypted; whoever holds the sheet holds the funds.
+pub fn paper_wallet_sheet(public_key: &str, mnemonic: &str, path: &DerivationPath) -> String {
+    let uri = keygen_prompt_uri(path);
+    format!(
+        "Solana Paper Wallet\n\
+        ===================\n\
+        Public Key: {public_key}\n\
+        Seed Phrase: {mnemonic}\n\
+        BIP39 Passphrase: (none)\n\
+        Derivation Path: {path}\n\
+        \n\
+        To recover the keypair, run the command below, enter the seed phrase when prompted and\n\
+        leave the passphrase empty:\n  \
+        solana-keygen recover '{uri}' --outfile keypair.json\n\
+        \n\
+        To check the public key without writing a file:\n  \
+        solana-keygen pubkey '{uri}'\n"
+    )
+}
+
+pub fn generate_solana_keypair() -> (String, Vec<u8>) {
+    let keypair = Keypair::new();
+    let pubkey = address_of(&keypair);
+    let keypair_bytes = keypair.to_bytes().to_vec();
+    (pubkey, keypair_bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_valid_base58_prefixes() {
+        assert!(is_valid_base58_prefix("A"));
+        assert!(is_valid_base58_prefix("1"));
+        assert!(is_valid_base58_prefix("ABC"));
+        assert!(is_valid_base58_prefix("Sov")); // 'l' is not valid in base58
+        assert!(is_valid_base58_prefix("123"));
+        assert!(is_valid_base58_prefix("JAZZ"));
+        assert!(is_valid_base58_prefix("MyWavvet"));
+    }
+
+    #[test]
+    fn test_invalid_base58_prefixes() {
+        assert!(!is_valid_base58_prefix(""));
+        assert!(!is_valid_base58_prefix("0"));
+        assert!(!is_valid_base58_prefix("O"));
+        assert!(!is_valid_base58_prefix("I"));
+        assert!(!is_valid_base58_prefix("l"));
+        assert!(!is_valid_base58_prefix("_"));
+        assert!(!is_valid_base58_prefix("Test0"));
+        assert!(!is_valid_base58_prefix("ABC_"));
+        assert!(!is_valid_base58_prefix("Sol+"));
+    }
+
+    #[test]
+    fn test_expected_iterations_calculation() {
+        let prefix = |text: &str| VanityPattern::Prefix(text.to_string()).expected_iterations();
+        assert_eq!(prefix("A"), 29); // 58/2
+        assert_eq!(prefix("AB"), 1682); // 58^2/2
+        assert_eq!(prefix("ABC"), 97556); // 58^3/2
+        assert_eq!(prefix("ABCDEFGHJK"), 58u128.pow(10) / 2);
+        // 58^11 doesn't fit in a u64, but the halved estimate does, just
+        assert_eq!(prefix("ABCDEFGHJKL"), 58u128.pow(11) / 2);
+        assert!(prefix("ABCDEFGHJKL") < u64::MAX.into());
+        // From 12 characters the estimate is past u64::MAX and still exact
+        assert_eq!(prefix("ABCDEFGHJKLM"), 58u128.pow(12) / 2);
+        assert!(prefix("ABCDEFGHJKLM") > u64::MAX.into());
+        // 58^44 doesn't fit in a u128 either; the estimate saturates instead of overflowing
+        assert_eq!(prefix(&"z".repeat(44)), u128::MAX);
+    }
+
+    #[test]
+    fn test_each_matcher_reports_its_difficulty() {
+        let registry = MatcherRegistry::default();
+        let expected =
+            |name: &str, arg: &str| registry.build(name, arg).unwrap().expected_iterations();
+        assert_eq!(expected("prefix", "Sun"), Some(58u128.pow(3) / 2));
+        assert_eq!(expected("suffix", "Sun"), Some(58u128.pow(3) / 2));
+        // Two chances per address
+        assert_eq!(expected("either", "Sun"), Some(58u128.pow(3) / 4));
+        // Only the literal characters of a mask count
+        assert_eq!(expected("mask", "S?n??"), Some(58u128.pow(2) / 2));
+        assert_eq!(expected("regex", "^Sun"), Some(58u128.pow(3) / 2));
+        // 3 of 58 characters allowed at position 0: 58/3 candidates, halved
+        assert_eq!(expected("charset", "0=ABC"), Some(9));
+        assert_eq!(expected("any-prefix", "A,B"), Some(14));
+        assert_eq!(expected("smart-case", "b"), Some(14));
+    }
+
+    #[test]
+    fn test_solana_keypair_generation() {
+        let (pubkey, keypair_bytes) = generate_solana_keypair();
+
+        // Test that public key is valid Base58
+        assert!(bs58::decode(&pubkey).into_vec().is_ok());
+
+        // Test that keypair bytes are correct length (64 bytes)
+        assert_eq!(keypair_bytes.len(), 64);
+
+        // Test that public key is not empty
+        assert!(!pubkey.is_empty());
+
+        // Test that public key is reasonable length (typically 32-44 characters)
+        assert!(pubkey.len() >= 32 && pubkey.len() <= 44);
+    }
+
+    #[test]
+    fn test_keypair_consistency() {
+        // Generate multiple keypairs and ensure they're different
+        let (pubkey1, _) = generate_solana_keypair();
+        let (pubkey2, _) = generate_solana_keypair();
+
+        assert_ne!(pubkey1, pubkey2);
+    }
+
+    #[test]
+    fn test_base58_alphabet_completeness() {
+        // Test that all expected characters are considered valid
+        let valid_chars = "123456789ABCDEFGHJKLMNPQRSTUVWXYZabcdefghijkmnopqrstuvwxyz";
+
+        for c in valid_chars.chars() {
+            assert!(
+                is_valid_base58_prefix(&c.to_string()),
+                "Character '{c}' should be valid"
+            );
+        }
+    }
+
+    #[test]
+    fn test_edge_cases() {
+        // Test edge cases for prefix validation
+        assert!(!is_valid_base58_prefix(" "));
+        assert!(!is_valid_base58_prefix("\n"));
+        assert!(!is_valid_base58_prefix("\t"));
+        assert!(!is_valid_base58_prefix("A B"));
+        assert!(!is_valid_base58_prefix("A\n"));
+    }
+
+    #[test]
+    fn test_pubkey_from_seed_matches_solana_keypair() {
+        // Holds for whichever ed25519 backend is compiled in
+        for _ in 0..10 {
+            let (_, seed) = random_keypair_seed(
+                &mut OsRng,
+                false,
+                &DerivationPath::default(),
+                &SeedSalt::default(),
+            );
+            let keypair = Keypair::from_seed(&seed).unwrap();
+            assert_eq!(
+                pubkey_from_seed(&seed).unwrap(),
+                keypair.pubkey().to_bytes()
+            );
+        }
+    }
+
+    #[test]
+    fn test_search_stats_computation() {
+        let stats = SearchStats::new(500, Duration::from_secs(2), 1682, 8);
+
+        assert_eq!(stats.iterations, 500);
+        assert_eq!(stats.expected, 1682);
+        assert_eq!(stats.threads, 8);
+        assert_eq!(stats.rate, 250.0);
+        assert!((stats.luck_factor - 3.364).abs() < 1e-9);
+        assert!(stats.is_lucky());
+
+        let unlucky = SearchStats::new(3364, Duration::from_secs(1), 1682, 8);
+        assert_eq!(unlucky.luck_factor, 0.5);
+        assert!(!unlucky.is_lucky());
+    }
+
+    #[test]
+    fn test_search_stats_zero_guards() {
+        // Neither a zero elapsed time nor zero iterations should divide by zero
+        let stats = SearchStats::new(0, Duration::ZERO, 29, 1);
+
+        assert_eq!(stats.rate, 0.0);
+        assert_eq!(stats.luck_factor, 0.0);
+        assert!(stats.rate.is_finite() && stats.luck_factor.is_finite());
+    }
+
+    #[test]
+    fn test_counts_past_u64_max_do_not_wrap() {
+        let past = u64::MAX as u128 + 1;
+        assert_eq!(format_number(u64::MAX.into()), "18446744.1T");
+        assert_eq!(format_number(past), "18446744.1T");
+        // Far past u64, the figure is only as precise as an f64, but it's still in range
+        assert!(format_number(u128::MAX).starts_with("34028236692093"));
+
+        // A 12-character prefix's estimate, and a run twice as long, keep their ratio
+        let expected = VanityPattern::Prefix("ABCDEFGHJKLM".to_string()).expected_iterations();
+        let stats = SearchStats::new(expected * 2, Duration::from_secs(1), expected, 1);
+        assert_eq!(stats.iterations, expected * 2);
+        assert!((stats.luck_factor - 0.5).abs() < 1e-12);
+        assert!(stats.rate > u64::MAX as f64);
+
+        // Saved statistics read back exactly
+        let record = StatisticsRecord {
+            iterations: past + 7,
+            elapsed_seconds: 1.0,
+            iterations_per_second: stats.rate,
+            expected_iterations: expected,
+            luck_factor: stats.luck_factor,
+            threads: 1,
+        };
+        let json = serde_json::to_string(&record).unwrap();
+        assert!(json.contains("\"iterations\":18446744073709551623"));
+        assert_eq!(
+            serde_json::from_str::<StatisticsRecord>(&json).unwrap(),
+            record
+        );
+    }
+
+    #[test]
+    fn test_give_up_advisor_speaks_once_past_the_threshold() {
+        // A one-character prefix: 58 candidates per match on average, so 99% takes ln(100) * 58
+        let mut advisor = GiveUpAdvisor::new(29, SUSPICIOUS_PROBABILITY);
+        assert_eq!(advisor.observe(0), None);
+        assert_eq!(advisor.observe(58), None);
+        assert_eq!(advisor.observe(267), None);
+        let probability = advisor.observe(268).expect("past 99% by 268 candidates");
+        assert!((0.99..0.991).contains(&probability));
+        // Once is enough
+        assert_eq!(advisor.observe(269), None);
+        assert_eq!(advisor.observe(10_000), None);
+
+        // A long jump between samples still advises, with the chance as it stands
+        let mut advisor = GiveUpAdvisor::new(29, SUSPICIOUS_PROBABILITY);
+        assert!(advisor.observe(2_000).unwrap() > 0.999_999);
+
+        // The threshold is the caller's; a stricter one waits longer
+        let mut strict = GiveUpAdvisor::new(29, 0.999);
+        assert_eq!(strict.observe(268), None);
+        assert!(strict.observe(401).is_some());
+    }
+
+    #[test]
+    fn test_rate_drop_guard_needs_a_sustained_drop() {
+        let mut guard = RateDropGuard::new(0.4, Duration::from_secs(30));
+        // Warming up to a peak of 100K/s, then a brief dip that recovers
+        for (second, rate) in [(1.0, 50_000.0), (2.0, 100_000.0), (3.0, 90_000.0)] {
+            assert!(!guard.observe(second, rate));
+        }
+        for second in 4..20 {
+            assert!(!guard.observe(second as f64, 10_000.0));
+        }
+        assert!(!guard.observe(20.0, 40_000.0));
+        assert_eq!(guard.peak(), 100_000.0);
+
+        // Throttled for good: trips once it has stayed low for the full 30 seconds
+        for second in 21..51 {
+            assert!(
+                !guard.observe(second as f64, 39_999.0),
+                "tripped at {second}s"
+            );
+        }
+        assert!(guard.observe(51.0, 30_000.0));
+
+        // A rate that is merely low from the start never trips
+        let mut steady = RateDropGuard::new(0.4, Duration::from_secs(30));
+        assert!((0..120).all(|second| !steady.observe(second as f64, 1_000.0)));
+    }
+
+    #[test]
+    fn test_near_miss_histogram_aggregation() {
+        let mut first = NearMisses::default();
+        for matched in [0, 1, 1, 2, 5] {
+            first.record(matched);
+        }
+        let mut second = NearMisses::default();
+        for matched in [3, 1] {
+            second.record(matched);
+        }
+        first.merge(&second);
+
+        // Counts are cumulative, and deeper misses than the histogram tracks still count
+        assert_eq!(first.shared(1), 6);
+        assert_eq!(first.shared(2), 3);
+        assert_eq!(first.shared(3), 2);
+        assert_eq!(first.shared(0), 0);
+        assert_eq!(first.shared(NEAR_MISS_DEPTH + 1), 0);
+        assert_eq!(
+            first.histogram_lines(),
+            [
+                "Shared first 1 char: 6",
+                "Shared first 2 chars: 3",
+                "Shared first 3 chars: 2"
+            ]
+        );
+
+        // End to end: the exact match itself is not a near miss
+        let mut config = SearchConfig::new(VanityPattern::Prefix("11".to_string()), false, 2);
+        config.collect_near_misses = true;
+        let result = search_vanity(&config, &SearchProgress::default());
+        let near_misses = result.stats.near_misses.unwrap();
+        assert!(u128::from(near_misses.shared(1)) < result.stats.iterations);
+        assert_eq!(near_misses.shared(2), 0);
+    }
+
+    #[test]
+    fn test_search_vanity_finds_prefix() {
+        let progress = SearchProgress::default();
+        let config = SearchConfig::new(VanityPattern::Prefix("1".to_string()), false, 2);
+        let result = search_vanity(&config, &progress);
+        let wallet = result.wallet.unwrap();
+
+        assert!(wallet.public_key.starts_with('1'));
+        assert_eq!(wallet.public_key, address_of(&wallet.keypair));
+        assert!(wallet.mnemonic.is_none());
+        assert!(result.closest.is_none());
+        assert!(progress.done.load(Ordering::Relaxed));
+        assert!(result.stats.iterations > 0);
+        assert_eq!(result.stats.expected, 29);
+        assert_eq!(result.stats.threads, 2);
+    }
+
+    #[test]
+    fn test_generate_keypair_from_stubbed_entropy() {
+        let source = std::io::Cursor::new([42u8; 32].to_vec());
+        let mut rng = entropy::HardwareRng::from_reader(Box::new(source));
+        let (mnemonic, keypair) =
+            generate_keypair_from(&mut rng, false, &DerivationPath::default()).unwrap();
+
+        assert!(mnemonic.is_none());
+        assert_eq!(keypair.to_bytes()[..32], [42u8; 32]);
+    }
+
+    #[test]
+    fn test_secret_seed_bytes_rebuilds_the_keypair() {
+        let (_, keypair) = generate_keypair(false).unwrap();
+        let seed = secret_seed_bytes(&keypair);
+        assert_eq!(keypair.to_bytes()[..32], seed);
+        assert_eq!(
+            Keypair::from_seed(&seed).unwrap().pubkey(),
+            keypair.pubkey()
+        );
+    }
+
+    #[test]
+    fn test_search_with_hardware_entropy() {
+        let mut config = SearchConfig::new(VanityPattern::Prefix("1".to_string()), false, 1);
+        config.entropy = EntropySource::Hardware;
+        let result = search_vanity(&config, &SearchProgress::default());
+
+        assert!(result.wallet.unwrap().public_key.starts_with('1'));
+    }
+
+    #[test]
+    fn test_first_character_probabilities_sum_to_one() {
+        let alphabet = "123456789ABCDEFGHJKLMNPQRSTUVWXYZabcdefghijkmnopqrstuvwxyz";
+        let total: f64 = alphabet
+            .chars()
+            .map(|c| prefix_probability(&c.to_string()))
+            .sum();
+        assert!((total - 1.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_first_char_guard_refuses_unseen_characters() {
+        // A sampler that never produces an address starting with 'z'
+        let addresses = ["2abc", "Gxyz", "5zzz", "Azzz"];
+        let mut next = addresses.iter().cycle();
+        let sample = FirstCharSample::collect(400, || next.next().unwrap().to_string());
+        assert_eq!(sample.samples(), 400);
+        assert_eq!(sample.count('G'), 100);
+        assert_eq!(sample.share('z'), 0.0);
+
+        let err = sample.check_prefix("zap", 0.0).unwrap_err();
+        assert_eq!((err.first, err.seen, err.samples), ('z', 0, 400));
+        assert!(err.to_string().contains("never finish"));
+        assert!(sample.check_prefix("Gum", 0.0).is_ok());
+
+        // A raised threshold also refuses characters that are merely rare
+        assert!(sample.check_prefix("Gum", 0.25).is_err());
+        assert!(sample.check_prefix("Gum", 0.2).is_ok());
+    }
+
+    #[test]
+    fn test_standard_sample_sees_every_first_character() {
+        let sample = FirstCharSample::standard();
+        assert_eq!(sample.samples(), FIRST_CHAR_SAMPLES);
+        for c in "123456789ABCDEFGHJKLMNPQRSTUVWXYZabcdefghijkmnopqrstuvwxyz".chars() {
+            assert!(
+                sample.check_prefix(&c.to_string(), 0.0).is_ok(),
+                "'{c}' was never sampled"
+            );
+        }
+    }
+
+    #[test]
+    fn test_search_bits_per_second() {
+        assert!((search_bits_per_second(1_048_576.0) - 20.0).abs() < 1e-9);
+        assert!((bits_as_base58_chars(search_bits_per_second(58f64.powi(3))) - 3.0).abs() < 1e-9);
+        // A rate that covers a prefix's bits finds it in about a second
+        let bits = prefix_difficulty_bits("z");
+        assert!((search_bits_per_second(2f64.powf(bits)) - bits).abs() < 1e-9);
+        assert_eq!(search_bits_per_second(0.0), 0.0);
+        assert_eq!(search_bits_per_second(0.5), 0.0);
+    }
+
+    #[test]
+    fn test_prefix_difficulty_bits() {
+        // A leading '1' is a zero byte
+        assert!((prefix_difficulty_bits("1") - 8.0).abs() < 1e-9);
+        assert!((prefix_difficulty_bits("11") - 16.0).abs() < 1e-9);
+        // Most addresses are 44 characters starting with 2-J, so those are cheap and the rest dear
+        assert!(prefix_difficulty_bits("A") < 5.0);
+        assert!(prefix_difficulty_bits("z") > 9.0);
+        assert_eq!(prefix_difficulty_bits("0"), f64::INFINITY);
+    }
+
+    #[test]
+    fn test_suffix_difficulty() {
+        let lengths = address_length_distribution();
+        assert!((lengths.values().sum::<f64>() - 1.0).abs() < 1e-9);
+        // 1 - 58^43 / 2^256 of addresses reach 44 characters
+        assert!((lengths[&44] - 0.942).abs() < 0.01);
+        assert!(lengths.keys().all(|len| (32..=44).contains(len)));
+
+        // Plausible: every character costs log2(58) bits, never less
+        for suffix in ["z", "Sun", "1", "pump"] {
+            let uniform = suffix.len() as f64 * 58f64.log2();
+            let bits = suffix_difficulty(suffix);
+            assert!(bits >= uniform - 1e-9, "{suffix}: {bits}");
+            assert!(bits - uniform < 0.01, "{suffix}: {bits}");
+        }
+
+        assert_eq!(suffix_difficulty(""), 0.0);
+        assert_eq!(suffix_difficulty("0"), f64::INFINITY);
+        assert_eq!(suffix_difficulty(&"z".repeat(44)), f64::INFINITY);
+
+        // The banner's expected iterations for a suffix come from the same model
+        let expected = VanityPattern::Suffix("Sun".to_string()).expected_iterations();
+        let uniform = 58u128.pow(3) / 2;
+        assert!(
+            expected >= uniform && expected - uniform < uniform / 100,
+            "{expected}"
+        );
+        assert_eq!(
+            VanityPattern::Suffix("0".to_string()).expected_iterations(),
+            u128::MAX
+        );
+    }
+
+    #[test]
+    fn test_difficulty_comparison_boundaries() {
+        let reference = |bits: f64| {
+            let comparison = difficulty_comparison(bits);
+            comparison[..comparison.find(" (").unwrap()].to_string()
+        };
+        assert_eq!(
+            reference(0.0),
+            "about as hard as guessing a number from 1 to 100"
+        );
+        assert_eq!(
+            reference(7.0),
+            "about as hard as guessing a number from 1 to 100"
+        );
+        assert_eq!(reference(7.01), "about as hard as guessing a 3-digit PIN");
+        assert_eq!(reference(14.0), "about as hard as guessing a 4-digit PIN");
+        assert_eq!(reference(14.01), "about as hard as guessing a 6-digit PIN");
+        assert_eq!(
+            reference(57.0),
+            "about as hard as winning two lottery jackpots in a row"
+        );
+        assert!(reference(57.01).starts_with("beyond practical"));
+        assert!(reference(f64::INFINITY).starts_with("beyond practical"));
+
+        // The bit count is always shown too
+        assert_eq!(
+            difficulty_comparison(13.29),
+            "about as hard as guessing a 4-digit PIN (a 13-bit search)"
+        );
+    }
+
+    #[test]
+    fn test_difficulty_breakdown_sums_to_total() {
+        for prefix in ["A", "Sun", "zzz", "1ABC", "MyKey"] {
+            let breakdown = difficulty_breakdown(prefix);
+            assert_eq!(
+                breakdown.iter().map(|(c, _)| *c).collect::<String>(),
+                prefix
+            );
+
+            let sum: f64 = breakdown.iter().map(|(_, bits)| bits).sum();
+            assert!((sum - prefix_difficulty_bits(prefix)).abs() < 1e-6);
+        }
+
+        // Characters after the first are close to uniform
+        let uniform = 58f64.log2();
+        for (_, bits) in &difficulty_breakdown("Sun")[1..] {
+            assert!((bits - uniform).abs() < 0.1);
+        }
+    }
+
+    #[test]
+    fn test_extend_search_finds_longer_match() {
+        let progress = SearchProgress::default();
+        let config = SearchConfig::new(VanityPattern::Prefix("2".to_string()), false, 2);
+        let found = search_vanity(&config, &progress).wallet.unwrap();
+
+        let target = "2zzzzzzzzz";
+        let extend = SearchConfig::new(VanityPattern::Prefix(target.to_string()), false, 2);
+        let (best, stats) = extend_search(
+            found,
+            &*config.pattern,
+            &extend,
+            Duration::from_millis(200),
+            &SearchProgress::default(),
+        );
+
+        assert!(stats.iterations > 0);
+        assert!(best.wallet.public_key.starts_with('2'));
+        assert!(best.matched_chars >= 1);
+        assert_eq!(
+            best.matched_chars,
+            common_prefix_len(&best.wallet.public_key, target)
+        );
+    }
+
+    #[test]
+    fn test_extend_search_keeps_original_when_nothing_better() {
+        let progress = SearchProgress::default();
+        let config = SearchConfig::new(VanityPattern::Prefix("2".to_string()), false, 1);
+        let found = search_vanity(&config, &progress).wallet.unwrap();
+        let original = found.public_key.clone();
+
+        // The target is already fully matched, so no candidate can beat it
+        let target = VanityPattern::Prefix(leading_chars(&original, 2).to_string());
+        let extend = SearchConfig::new(target, false, 1);
+        let (best, _) = extend_search(
+            found,
+            &*config.pattern,
+            &extend,
+            Duration::from_millis(50),
+            &SearchProgress::default(),
+        );
+
+        assert_eq!(best.wallet.public_key, original);
+        assert_eq!(best.matched_chars, 2);
+    }
+
+    #[test]
+    fn test_extension_must_still_match_the_original_pattern() {
+        let wallet = |public_key: &str, matched_chars: usize| ClosestMatch {
+            wallet: VanityWallet {
+                mnemonic: None,
+                public_key: public_key.to_string(),
+                keypair: Keypair::new(),
+            },
+            matched_chars,
+        };
+        let original = VanityPattern::EitherEnd("Sun".to_string());
+        let target = VanityPattern::EitherEnd("SunRise".to_string());
+        let found = "SunXq4f2aBcDeFgHjKmNpQrStUvWxYz";
+        let far_end = "Xq4f2aBcDeFgHjKmNpQrStUvWxnRise";
+        assert_eq!(target.closeness(found), 3);
+        assert_eq!(target.closeness(far_end), 5);
+
+        // Closer to SunRise, but not a Sun address at either end
+        let best = closer_match(wallet(found, 3), Some(wallet(far_end, 5)), &original);
+        assert_eq!(best.wallet.public_key, found);
+        assert_eq!(best.matched_chars, 3);
+
+        // A closer candidate that is still a Sun address does replace it
+        let better = "SunRq4f2aBcDeFgHjKmNpQrStUvWxYz";
+        let best = closer_match(wallet(found, 3), Some(wallet(better, 4)), &original);
+        assert_eq!(best.wallet.public_key, better);
+    }
+
+    #[test]
+    fn test_format_number() {
+        assert_eq!(format_number(0), "0");
+        assert_eq!(format_number(999), "999");
+        assert_eq!(format_number(1_000), "1.0K");
+        assert_eq!(format_number(1_500), "1.5K");
+        assert_eq!(format_number(999_999), "1000.0K");
+        assert_eq!(format_number(1_000_000), "1.0M");
+        assert_eq!(format_number(2_500_000_000), "2.5B");
+        assert_eq!(format_number(1_000_000_000_000), "1.0T");
+    }
+
+    #[test]
+    fn test_format_percent() {
+        assert_eq!(format_percent(0.9), "90%");
+        assert_eq!(format_percent(0.07), "7%");
+        assert_eq!(format_percent(0.999), "99.9%");
+        assert_eq!(format_percent(0.12345), "12.345%");
+        assert_eq!(format_percent(1.0), "100%");
+        assert_eq!(format_percent(0.0), "0%");
+    }
+
+    #[test]
+    fn test_fixed_rate_units() {
+        let unit = |text: &str| text.parse::<RateUnit>().unwrap();
+        let rates = [0.0, 999.9, 41_234.6, 1_500_000.0, 2_000_000_000.0];
+        let shown = |unit: RateUnit| rates.map(|rate| unit.format(rate));
+
+        assert_eq!(
+            shown(RateUnit::default()),
+            ["0", "999", "41.2K", "1.5M", "2.0B"]
+        );
+        // A fixed unit keeps its suffix however small or large the rate gets
+        assert_eq!(
+            shown(unit("keys")),
+            ["0", "999", "41234", "1500000", "2000000000"]
+        );
+        assert_eq!(
+            shown(unit("K")),
+            ["0.0K", "1.0K", "41.2K", "1500.0K", "2000000.0K"]
+        );
+        assert_eq!(
+            shown(unit("millions")),
+            ["0.0M", "0.0M", "0.0M", "1.5M", "2000.0M"]
+        );
+        assert_eq!(
+            shown(unit("kH=1000")),
+            ["0.0kH", "1.0kH", "41.2kH", "1500.0kH", "2000000.0kH"]
+        );
+        assert_eq!(unit(" H = 1 ").format(41_234.6), "41234H");
+
+        assert_eq!(
+            "1000".parse::<RateUnit>(),
+            Err(RateUnitError::Unknown("1000".to_string()))
+        );
+        assert_eq!(
+            "kH=".parse::<RateUnit>(),
+            Err(RateUnitError::Unknown("kH=".to_string()))
+        );
+        assert_eq!("kH=0".parse::<RateUnit>(), Err(RateUnitError::ZeroDivisor));
+    }
+
+    #[test]
+    fn test_format_duration() {
+        assert_eq!(format_duration(0.0), "0.0s");
+        assert_eq!(format_duration(59.9), "59.9s");
+        assert_eq!(format_duration(60.0), "1.0m");
+        assert_eq!(format_duration(90.0), "1.5m");
+        assert_eq!(format_duration(3600.0), "1.0h");
+        assert_eq!(format_duration(86_400.0), "1.0d");
+        assert_eq!(format_duration(172_800.0), "2.0d");
+    }
+
+    #[test]
+    fn test_validate_prefix_rejects_non_ascii() {
+        assert_eq!(validate_prefix("Sun"), Ok(()));
+        assert_eq!(validate_prefix(""), Err(PrefixError::Empty));
+        assert_eq!(validate_prefix("Sol"), Err(PrefixError::InvalidChar('l')));
+        assert_eq!(validate_prefix("🚀"), Err(PrefixError::NonAscii('🚀')));
+        assert_eq!(validate_prefix("Café"), Err(PrefixError::NonAscii('é')));
+        assert_eq!(
+            validate_prefix("Ab\u{301}"),
+            Err(PrefixError::NonAscii('\u{301}'))
+        );
+        assert_eq!(validate_prefix("A\tB"), Err(PrefixError::ControlChar('\t')));
+        assert_eq!(
+            validate_prefix("A\tB").unwrap_err().to_string(),
+            "'\\t' (U+0009) is a control character; addresses only use Base58 letters and digits"
+        );
+
+        // A 50-character prefix is valid Base58 but longer than any address
+        assert_eq!(
+            validate_prefix(&"A".repeat(50)),
+            Err(PrefixError::TooLong(50))
+        );
+        assert!(validate_prefix(&"A".repeat(MAX_ADDRESS_LEN)).is_ok());
+        assert!(!is_valid_base58_prefix("ñ"));
+    }
+
+    #[test]
+    fn test_leading_chars_respects_char_boundaries() {
+        assert_eq!(leading_chars("BbGW5Yqtsa4XyZ", 10), "BbGW5Yqtsa");
+        assert_eq!(leading_chars("short", 10), "short");
+        assert_eq!(leading_chars("", 10), "");
+        // Slicing these by bytes would panic mid-character
+        assert_eq!(leading_chars("🚀🚀🚀", 2), "🚀🚀");
+        assert_eq!(leading_chars("éééééééééééé", 10), "éééééééééé");
+    }
+
+    #[test]
+    fn test_wallet_file_name_for_edge_case_keys() {
+        assert_eq!(
+            wallet_file_name("BbGW5Yqtsa4XyZ", "output.json"),
+            "BbGW5Yqtsa_output.json"
+        );
+        assert_eq!(wallet_file_name("Ab", "output.txt"), "Ab_output.txt");
+        assert_eq!(wallet_file_name("", "output.txt"), "wallet_output.txt");
+        assert_eq!(
+            wallet_file_name("ééééééééééé", "paper_backup.txt"),
+            "éééééééééé_paper_backup.txt"
+        );
+    }
+
+    #[test]
+    fn test_name_template_expansion() {
+        let fields = NameFields {
+            public_key: "SunX4f2aBcDeFgHjK",
+            prefix: Some("Sun"),
+            date: chrono::NaiveDate::from_ymd_opt(2026, 10, 16).unwrap(),
+        };
+        let render = |template: &str| template.parse::<NameTemplate>().unwrap().render(&fields);
+        assert_eq!(
+            render("{prefix}-{date}-{pubkey8}"),
+            "Sun-2026-10-16-SunX4f2a"
+        );
+        assert_eq!(render("{pubkey}"), "SunX4f2aBcDeFgHjK");
+        assert_eq!(render("treasury_{pubkey4}.v2"), "treasury_SunX.v2");
+        assert_eq!(render("{pubkey99}"), "SunX4f2aBcDeFgHjK");
+
+        // Without a prefix the placeholder is empty, and an empty result falls back to `wallet`
+        let no_prefix = NameFields {
+            prefix: None,
+            ..fields.clone()
+        };
+        let template: NameTemplate = "{prefix}".parse().unwrap();
+        assert_eq!(template.render(&no_prefix), "wallet");
+
+        // The default template is the usual leading key characters
+        assert_eq!(
+            NameTemplate::default().render(&fields),
+            leading_chars(fields.public_key, FILE_NAME_KEY_CHARS)
+        );
+    }
+
+    #[test]
+    fn test_name_template_without_key_never_overwrites() {
+        let dir = test_dir("stem-collisions");
+        let template: NameTemplate = "treasury".parse().unwrap();
+        let mut written = Vec::new();
+        for public_key in ["SunAAAA", "SunBBBB", "SunCCCC"] {
+            let stem = unused_stem(&dir, &template.render(&NameFields::new(public_key, None)));
+            let path = dir.join(format!("{stem}_output.txt"));
+            write_atomic(&path, public_key.as_bytes()).unwrap();
+            written.push(path);
+        }
+
+        assert_eq!(
+            written,
+            ["treasury", "treasury-2", "treasury-3"]
+                .map(|stem| dir.join(format!("{stem}_output.txt")))
+        );
+        // Every wallet is still on disk
+        for (path, public_key) in written.iter().zip(["SunAAAA", "SunBBBB", "SunCCCC"]) {
+            assert_eq!(fs::read_to_string(path).unwrap(), public_key);
+        }
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_name_template_rejects_bad_templates() {
+        let parse = |template: &str| template.parse::<NameTemplate>().err();
+        assert_eq!(parse(""), Some(NameTemplateError::Empty));
+        assert_eq!(
+            parse("{owner}-{pubkey8}"),
+            Some(NameTemplateError::UnknownPlaceholder("owner".to_string()))
+        );
+        assert_eq!(
+            parse("{pubkey0}"),
+            Some(NameTemplateError::UnknownPlaceholder("pubkey0".to_string()))
+        );
+        assert_eq!(parse("{prefix"), Some(NameTemplateError::Unbalanced));
+        assert_eq!(parse("prefix}"), Some(NameTemplateError::Unbalanced));
+        assert_eq!(parse("../{pubkey}"), Some(NameTemplateError::LeadingDot));
+        assert_eq!(
+            parse("a/{pubkey}"),
+            Some(NameTemplateError::UnsafeChar('/'))
+        );
+        assert_eq!(
+            parse("{date} {pubkey}"),
+            Some(NameTemplateError::UnsafeChar(' '))
+        );
+        assert_eq!(
+            parse("{date}:{pubkey}"),
+            Some(NameTemplateError::UnsafeChar(':'))
+        );
+    }
+
+    #[test]
+    fn test_estimate_wall_clock() {
+        assert_eq!(
+            estimate_wall_clock(1_000_000, 1_000.0),
+            Some(Duration::from_secs(1_000))
+        );
+        // Eight workers at 1,000/s each
+        assert_eq!(
+            estimate_wall_clock(1_000_000, 8_000.0),
+            Some(Duration::from_secs(125))
+        );
+        assert_eq!(estimate_wall_clock(0, 1_000.0), Some(Duration::ZERO));
+        assert_eq!(estimate_wall_clock(1_000, 0.0), None);
+        assert_eq!(estimate_wall_clock(1_000, f64::NAN), None);
+        // Counts past u64::MAX still give an estimate rather than wrapping
+        let past_u64 = 58u128.pow(12);
+        assert!(past_u64 > u64::MAX as u128);
+        assert!(estimate_wall_clock(past_u64, 1e9).is_some_and(|time| time.as_secs() > 1 << 30));
+        // Beyond what a Duration holds there is no estimate, which callers treat as forever
+        assert_eq!(estimate_wall_clock(u128::MAX, 1e9), None);
+    }
+
+    #[test]
+    fn test_needs_confirmation() {
+        let hour = LONG_RUN_THRESHOLD;
+        assert!(needs_confirmation(3.2 * 3600.0, hour, false, true));
+        assert!(!needs_confirmation(30.0, hour, false, true));
+        assert!(!needs_confirmation(3600.0, hour, false, true));
+        // --yes and non-interactive sessions never prompt
+        assert!(!needs_confirmation(3.2 * 3600.0, hour, true, true));
+        assert!(!needs_confirmation(3.2 * 3600.0, hour, false, false));
+        // An unmeasurable rate gives an infinite estimate, which still asks
+        assert!(needs_confirmation(f64::INFINITY, hour, false, true));
+    }
+
+    #[test]
+    fn test_secrets_on_stdout() {
+        assert!(secrets_on_stdout(true, false));
+        assert!(secrets_on_stdout(true, true));
+        // Piped or redirected output only carries secrets when asked to
+        assert!(!secrets_on_stdout(false, false));
+        assert!(secrets_on_stdout(false, true));
+    }
+
+    #[test]
+    fn test_is_affirmative() {
+        assert!(is_affirmative("y"));
+        assert!(is_affirmative("Yes\n"));
+        assert!(is_affirmative("  Y  "));
+        assert!(!is_affirmative(""));
+        assert!(!is_affirmative("n"));
+        assert!(!is_affirmative("yep"));
+    }
+
+    #[test]
+    fn test_measure_rate() {
+        let config = SearchConfig::new(VanityPattern::Prefix("A".to_string()), false, 1);
+        assert!(measure_rate(&config, Duration::from_millis(50)) > 0.0);
+    }
+
+    #[test]
+    fn test_bench_rates_are_positive() {
+        for (name, pattern) in bench_patterns() {
+            let config = SearchConfig::new(pattern, false, 1);
+            let (peak, stats) = bench_rates(
+                &config,
+                Duration::from_millis(150),
+                Duration::from_millis(50),
+            );
+            assert!(stats.iterations > 0, "{name} measured no candidates");
+            assert!(stats.rate > 0.0);
+            // No window can hold more candidates than the whole run
+            assert!(peak <= stats.iterations as f64 / 0.05, "{name}: {peak}");
+        }
+    }
+
+    #[test]
+    fn test_peak_rate_is_the_best_window() {
+        let start = Instant::now();
+        let at = |millis| start + Duration::from_millis(millis);
+        let mut peak = PeakRate::new(Duration::from_millis(100), start);
+
+        // Too soon to close the first window
+        peak.sample(at(50), 400);
+        assert_eq!(peak.peak, 0.0);
+        // 100, then 300, then 100 candidates per 100ms window
+        peak.sample(at(100), 100);
+        peak.sample(at(200), 400);
+        peak.sample(at(300), 500);
+        assert!((peak.peak - 3000.0).abs() < 1e-6, "{}", peak.peak);
+        // Well above the sustained 500 / 0.3s
+        assert!(peak.peak > 500.0 / 0.3 * 1.5);
+    }
+
+    /// Fast-mode candidates per second one thread must manage in a release build
+    ///
+    /// A typical core does about 41K/s (see the README); the floor leaves room for slow CI
+    /// runners while still catching a hot loop that got several times slower.
+    /// `VANITY_MIN_RATE` overrides it for machines known to be faster or slower.
+    const THROUGHPUT_FLOOR: f64 = 10_000.0;
+
+    #[test]
+    #[ignore = "timing-sensitive; run with cargo test --release -- --ignored throughput"]
+    fn test_fast_mode_throughput_floor() {
+        let floor = std::env::var("VANITY_MIN_RATE")
+            .ok()
+            .and_then(|rate| rate.parse().ok())
+            .unwrap_or(THROUGHPUT_FLOOR);
+        let config = SearchConfig::new(VanityPattern::Prefix("A".to_string()), false, 1);
+        // Warm up caches and the allocator before the measured window
+        measure_rate(&config, Duration::from_millis(200));
+        let rate = measure_rate(&config, Duration::from_secs(2));
+        assert!(
+            rate >= floor,
+            "fast mode ran at {rate:.0}/s per thread, below the {floor:.0}/s floor"
+        );
+    }
+
+    #[test]
+    fn test_mask_matches() {
+        assert!(mask_matches("Sola4Xyz", "So?a??"));
+        assert!(mask_matches("SoXaAB", "So?a??"));
+        assert!(!mask_matches("SoXbAB", "So?a??"));
+        assert!(!mask_matches("XoXaAB", "So?a??"));
+        // The address has to be at least as long as the mask
+        assert!(!mask_matches("So", "So?"));
+        assert!(mask_matches("anything", "???"));
+
+        let pattern = VanityPattern::Mask("So?a".to_string());
+        assert!(pattern.matches("SoZaaaa"));
+        assert!(!pattern.matches("aaaSoZa"));
+    }
+
+    #[test]
+    fn test_mask_difficulty_counts_fixed_characters() {
+        let mask = VanityPattern::Mask("So?a??".to_string());
+        assert_eq!(mask_fixed_chars("So?a??"), "Soa");
+        assert_eq!(mask.target_len(), 3);
+        assert_eq!(mask.expected_iterations(), 58u128.pow(3) / 2);
+        assert_eq!(
+            mask.expected_iterations(),
+            VanityPattern::Prefix("Soa".to_string()).expected_iterations()
+        );
+    }
+
+    #[test]
+    fn test_mask_closeness_and_validation() {
+        let mask = VanityPattern::Mask("So?a".to_string());
+        assert_eq!(mask.closeness("SoXa"), 3);
+        assert_eq!(mask.closeness("XoXa"), 2);
+        assert_eq!(mask.closeness("XXXX"), 0);
+
+        assert_eq!(validate_mask("So?a??"), Ok(()));
+        assert_eq!(validate_mask("??"), Err(PrefixError::Empty));
+        assert_eq!(validate_mask("S0?"), Err(PrefixError::InvalidChar('0')));
+        assert_eq!(validate_mask("S?é"), Err(PrefixError::NonAscii('é')));
+        let long_mask = format!("S{}", "?".repeat(MAX_ADDRESS_LEN));
+        assert_eq!(validate_mask(&long_mask), Err(PrefixError::TooLong(45)));
+    }
+
+    #[test]
+    fn test_bad_seed_length_is_an_error() {
+        let short = [7u8; 16];
+        assert!(matches!(
+            keypair_from_seed(&short),
+            Err(KeygenError::InvalidSeed(_))
+        ));
+        assert!(matches!(
+            pubkey_from_seed(&short),
+            Err(KeygenError::InvalidSeed(_))
+        ));
+        assert!(pubkey_from_seed(&[]).is_err());
+        assert!(pubkey_from_seed(&[7u8; 32]).is_ok());
+
+        // The search skips and counts a candidate whose seed makes no keypair
+        let progress = SearchProgress::default();
+        assert_eq!(candidate_address(&short, &progress), None);
+        assert_eq!(progress.skipped.load(Ordering::Relaxed), 1);
+        let address = candidate_address(&[7u8; 32], &progress).unwrap();
+        assert_eq!(address, address_of(&keypair_from_seed(&[7u8; 32]).unwrap()));
+        assert_eq!(progress.skipped.load(Ordering::Relaxed), 1);
+
+        // Well-formed 32-byte seeds never get skipped
+        let progress = SearchProgress::default();
+        let config = SearchConfig::new(VanityPattern::Prefix("2".to_string()), false, 1);
+        assert!(search_vanity(&config, &progress).wallet.is_some());
+        assert_eq!(progress.skipped.load(Ordering::Relaxed), 0);
+    }
+
+    #[test]
+    fn test_constant_time_starts_with() {
+        let address = "SunX4f2aBcDeFgHjK";
+        for prefix in ["", "S", "Sun", "SunX4f2a", address] {
+            assert!(constant_time_starts_with(address, prefix), "{prefix}");
+        }
+        for prefix in ["s", "Sum", "XSun", "SunX4f2b", "SunX4f2aBcDeFgHjKz"] {
+            assert!(!constant_time_starts_with(address, prefix), "{prefix}");
+        }
+        // A mismatch in the first or the last byte is caught alike
+        assert!(!constant_time_starts_with("AunX", "SunX"));
+        assert!(!constant_time_starts_with("SunY", "SunX"));
+        assert!(!constant_time_starts_with("", "S"));
+
+        let matcher = ConstantTimePrefixMatcher::new("Sun").unwrap();
+        let plain = VanityPattern::Prefix("Sun".to_string());
+        for candidate in ["SunXyz", "SuXyz", "Sxyz", "xSun", "Su"] {
+            assert_eq!(matcher.matches(candidate), plain.matches(candidate));
+            assert_eq!(
+                Matcher::closeness(&matcher, candidate),
+                plain.closeness(candidate)
+            );
+        }
+        assert_eq!(
+            Matcher::expected_iterations(&matcher),
+            Some(plain.expected_iterations())
+        );
+        assert!(ConstantTimePrefixMatcher::new("S0n").is_err());
+    }
+
+    #[test]
+    fn test_matcher_registry_lookup() {
+        let registry = MatcherRegistry::default();
+        assert_eq!(
+            registry.names(),
+            vec![
+                "any-prefix",
+                "charset",
+                "either",
+                "mask",
+                "prefix",
+                "regex",
+                "smart-case",
+                "suffix"
+            ]
+        );
+
+        let prefix = registry.build("prefix", "Sun").unwrap();
+        assert!(prefix.matches("SunXyz"));
+        assert!(!prefix.matches("XyzSun"));
+        assert_eq!(prefix.expected_iterations(), Some(58u128.pow(3) / 2));
+
+        let suffix = registry.build("suffix", "Sun").unwrap();
+        assert!(suffix.matches("XyzSun"));
+        assert_eq!(suffix.closeness("XyzSun"), 3);
+
+        let mask = registry.build("mask", "S?n").unwrap();
+        assert!(mask.matches("SXnyz"));
+
+        let regex = registry.build("regex", "^[1-9]{2}.*Z$").unwrap();
+        assert!(regex.matches("42abcZ"));
+        assert!(!regex.matches("4abcZ"));
+        // 9 of 58 characters at each of two positions, then one fixed character
+        assert_eq!(
+            regex.expected_iterations(),
+            Some((58.0 / 9.0 * 58.0 / 9.0 * 58.0 / 2.0) as u128)
+        );
+    }
+
+    #[test]
+    fn test_regex_expected_iterations() {
+        assert_eq!(regex_expected_iterations("Sun$"), Some(58u128.pow(3) / 2));
+        assert_eq!(regex_expected_iterations("^(?i)s"), Some(14));
+        // An unanchored run has a chance at each of the 42 places it could start
+        assert_eq!(
+            regex_expected_iterations("Sun"),
+            Some(58u128.pow(3) / 42 / 2)
+        );
+        assert_eq!(regex_expected_iterations("^0"), Some(u128::MAX));
+        // Alternatives, fixed lengths and constrained gaps have no estimate
+        assert_eq!(regex_expected_iterations("^(Sun|Moon)"), None);
+        assert_eq!(regex_expected_iterations("^Sun$"), None);
+        assert_eq!(regex_expected_iterations("^S[0-9]*9$"), None);
+    }
+
+    #[test]
+    fn test_matcher_registry_errors() {
+        let registry = MatcherRegistry::default();
+        assert_eq!(
+            registry.build("glob", "S*").unwrap_err(),
+            MatcherError::Unknown("glob".to_string())
+        );
+        assert!(matches!(
+            registry.build("prefix", "S0l"),
+            Err(MatcherError::InvalidArgument(_))
+        ));
+        assert!(matches!(
+            registry.build("regex", "(unclosed"),
+            Err(MatcherError::InvalidArgument(_))
+        ));
+        assert!(MatcherRegistry::empty().build("prefix", "A").is_err());
+    }
+
+    #[test]
+    fn test_registering_a_custom_matcher() {
+        #[derive(Debug)]
+        struct Palindrome;
+
+        impl Matcher for Palindrome {
+            fn matches(&self, address: &str) -> bool {
+                address.chars().eq(address.chars().rev())
+            }
+        }
+
+        let mut registry = MatcherRegistry::empty();
+        registry.register("palindrome", |_| Ok(Box::new(Palindrome)));
+
+        let matcher = registry.build("palindrome", "").unwrap();
+        assert!(matcher.matches("abcba"));
+        assert!(!matcher.matches("abc"));
+        assert_eq!(matcher.closeness("abc"), 0);
+    }
+
+    #[test]
+    fn test_luckiest_search_keeps_best_luck_factor() {
+        let config = SearchConfig::new(VanityPattern::Prefix("2".to_string()), false, 1);
+        let (best, finds) = luckiest_search(&config, Duration::from_millis(300));
+        let best = best.expect("a one-character prefix is found many times in 300ms");
+        assert!(finds.len() > 1);
+
+        let top = finds
+            .iter()
+            .map(|stats| stats.luck_factor)
+            .fold(f64::MIN, f64::max);
+        assert_eq!(best.stats.luck_factor, top);
+        assert!(best.wallet.public_key.starts_with('2'));
+        assert!(keypair_matches_pubkey(
+            &best.wallet.keypair,
+            &best.wallet.public_key
+        ));
+    }
+
+    #[test]
+    fn test_rarest_of_simultaneous_matches() {
+        let target = VanityPattern::Prefix("SunRise".to_string());
+        let crafted = |address: &str| -> Candidate { (None, [0; 32], address.to_string()) };
+        let matches = vec![
+            crafted("Sunabc"),
+            crafted("SunRab"),
+            crafted("SunRaz"),
+            crafted("SunXyz"),
+        ];
+        let picked = rarest(matches, |(_, _, address)| target.closeness(address)).unwrap();
+        // Two share the longest run toward the target; the earlier one wins
+        assert_eq!(picked.2, "SunRab");
+        assert!(rarest(Vec::<Candidate>::new(), |_| 0).is_none());
+    }
+
+    #[test]
+    fn test_results_keep_every_concurrent_push() {
+        const THREADS: usize = 16;
+        const PUSHES: usize = 5_000;
+        let results = Results::default();
+        assert!(results.is_empty());
+
+        std::thread::scope(|scope| {
+            for thread in 0..THREADS {
+                let results = &results;
+                scope.spawn(move || {
+                    for push in 0..PUSHES {
+                        results.push((thread, push));
+                    }
+                });
+            }
+        });
+        assert_eq!(results.len(), THREADS * PUSHES);
+
+        let found = results.into_inner();
+        let unique: std::collections::HashSet<&(usize, usize)> = found.iter().collect();
+        assert_eq!(unique.len(), THREADS * PUSHES);
+        // Each thread's pushes stay in the order it made them
+        for thread in 0..THREADS {
+            let pushes: Vec<usize> = found
+                .iter()
+                .filter(|(from, _)| *from == thread)
+                .map(|&(_, push)| push)
+                .collect();
+            assert_eq!(pushes, (0..PUSHES).collect::<Vec<_>>());
+        }
+    }
+
+    #[test]
+    fn test_search_prefers_rarest_within_grace_window() {
+        let config = SearchConfig {
+            prefer_rarest: Some(PreferRarest {
+                target: Arc::new(VanityPattern::Prefix("22".to_string())),
+                window: Duration::from_millis(50),
+            }),
+            ..SearchConfig::new(VanityPattern::Prefix("2".to_string()), false, 2)
+        };
+        let wallet = search_vanity(&config, &SearchProgress::default())
+            .wallet
+            .unwrap();
+        assert!(wallet.public_key.starts_with('2'));
+        assert!(keypair_matches_pubkey(&wallet.keypair, &wallet.public_key));
+    }
+
+    #[test]
+    fn test_search_with_registry_matcher() {
+        let matcher = MatcherRegistry::default().build("suffix", "2").unwrap();
+        let config = SearchConfig {
+            pattern: Arc::from(matcher),
+            ..SearchConfig::new(VanityPattern::Prefix("1".to_string()), false, 2)
+        };
+        let wallet = search_vanity(&config, &SearchProgress::default())
+            .wallet
+            .unwrap();
+        assert!(wallet.public_key.ends_with('2'));
+    }
+
+    #[test]
+    fn test_probability_found_by() {
+        assert_eq!(probability_found_by(0, 1_000.0), 0.0);
+        // One expected run's worth of candidates: 1 - 1/e
+        let one_run = probability_found_by(1_000, 1_000.0);
+        assert!((one_run - (1.0 - (-1f64).exp())).abs() < 1e-12);
+        assert!((probability_found_by(3_000, 1_000.0) - 0.950_212_9).abs() < 1e-6);
+        // Tiny fractions stay accurate rather than rounding to zero
+        let sliver = probability_found_by(1, 1e12);
+        assert!((sliver - 1e-12).abs() < 1e-20);
+        assert!(probability_found_by(u128::MAX, 1.0) == 1.0);
+        assert_eq!(probability_found_by(5, 0.0), 1.0);
+
+        // Agrees with iterations_for_probability at the same odds
+        let n = iterations_for_probability(1.0 / 58f64.powi(4), 0.5).unwrap();
+        assert!((probability_found_by(n, 58f64.powi(4)) - 0.5).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_iterations_for_probability() {
+        let chance = 1.0 / 58.0;
+        // ln(1 - P) / ln(1 - 1/58)
+        assert_eq!(iterations_for_probability(chance, 0.5), Some(40));
+        assert_eq!(iterations_for_probability(chance, 0.9), Some(133));
+        assert_eq!(iterations_for_probability(chance, 0.99), Some(265));
+
+        // Each answer really reaches the requested probability, and one fewer doesn't
+        for probability in [0.5, 0.9, 0.99] {
+            let n = iterations_for_probability(chance, probability).unwrap() as i32;
+            assert!(1.0 - (1.0 - chance).powi(n) >= probability);
+            assert!(1.0 - (1.0 - chance).powi(n - 1) < probability);
+        }
+
+        // Rare matches: about ln(2) / p candidates for even odds
+        let rare = 1.0 / 58f64.powi(6);
+        let n = iterations_for_probability(rare, 0.5).unwrap() as f64;
+        assert!((n * rare - 2f64.ln()).abs() < 1e-6);
+
+        assert_eq!(iterations_for_probability(1.0, 0.9), Some(1));
+        assert_eq!(iterations_for_probability(chance, 0.0), Some(0));
+        assert_eq!(iterations_for_probability(0.0, 0.9), None);
+        assert_eq!(iterations_for_probability(chance, 1.0), None);
+        assert_eq!(iterations_for_probability(chance, -0.1), None);
+    }
+
+    #[test]
+    fn test_cost_estimate() {
+        // A leading 1 is one zero byte, exactly 1 in 256, so 256 candidates/s takes a second
+        let (time, kwh) = cost_estimate("1", 256.0, 3600.0);
+        assert_eq!(time, Duration::from_secs(1));
+        // 3600 W for one second is one watt-hour
+        assert!((kwh - 0.001).abs() < 1e-12);
+
+        // Twice the rate halves both; twice the power doubles only the energy
+        let (time, kwh) = cost_estimate("Sun", 100_000.0, 200.0);
+        let (faster, faster_kwh) = cost_estimate("Sun", 200_000.0, 200.0);
+        let (_, hungrier_kwh) = cost_estimate("Sun", 100_000.0, 400.0);
+        assert!((time.as_secs_f64() / faster.as_secs_f64() - 2.0).abs() < 1e-9);
+        assert!((kwh / faster_kwh - 2.0).abs() < 1e-9);
+        assert!((hungrier_kwh / kwh - 2.0).abs() < 1e-9);
+        let hours = time.as_secs_f64() / 3600.0;
+        assert!((kwh - 0.2 * hours).abs() < 1e-9);
+
+        assert_eq!(
+            cost_estimate("Sun", 0.0, 200.0),
+            (Duration::MAX, f64::INFINITY)
+        );
+        assert_eq!(
+            cost_estimate("0", 1000.0, 200.0),
+            (Duration::MAX, f64::INFINITY)
+        );
+    }
+
+    #[test]
+    fn test_check_writable() {
+        let dir = test_dir("writable");
+        ensure_output_dir(&dir).unwrap();
+        check_writable(&dir).unwrap();
+        // The probe file is cleaned up
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 0);
+
+        assert!(check_writable(&dir.join("missing")).is_err());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_save_or_dump_falls_back_on_write_failure() {
+        let dir = test_dir("dump");
+        // The parent directory doesn't exist, so the write fails
+        let path = dir.join("missing").join("wallet_output.txt");
+        let mut stdout = Vec::new();
+
+        let result = save_or_dump(&path, "Public Key: Abc123", &mut stdout);
+
+        assert!(result.is_err());
+        let printed = String::from_utf8(stdout).unwrap();
+        assert!(printed.contains("Unable to save"));
+        assert!(printed.contains("Public Key: Abc123"));
+    }
+
+    #[test]
+    fn test_save_or_dump_writes_file() {
+        let dir = test_dir("save");
+        ensure_output_dir(&dir).unwrap();
+        let path = dir.join("wallet_output.txt");
+        let mut stdout = Vec::new();
+
+        save_or_dump(&path, "Public Key: Abc123", &mut stdout).unwrap();
+
+        assert!(stdout.is_empty());
+        assert_eq!(fs::read_to_string(&path).unwrap(), "Public Key: Abc123");
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    /// Each wallet is complete on disk before the next one is saved, nothing is held back to be
+    /// written at the end. Whether `sync_all` got the bytes past the OS cache would take a power
+    /// cut to show, so this checks what a stopped process leaves behind, not the sync itself.
+    #[test]
+    fn test_batch_files_survive_a_mid_run_stop() {
+        let dir = test_dir("batch");
+        let records: Vec<WalletRecord> = (0..3).map(|_| sample_record()).collect();
+
+        for (saved, record) in records.iter().enumerate() {
+            let path = dir.join(wallet_file_name(&record.public_key, "output.json"));
+            let json = serde_json::to_string(record).unwrap();
+            save_or_dump(&path, &json, &mut io::sink()).unwrap();
+
+            // A run stopped here leaves every wallet so far complete on disk
+            for earlier in &records[..=saved] {
+                let path = dir.join(wallet_file_name(&earlier.public_key, "output.json"));
+                let read: WalletRecord =
+                    serde_json::from_str(&fs::read_to_string(path).unwrap()).unwrap();
+                assert_eq!(read.secret_key, earlier.secret_key);
+            }
+        }
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), records.len());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_mnemonic_entropy_hex_round_trips() {
+        let (mnemonic, _) = generate_keypair(true).unwrap();
+        let mnemonic = mnemonic.unwrap();
+
+        let hex = mnemonic_entropy_hex(&mnemonic).unwrap();
+        assert_eq!(hex.len(), 32); // 16 bytes for 12 words
+
+        let entropy: Vec<u8> = (0..hex.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&hex[i..i + 2], 16).unwrap())
+            .collect();
+        let rebuilt = Mnemonic::from_entropy_in(Language::English, &entropy).unwrap();
+        assert_eq!(rebuilt.to_string(), mnemonic);
+
+        // BIP39 test vector: all-zero entropy
+        let zero = "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about";
+        assert_eq!(mnemonic_entropy_hex(zero).unwrap(), "0".repeat(32));
+        assert!(mnemonic_entropy_hex("not a real mnemonic").is_err());
+    }
+
+    #[test]
+    fn test_charset_matcher() {
+        let matcher = CharsetMatcher::from_specs(&["0=ABC", "2=123"]).unwrap();
+        assert_eq!(matcher.len(), 2);
+        assert!(matcher.matches("AX1yz"));
+        assert!(matcher.matches("CZ3yz"));
+        assert!(!matcher.matches("DX1yz"));
+        assert!(!matcher.matches("AX4yz"));
+        assert!(!matcher.matches("A"));
+
+        assert_eq!(matcher.closeness("AX4yz"), 1);
+        assert_eq!(matcher.closeness("DX4yz"), 0);
+
+        // Order of the specs doesn't matter
+        let reversed = CharsetMatcher::from_specs(&["2=321", "0=CBA"]).unwrap();
+        assert_eq!(reversed, matcher);
+    }
+
+    #[test]
+    fn test_charset_matcher_difficulty() {
+        // (58/3) * (58/3) / 2
+        let matcher = CharsetMatcher::from_specs(&["0=ABC", "1=123"]).unwrap();
+        assert_eq!(matcher.expected_iterations(), Some(186));
+
+        // A single allowed character is as hard as a fixed prefix character
+        let single = CharsetMatcher::from_specs(&["0=A", "1=B"]).unwrap();
+        assert_eq!(single.expected_iterations(), Some(58 * 58 / 2));
+        assert!((single.difficulty_bits() - 2.0 * 58f64.log2()).abs() < 1e-9);
+        // Bits come from the set sizes, not the length of the spec text
+        assert!((matcher.difficulty_bits() - 2.0 * (58.0f64 / 3.0).log2()).abs() < 1e-9);
+
+        // Repeated characters don't make a set larger
+        let repeated = CharsetMatcher::from_specs(&["0=AAB"]).unwrap();
+        assert_eq!(repeated.expected_iterations(), Some(14));
+
+        // A set may be longer than an address: here every Base58 character but `1`
+        let all_but_one = "23456789ABCDEFGHJKLMNPQRSTUVWXYZabcdefghijkmnopqrstuvwxyz";
+        let broad = CharsetMatcher::from_specs(&[format!("0={all_but_one}")]).unwrap();
+        assert!(broad.matches("Sun"));
+        assert!(!broad.matches("1un"));
+    }
+
+    #[test]
+    fn test_charset_matcher_validation() {
+        let invalid = |specs: &[&str]| {
+            matches!(
+                CharsetMatcher::from_specs(specs),
+                Err(MatcherError::InvalidArgument(_))
+            )
+        };
+        assert!(invalid(&["0=A0"]));
+        assert!(invalid(&["0=Aé"]));
+        assert!(invalid(&["x=ABC"]));
+        assert!(invalid(&["ABC"]));
+        assert!(invalid(&["0="]));
+        assert!(invalid(&["44=A"]));
+        assert!(invalid(&["0=A", "0=B"]));
+        assert!(invalid(&[]));
+
+        let registry = MatcherRegistry::default();
+        assert!(registry.build("charset", "0=ABC,1=123").is_ok());
+        assert!(registry.build("charset", "0=ABC,1=0").is_err());
+    }
+
+    #[test]
+    fn test_profile_keygen() {
+        let mut rng = rand::rngs::StdRng::seed_from_u64(7);
+        let fast = profile_keygen(&mut rng, false, &DerivationPath::default(), 20);
+        assert_eq!(fast.iterations, 20);
+        let names: Vec<_> = fast.stages().iter().map(|(name, _)| *name).collect();
+        assert_eq!(names, ["Entropy", "Public key", "Base58 encoding"]);
+        assert_eq!(fast.mnemonic, Duration::ZERO);
+        assert!(fast.public_key > Duration::ZERO);
+
+        let slow = profile_keygen(&mut rng, true, &DerivationPath::default(), 2);
+        assert_eq!(slow.stages().len(), 6);
+        assert!(slow.seed > Duration::ZERO);
+        assert_eq!(
+            slow.total(),
+            slow.stages().iter().map(|(_, time)| *time).sum()
+        );
+        assert_eq!(slow.per_wallet(), slow.total().div_f64(2.0));
+    }
+
+    #[test]
+    fn test_dedup_prefixes() {
+        assert_eq!(dedup_prefixes(&["Sol", "Sol"]), vec!["Sol"]);
+        assert_eq!(dedup_prefixes(&["Sol", "So"]), vec!["So"]);
+        assert_eq!(
+            dedup_prefixes(&["Sun", "So", "Sol", "Sun"]),
+            vec!["Sun", "So"]
+        );
+        // Case matters in Base58, so these are different prefixes
+        assert_eq!(dedup_prefixes(&["sol", "Sol"]), vec!["sol", "Sol"]);
+
+        assert_eq!(
+            prefix_overlaps(&["So", "Sol", "Sola", "So"]),
+            vec![
+                PrefixOverlap::Subsumed {
+                    redundant: "Sol".to_string(),
+                    by: "So".to_string()
+                },
+                PrefixOverlap::Subsumed {
+                    redundant: "Sola".to_string(),
+                    by: "So".to_string()
+                },
+                PrefixOverlap::Duplicate("So".to_string()),
+            ]
+        );
+        assert!(prefix_overlaps(&["Sun", "Moon"]).is_empty());
+    }
+
+    #[test]
+    fn test_any_prefix_matcher() {
+        let matcher = AnyPrefixMatcher::new(&["Sun", "Moon", "Sunny"]).unwrap();
+        assert_eq!(matcher.prefixes(), ["Sun", "Moon"]);
+        assert!(matcher.matches("SunXyz"));
+        assert!(matcher.matches("MoonXyz"));
+        assert!(!matcher.matches("XyzSun"));
+        assert_eq!(matcher.match_ranges("MoonXyz"), vec![0..4]);
+        assert_eq!(matcher.closeness("MooXyz"), 3);
+
+        // Dropping the subsumed "Sunny" leaves the estimate at what the two real prefixes give
+        let chance = 58f64.powi(-3) + 58f64.powi(-4);
+        assert_eq!(
+            matcher.expected_iterations(),
+            Some((1.0 / chance / 2.0) as u128)
+        );
+
+        let registry = MatcherRegistry::default();
+        assert!(registry
+            .build("any-prefix", "Sun, Moon")
+            .unwrap()
+            .matches("MoonXyz"));
+        assert!(registry.build("any-prefix", "Sun,M0on").is_err());
+        assert!(registry.build("any-prefix", "Sun,").is_err());
+    }
+
+    #[test]
+    fn test_join_within_gives_up_on_a_stuck_thread() {
+        let release = Arc::new(AtomicBool::new(false));
+        let stuck = {
+            let release = Arc::clone(&release);
+            std::thread::spawn(move || {
+                while !release.load(Ordering::Relaxed) {
+                    std::thread::sleep(Duration::from_millis(5));
+                }
+            })
+        };
+        let start = Instant::now();
+        assert_eq!(
+            join_within(stuck, Duration::from_millis(50)),
+            Err(JoinError::TimedOut)
+        );
+        assert!(start.elapsed() < Duration::from_secs(1));
+        release.store(true, Ordering::Relaxed);
+
+        let panicked = std::thread::spawn(|| panic!("reporter bug"));
+        assert_eq!(
+            join_within(panicked, Duration::from_secs(5)),
+            Err(JoinError::Panicked)
+        );
+        let finished = std::thread::spawn(|| 7);
+        assert_eq!(join_within(finished, Duration::from_secs(5)), Ok(7));
+    }
+
+    #[test]
+    fn test_search_finishes_when_the_reporter_hangs() {
+        let config = SearchConfig::new(VanityPattern::Prefix("1".to_string()), false, 1);
+        let release = Arc::new(AtomicBool::new(false));
+        // A reporter that never notices the search is done
+        let stuck = {
+            let release = Arc::clone(&release);
+            std::thread::spawn(move || {
+                while !release.load(Ordering::Relaxed) {
+                    std::thread::sleep(Duration::from_millis(5));
+                }
+            })
+        };
+        let start = Instant::now();
+        let (result, reporter) = search_with_reporter(
+            &config,
+            &SearchProgress::default(),
+            stuck,
+            Duration::from_millis(100),
+        );
+        assert!(result.wallet.unwrap().public_key.starts_with('1'));
+        assert_eq!(reporter, Err(JoinError::TimedOut));
+        assert!(start.elapsed() < Duration::from_secs(10));
+        release.store(true, Ordering::Relaxed);
+
+        let panicked = std::thread::spawn(|| panic!("reporter bug"));
+        let (result, reporter) = search_with_reporter(
+            &config,
+            &SearchProgress::default(),
+            panicked,
+            REPORTER_JOIN_TIMEOUT,
+        );
+        assert!(result.wallet.is_some());
+        assert_eq!(reporter, Err(JoinError::Panicked));
+    }
+
+    #[test]
+    fn test_case_variants() {
+        assert_eq!(case_variants("sun"), ["sun", "Sun"]);
+        // Already capitalized, or all capitals: the typed spelling is the only one
+        assert_eq!(case_variants("Sun"), ["Sun"]);
+        assert_eq!(case_variants("SUN"), ["SUN"]);
+        assert_eq!(case_variants("sUN"), ["sUN", "SUN"]);
+        // `I`, `O` and `l` aren't Base58, so spellings using them drop out
+        assert_eq!(case_variants("ice"), ["ice"]);
+        assert_eq!(case_variants("lamp"), ["Lamp"]);
+        assert_eq!(case_variants("oak"), ["oak"]);
+        assert!(case_variants("Sol").is_empty());
+        assert!(case_variants("").is_empty());
+    }
+
+    #[test]
+    fn test_case_variant_matcher() {
+        let matcher = CaseVariantMatcher::new("sun").unwrap();
+        assert_eq!(matcher.variants(), ["sun", "Sun"]);
+        assert!(matcher.matches("sunXyz"));
+        assert!(matcher.matches("SunXyz"));
+        assert!(!matcher.matches("SUNXyz"));
+        assert_eq!(matcher.variant_of("SunXyz"), Some("Sun"));
+        assert_eq!(matcher.variant_of("SUNXyz"), None);
+        assert_eq!(matcher.match_ranges("sunXyz"), vec![0..3]);
+
+        // Two spellings of a 3-character prefix: twice the odds, half the work
+        assert_eq!(
+            matcher.expected_iterations(),
+            Some((58f64.powi(3) / 2.0 / 2.0) as u128)
+        );
+
+        assert!(CaseVariantMatcher::new("Su1").is_err());
+        assert!(CaseVariantMatcher::new("Sol").is_err());
+        assert!(CaseVariantMatcher::new("").is_err());
+        assert!(MatcherRegistry::default()
+            .build("smart-case", "sun")
+            .unwrap()
+            .matches("SunXyz"));
+    }
+
+    #[test]
+    fn test_smart_case_result_records_matched_spelling() {
+        let matcher = CaseVariantMatcher::new("ab").unwrap();
+        let config = SearchConfig::new(matcher.clone(), false, 2);
+        let result = search_vanity(&config, &SearchProgress::default());
+        let wallet = result.wallet.expect("a two-letter prefix is found quickly");
+
+        let found = NormalizedMatch::for_case_variant("ab", &matcher, &wallet.public_key).unwrap();
+        assert_eq!(found.requested_pattern, "ab");
+        assert!(["ab", "Ab"].contains(&found.matched_pattern.as_str()));
+        assert!(wallet.public_key.starts_with(&found.matched_pattern));
+        assert_eq!(found.actual_address, wallet.public_key);
+
+        // Both the JSON and the text file keep the pair
+        let record =
+            WalletRecord::new(&wallet, &result.stats).with_pattern_match(Some(found.clone()));
+        let json = serde_json::to_string(&record).unwrap();
+        assert_eq!(
+            parse_saved_wallet(&json).unwrap().pattern_match,
+            Some(found.clone())
+        );
+        let text = record.to_text_file(None);
+        assert!(text.contains(&format!("Matched Pattern: {}\n", found.matched_pattern)));
+        assert_eq!(
+            parse_saved_wallet(&text).unwrap().pattern_match,
+            Some(found)
+        );
+        assert_eq!(verify_saved_wallet(&text, &wallet.keypair), Ok(()));
+
+        // An address that doesn't start with any spelling gets no match to save
+        assert_eq!(
+            NormalizedMatch::for_case_variant("ab", &matcher, "ABxyz"),
+            None
+        );
+        assert_eq!(
+            NormalizedMatch::for_case_variant("ab", &matcher, "xab"),
+            None
+        );
+        assert_eq!(WalletRecord::from_wallet(&wallet).pattern_match, None);
+    }
+
+    /// Wallet `search_seeded` finds for prefix "1", fast mode, seed 42
+    const PINNED_SEEDED_PUBKEY: &str = "14LopVW3vYcHT8h1c4Fw4bRQ5yLaVtGZwu2Yn62hsBWS";
+
+    #[test]
+    fn test_seen_set_skips_earlier_finds() {
+        let dir = test_dir("seen-set");
+        let path = dir.join("seen.txt");
+        assert!(SeenSet::load(&path).unwrap().is_empty());
+        fs::write(&path, format!("\n{PINNED_SEEDED_PUBKEY}\n  \n")).unwrap();
+
+        let seen = Arc::new(SeenSet::load(&path).unwrap());
+        assert_eq!(seen.len(), 1);
+        let mut config = SearchConfig::new(VanityPattern::Prefix("1".to_string()), false, 1);
+        config.entropy = EntropySource::Seeded(42);
+        config.seen = Some(Arc::clone(&seen));
+
+        // The seeded search would find the pinned wallet first; with it seen, it moves past it
+        let progress = SearchProgress::default();
+        let wallet = search_vanity(&config, &progress).wallet.unwrap();
+        assert_ne!(wallet.public_key, PINNED_SEEDED_PUBKEY);
+        assert!(wallet.public_key.starts_with('1'));
+        assert_eq!(progress.duplicates.load(Ordering::Relaxed), 1);
+
+        // Recording appends one line per new key, and a repeat is a no-op
+        assert!(seen.record(&path, &wallet.public_key).unwrap());
+        assert!(!seen.record(&path, &wallet.public_key).unwrap());
+        let reloaded = SeenSet::load(&path).unwrap();
+        assert_eq!(reloaded.len(), 2);
+        assert!(reloaded.contains(&wallet.public_key));
+        assert_eq!(
+            fs::read_to_string(&path).unwrap(),
+            format!("\n{PINNED_SEEDED_PUBKEY}\n  \n{}\n", wallet.public_key)
+        );
+
+        // A second run with the updated set reports neither
+        let again = search_vanity(&config, &SearchProgress::default())
+            .wallet
+            .unwrap();
+        assert!(!reloaded.contains(&again.public_key));
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_seeded_search_end_to_end() {
+        let search = || search_seeded(VanityPattern::Prefix("1".to_string()), false, 42);
+        let first = search();
+        let second = search();
+
+        let wallet = first
+            .wallet
+            .expect("an unbounded search always finds a match");
+        let again = second.wallet.unwrap();
+        assert_eq!(wallet.public_key, PINNED_SEEDED_PUBKEY);
+        assert_eq!(again.public_key, wallet.public_key);
+        assert_eq!(first.stats.iterations, second.stats.iterations);
+        assert!(keypair_matches_pubkey(&wallet.keypair, &wallet.public_key));
+
+        // Through the same record the CLI writes to disk
+        let record = WalletRecord::new(&wallet, &first.stats);
+        let json = serde_json::to_string(&record).unwrap();
+        let mut parsed: WalletRecord = serde_json::from_str(&json).unwrap();
+        // Timings are wall-clock floats and may lose a last digit through JSON; everything else,
+        // the counts included, must come back exactly
+        let (got, want) = (
+            parsed.statistics.as_mut().unwrap(),
+            record.statistics.as_ref().unwrap(),
+        );
+        for (got, want) in [
+            (&mut got.elapsed_seconds, want.elapsed_seconds),
+            (&mut got.iterations_per_second, want.iterations_per_second),
+            (&mut got.luck_factor, want.luck_factor),
+        ] {
+            assert!((*got - want).abs() <= want.abs() * 1e-12, "{got} != {want}");
+            *got = want;
+        }
+        assert_eq!(parsed, record);
+        assert_eq!(parsed.public_key, PINNED_SEEDED_PUBKEY);
+    }
+
+    #[test]
+    fn test_fast_mode_secret_rebuilds_matched_pubkey() {
+        let result = search_seeded(VanityPattern::Prefix("A".into()), false, 7);
+        let record = WalletRecord::new(&result.wallet.unwrap(), &result.stats);
+        assert!(record.mnemonic.is_none());
+
+        // The Base58 secret is what wallets take as a private key import
+        let secret = bs58::decode(&record.secret_key).into_vec().unwrap();
+        let imported = Keypair::try_from(secret.as_slice()).unwrap();
+        assert_eq!(address_of(&imported), record.public_key);
+        assert!(record.public_key.starts_with('A'));
+
+        let from_json = Keypair::try_from(record.keypair_json.as_slice()).unwrap();
+        assert_eq!(from_json.pubkey(), imported.pubkey());
+    }
+
+    #[test]
+    fn test_saved_files_recover_the_exact_keypair() {
+        let fast = search_seeded(VanityPattern::Prefix("A".into()), false, 3)
+            .wallet
+            .unwrap();
+        let path: DerivationPath = "m/44'/501'/7'/0'".parse().unwrap();
+        let (mnemonic, keypair) = generate_keypair_from(&mut OsRng, true, &path).unwrap();
+        let with_mnemonic = VanityWallet {
+            mnemonic,
+            public_key: address_of(&keypair),
+            keypair,
+        };
+
+        for wallet in [&fast, &with_mnemonic] {
+            let record = WalletRecord::from_wallet(wallet).with_derivation_path(&path);
+            let text = record.to_text_file(Some("Statistics:\n"));
+            let json = serde_json::to_string_pretty(&record).unwrap();
+            for contents in [&text, &json] {
+                assert_eq!(verify_saved_wallet(contents, &wallet.keypair), Ok(()));
+                // A file that recovers a different key must never pass
+                assert_eq!(
+                    verify_saved_wallet(contents, &Keypair::new()),
+                    Err(RecoveryError::SecretMismatch)
+                );
+            }
+        }
+
+        // Saved at the wrong path, the mnemonic derives some other key
+        let record = WalletRecord::from_wallet(&with_mnemonic)
+            .with_derivation_path(&DerivationPath::default());
+        assert_eq!(
+            verify_saved_wallet(&record.to_text_file(None), &with_mnemonic.keypair),
+            Err(RecoveryError::MnemonicMismatch)
+        );
+
+        let mut record = WalletRecord::from_wallet(&fast);
+        record.public_key = with_mnemonic.public_key.clone();
+        assert_eq!(
+            verify_saved_wallet(&record.to_text_file(None), &fast.keypair),
+            Err(RecoveryError::PublicKeyMismatch)
+        );
+        assert!(matches!(
+            verify_saved_wallet("Public Key: abc\n", &fast.keypair),
+            Err(RecoveryError::Unreadable(_))
+        ));
+    }
+
+    #[test]
+    fn test_seeded_search_with_mnemonic() {
+        let search = || search_seeded(VanityPattern::Prefix("1".to_string()), true, 42);
+        let first = search().wallet.unwrap();
+        let second = search().wallet.unwrap();
+
+        assert!(first.mnemonic.is_some());
+        assert_eq!(first.mnemonic, second.mnemonic);
+        assert_eq!(first.public_key, second.public_key);
+    }
+
+    #[test]
+    fn test_color_mode_decision() {
+        // Auto follows the terminal, and NO_COLOR wins over it
+        assert!(ColorMode::Auto.enabled(true, false));
+        assert!(!ColorMode::Auto.enabled(false, false));
+        assert!(!ColorMode::Auto.enabled(true, true));
+
+        // Explicit choices ignore both
+        assert!(ColorMode::Always.enabled(false, true));
+        assert!(!ColorMode::Never.enabled(true, false));
+
+        assert_eq!("Always".parse(), Ok(ColorMode::Always));
+        assert_eq!("never".parse(), Ok(ColorMode::Never));
+        assert!("sometimes".parse::<ColorMode>().is_err());
+    }
+
+    #[test]
+    fn test_banner_decision() {
+        assert_eq!(Banner::choose(false, false), Banner::Standard);
+        assert_eq!(Banner::choose(false, true), Banner::Detailed);
+        // --no-banner wins over --verbose
+        assert_eq!(Banner::choose(true, false), Banner::Hidden);
+        assert_eq!(Banner::choose(true, true), Banner::Hidden);
+
+        assert!(Banner::Standard.is_shown() && Banner::Detailed.is_shown());
+        assert!(!Banner::Hidden.is_shown());
+    }
+
+    #[test]
+    fn test_match_ranges() {
+        let address = "SunXyz123Abc";
+        let ranges = |matcher: &dyn Matcher| -> Vec<(usize, usize)> {
+            matcher
+                .match_ranges(address)
+                .into_iter()
+                .map(|range| (range.start, range.end))
+                .collect()
+        };
+
+        assert_eq!(ranges(&VanityPattern::Prefix("Sun".into())), vec![(0, 3)]);
+        assert_eq!(ranges(&VanityPattern::Suffix("Abc".into())), vec![(9, 12)]);
+        assert_eq!(
+            ranges(&VanityPattern::EitherEnd("Abc".into())),
+            vec![(9, 12)]
+        );
+        assert_eq!(
+            ranges(&VanityPattern::Mask("S?n?y".into())),
+            vec![(0, 1), (2, 3), (4, 5)]
+        );
+        assert_eq!(
+            ranges(&RegexMatcher(regex::Regex::new("[0-9]+").unwrap())),
+            vec![(6, 9)]
+        );
+        assert_eq!(
+            ranges(&CharsetMatcher::from_specs(&["1=tu", "3=X"]).unwrap()),
+            vec![(1, 2), (3, 4)]
+        );
+
+        // Nothing to highlight without a match
+        assert!(ranges(&VanityPattern::Prefix("Moon".into())).is_empty());
+    }
+
+    #[test]
+    fn test_key_entropy_flags_zero_mnemonic() {
+        let zero = "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about";
+        let wallet = |mnemonic: Option<&str>, keypair: Keypair| VanityWallet {
+            mnemonic: mnemonic.map(str::to_string),
+            public_key: address_of(&keypair),
+            keypair,
+        };
+
+        let weak = wallet(Some(zero), Keypair::new());
+        assert_eq!(weak.key_entropy(), vec![0u8; 16]);
+        assert!(entropy::looks_low_entropy(&weak.key_entropy()));
+
+        // Without a mnemonic the keypair seed itself is checked
+        let zero_seed = wallet(None, keypair_from_seed(&[0u8; 32]).unwrap());
+        assert!(entropy::looks_low_entropy(&zero_seed.key_entropy()));
+
+        let (mnemonic, keypair) = generate_keypair(true).unwrap();
+        let strong = wallet(mnemonic.as_deref(), keypair);
+        assert_eq!(strong.key_entropy().len(), 16);
+        assert!(!entropy::looks_low_entropy(&strong.key_entropy()));
+    }
+
+    #[test]
+    fn test_derivation_path_parsing() {
+        let standard: DerivationPath = "m/44'/501'/0'/0'".parse().unwrap();
+        assert_eq!(standard, DerivationPath::default());
+        assert_eq!(standard.to_string(), SOLANA_DERIVATION_PATH);
+
+        let custom: DerivationPath = "m/44'/501'/5'/0'/3'".parse().unwrap();
+        assert_eq!(custom.to_string(), "m/44'/501'/5'/0'/3'");
+        assert!("m/44'/501'".parse::<DerivationPath>().is_ok());
+
+        assert!(matches!(
+            "m/44'/501'/0/0".parse::<DerivationPath>(),
+            Err(DerivationPathError::NotHardened(_))
+        ));
+        for malformed in ["44'/501'/0'/0'", "m/44'/abc'", "m", ""] {
+            assert!(
+                matches!(
+                    malformed.parse::<DerivationPath>(),
+                    Err(DerivationPathError::Malformed(_))
+                ),
+                "{malformed}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_explained_derivation_matches_direct_derivation() {
+        let mut rng = rand::rngs::StdRng::seed_from_u64(173);
+        let path: DerivationPath = "m/44'/501'/2'/0'".parse().unwrap();
+        let (mnemonic, keypair) = generate_keypair_from(&mut rng, true, &path).unwrap();
+        let mnemonic = mnemonic.unwrap();
+
+        let steps = explain_derivation(&mnemonic, "", &path).unwrap();
+        let labels: Vec<&str> = steps.iter().map(|step| step.label.as_str()).collect();
+        assert_eq!(
+            labels,
+            [
+                "Entropy",
+                "BIP39 seed",
+                "SLIP-10 m",
+                "SLIP-10 m/44'",
+                "SLIP-10 m/44'/501'",
+                "SLIP-10 m/44'/501'/2'",
+                "SLIP-10 m/44'/501'/2'/0'",
+                "Public key",
+                "Address",
+            ]
+        );
+        assert_eq!(steps[0].output, mnemonic_entropy_hex(&mnemonic).unwrap());
+        assert!(steps[1].detail.contains("salt \"mnemonic\""));
+        // The last SLIP-10 key is the keypair's private seed
+        let final_key = steps[6].output.split(' ').next().unwrap();
+        assert_eq!(final_key, to_hex(&secret_seed_bytes(&keypair)));
+        assert_eq!(steps[7].output, to_hex(&keypair.pubkey().to_bytes()));
+        assert_eq!(steps[8].output, address_of(&keypair));
+        assert!(steps[..7].iter().all(|step| step.secret));
+        assert!(steps[7..].iter().all(|step| !step.secret));
+
+        // A passphrase shows up in the salt and leads where direct derivation does
+        let steps = explain_derivation(&mnemonic, "extra", &path).unwrap();
+        assert!(steps[1].detail.contains("salt \"mnemonicextra\""));
+        let direct = derive_with_passphrase(&mnemonic, "extra", &path).unwrap();
+        let direct = keypair_from_seed(&direct).unwrap();
+        assert_eq!(steps.last().unwrap().output, address_of(&direct));
+
+        assert!(explain_derivation("not a mnemonic", "", &path).is_err());
+    }
+
+    #[test]
+    fn test_passphrase_round_trips_through_derivation() {
+        let path = DerivationPath::default();
+        let mut rng = rand::rngs::StdRng::seed_from_u64(161);
+        let (mnemonic, keypair) = generate_keypair_from(&mut rng, true, &path).unwrap();
+        let mnemonic = mnemonic.unwrap();
+
+        // Generation and restore agree when there is no passphrase
+        let plain = derive_with_passphrase(&mnemonic, "", &path).unwrap();
+        assert_eq!(plain, keypair.secret_bytes()[..32]);
+
+        // The same passphrase always gives the same address, and it must change the address
+        let first = derive_with_passphrase(&mnemonic, "correct horse", &path).unwrap();
+        let second = derive_with_passphrase(&mnemonic, "correct horse", &path).unwrap();
+        assert_eq!(first, second);
+        assert_ne!(first, plain);
+        assert_ne!(
+            first,
+            derive_with_passphrase(&mnemonic, "correct horsE", &path).unwrap()
+        );
+        assert!(derive_with_passphrase("not a mnemonic", "", &path).is_err());
+    }
+
+    #[test]
+    fn test_numbered_mnemonic() {
+        let twelve = "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about";
+        let numbered = numbered_mnemonic(twelve);
+        let lines: Vec<&str> = numbered.lines().collect();
+        assert_eq!(lines.len(), 12);
+        assert_eq!(lines[0], "1. abandon");
+        assert_eq!(lines[11], "12. about");
+
+        let entropy = [0xffu8; 32];
+        let twenty_four = Mnemonic::from_entropy_in(Language::English, &entropy)
+            .unwrap()
+            .to_string();
+        let numbered = numbered_mnemonic(&twenty_four);
+        let lines: Vec<&str> = numbered.lines().collect();
+        assert_eq!(lines.len(), 24);
+        assert_eq!(lines[0], "1. zoo");
+        assert_eq!(lines[23], "24. vote");
+        // Reading the words back in order gives the original phrase
+        let words: Vec<&str> = lines
+            .iter()
+            .map(|line| line.split_once(". ").unwrap().1)
+            .collect();
+        assert_eq!(words.join(" "), twenty_four);
+    }
+
+    #[test]
+    fn test_derive_seed_at_custom_path() {
+        let mnemonic = Mnemonic::parse_in(
+            Language::English,
+            "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about",
+        )
+        .unwrap();
+        let seed = mnemonic.to_seed("");
+
+        assert_eq!(
+            derive_seed_at(&seed, &DerivationPath::default()),
+            derive_solana_seed(&seed)
+        );
+        let custom: DerivationPath = "m/44'/501'/5'/0'/3'".parse().unwrap();
+        assert_ne!(derive_seed_at(&seed, &custom), derive_solana_seed(&seed));
+
+        // Generated wallets use the path they're given
+        let path: DerivationPath = "m/44'/501'/1'/0'".parse().unwrap();
+        let mut rng =
+            entropy::HardwareRng::from_reader(Box::new(io::Cursor::new([0u8; 16].to_vec())));
+        let (mnemonic, keypair) = generate_keypair_from(&mut rng, true, &path).unwrap();
+        let seed = Mnemonic::parse_in(Language::English, mnemonic.as_deref().unwrap())
+            .unwrap()
+            .to_seed("");
+        assert_eq!(keypair.secret_bytes(), &derive_seed_at(&seed, &path));
+    }
+
+    #[test]
+    fn test_coin_type_changes_the_address() {
+        assert_eq!(
+            DerivationPath::for_coin_type(SOLANA_COIN_TYPE).unwrap(),
+            DerivationPath::default()
+        );
+        let ethereum = DerivationPath::for_coin_type(60).unwrap();
+        assert_eq!(ethereum.to_string(), "m/44'/60'/0'/0'");
+
+        let seed = Mnemonic::parse_in(
+            Language::English,
+            "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about",
+        )
+        .unwrap()
+        .to_seed("");
+        let address = |path: &DerivationPath| {
+            keypair_from_seed(&derive_seed_at(&seed, path))
+                .unwrap()
+                .pubkey()
+                .to_string()
+        };
+        assert_ne!(address(&ethereum), address(&DerivationPath::default()));
+
+        let largest = slip10::HARDEND - 1;
+        assert!(DerivationPath::for_coin_type(largest).is_ok());
+        assert_eq!(
+            DerivationPath::for_coin_type(slip10::HARDEND),
+            Err(DerivationPathError::CoinTypeOutOfRange(slip10::HARDEND))
+        );
+    }
+
+    #[test]
+    fn test_record_keeps_derivation_path() {
+        let path: DerivationPath = "m/44'/501'/5'/0'/3'".parse().unwrap();
+        let (mnemonic, keypair) = generate_keypair(true).unwrap();
+        let wallet = VanityWallet {
+            mnemonic,
+            public_key: address_of(&keypair),
+            keypair,
+        };
+        let record = WalletRecord::from_wallet(&wallet).with_derivation_path(&path);
+        assert_eq!(
+            record.derivation_path.as_deref(),
+            Some("m/44'/501'/5'/0'/3'")
+        );
+
+        let json = serde_json::to_string(&record).unwrap();
+        assert!(json.contains("\"derivation_path\":\"m/44'/501'/5'/0'/3'\""));
+
+        // Fast-mode keys aren't derived from anything
+        let (_, keypair) = generate_keypair(false).unwrap();
+        let fast = VanityWallet {
+            mnemonic: None,
+            public_key: address_of(&keypair),
+            keypair,
+        };
+        let record = WalletRecord::from_wallet(&fast).with_derivation_path(&path);
+        assert_eq!(record.derivation_path, None);
+        assert!(!serde_json::to_string(&record)
+            .unwrap()
+            .contains("derivation_path"));
+    }
+
+    #[test]
+    fn test_target_wallet_mappings() {
+        let cases = [
+            ("phantom", TargetWallet::Phantom, "Phantom"),
+            ("Solflare", TargetWallet::Solflare, "Solflare"),
+            ("backpack", TargetWallet::Backpack, "Backpack"),
+        ];
+        for (arg, target, name) in cases {
+            assert_eq!(arg.parse::<TargetWallet>(), Ok(target));
+            assert_eq!(target.to_string(), name);
+            // Every app's first account sits at the standard path
+            assert_eq!(target.derivation_path(), DerivationPath::default());
+        }
+        assert!("exodus".parse::<TargetWallet>().is_err());
+        assert_eq!(TARGET_WALLETS.len(), cases.len());
+
+        // A bare prompt:// reads the raw seed, which no derivation path can give
+        for keygen in ["cli", "solana-keygen"] {
+            let err = keygen.parse::<TargetWallet>().unwrap_err();
+            assert!(err.contains("prompt://?key=0/0"), "{err}");
+        }
+
+        // Each app's wallet has the address its own scheme gives, and not the raw seed's
+        let mut rng = rand::rngs::StdRng::seed_from_u64(172);
+        for (target, name, path, _) in TARGET_WALLETS {
+            let (mnemonic, keypair) =
+                generate_keypair_from(&mut rng, true, &target.derivation_path()).unwrap();
+            let scheme = detect_scheme(&mnemonic.unwrap(), &address_of(&keypair))
+                .unwrap()
+                .unwrap_or_else(|| panic!("{name}'s wallet matches no scheme"));
+            assert_eq!(scheme.path, Some(*path), "{name}");
+        }
+    }
+
+    #[test]
+    fn test_record_keeps_target_wallet() {
+        let record = sample_record().with_target_wallet(Some(TargetWallet::Solflare));
+        assert_eq!(record.target_wallet.as_deref(), Some("Solflare"));
+        assert!(record
+            .to_text_file(None)
+            .contains("Target Wallet: Solflare\n"));
+        assert!(serde_json::to_string(&record)
+            .unwrap()
+            .contains("\"target_wallet\":\"Solflare\""));
+        let parsed = parse_saved_wallet(&record.to_text_file(None)).unwrap();
+        assert_eq!(parsed.target_wallet.as_deref(), Some("Solflare"));
+
+        let record = sample_record().with_target_wallet(None);
+        assert!(!record.to_text_file(None).contains("Target Wallet"));
+    }
+
+    #[test]
+    fn test_experimental_salt_yields_a_different_seed() {
+        let phrase = "abandon abandon abandon abandon abandon abandon abandon abandon abandon \
+                      abandon abandon about";
+        let mnemonic = Mnemonic::parse_in(Language::English, phrase).unwrap();
+        let standard = SeedSalt::default().seed(&mnemonic);
+        assert_eq!(standard, mnemonic.to_seed(""));
+
+        let acme = SeedSalt::Experimental("acme".to_string());
+        assert_ne!(acme.seed(&mnemonic), standard);
+        // Not the same as using the text as a BIP39 passphrase either
+        assert_ne!(
+            acme.seed(&mnemonic),
+            SeedSalt::Bip39("acme".to_string()).seed(&mnemonic)
+        );
+        assert_ne!(
+            acme.seed(&mnemonic),
+            SeedSalt::Experimental("acme2".to_string()).seed(&mnemonic)
+        );
+        assert_eq!(acme.seed(&mnemonic), acme.seed(&mnemonic));
+        assert_eq!(acme.experimental(), Some("acme"));
+        assert_eq!(SeedSalt::default().experimental(), None);
+
+        let path = DerivationPath::default();
+        assert_ne!(
+            derive_with_salt(phrase, &acme, &path).unwrap(),
+            derive_with_passphrase(phrase, "", &path).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_salted_wallet_verifies_with_its_recorded_salt() {
+        let salt = SeedSalt::Experimental("acme".to_string());
+        let config = SearchConfig {
+            seed_salt: salt.clone(),
+            ..SearchConfig::new(VanityPattern::Prefix("A".to_string()), true, 1)
+        };
+        let result = search_vanity(&config, &SearchProgress::default());
+        let wallet = result
+            .wallet
+            .expect("a one-character prefix is found quickly");
+        let mnemonic = wallet.mnemonic.as_deref().unwrap();
+        assert_eq!(
+            derive_with_salt(mnemonic, &salt, &DerivationPath::default()).unwrap(),
+            secret_seed_bytes(&wallet.keypair)
+        );
+
+        let record = WalletRecord::new(&wallet, &result.stats)
+            .with_derivation_path(&DerivationPath::default())
+            .with_seed_salt(&salt);
+        let text = record.to_text_file(None);
+        assert!(text.contains("Experimental Salt: acme\n"));
+        assert_eq!(verify_saved_wallet(&text, &wallet.keypair), Ok(()));
+        let json = serde_json::to_string(&record).unwrap();
+        assert_eq!(verify_saved_wallet(&json, &wallet.keypair), Ok(()));
+
+        // Without the salt the mnemonic recovers some other wallet
+        let unsalted = text.replace("Experimental Salt: acme\n", "");
+        assert_eq!(
+            verify_saved_wallet(&unsalted, &wallet.keypair),
+            Err(RecoveryError::MnemonicMismatch)
+        );
+        // Standard salts aren't recorded
+        assert_eq!(
+            record
+                .with_seed_salt(&SeedSalt::default())
+                .experimental_salt,
+            None
+        );
+    }
+
+    #[test]
+    fn test_keygen_prompt_uri() {
+        // The standard path is what `solana-keygen` calls key 0/0
+        assert_eq!(
+            keygen_prompt_uri(&DerivationPath::default()),
+            "prompt://?key=0/0"
+        );
+
+        let path = |text: &str| text.parse::<DerivationPath>().unwrap();
+        assert_eq!(keygen_prompt_uri(&path("m/44'/501'/7'")), "prompt://?key=7");
+        assert_eq!(
+            keygen_prompt_uri(&path("m/44'/501'/2'/1'")),
+            "prompt://?key=2/1"
+        );
+        assert_eq!(
+            keygen_prompt_uri(&path("m/44'/501'/5'/0'/3'")),
+            "prompt://?full-path=m/44/501/5/0/3"
+        );
+        assert_eq!(
+            keygen_prompt_uri(&path("m/44'/60'/0'/0'")),
+            "prompt://?full-path=m/44/60/0/0"
+        );
+        assert_eq!(
+            path("m/44'/501'/5'/0'/3'").indexes(),
+            vec![44, 501, 5, 0, 3]
+        );
+    }
+
+    #[test]
+    fn test_paper_wallet_sheet() {
+        let (mnemonic, keypair) = generate_keypair(true).unwrap();
+        let mnemonic = mnemonic.unwrap();
+        let public_key = address_of(&keypair);
+        let sheet = paper_wallet_sheet(&public_key, &mnemonic, &DerivationPath::default());
+
+        assert!(sheet.contains(&format!("Public Key: {public_key}")));
+        assert!(sheet.contains(&format!("Seed Phrase: {mnemonic}")));
+        assert!(sheet.contains("Derivation Path: m/44'/501'/0'/0'"));
+        assert!(sheet.contains("solana-keygen recover 'prompt://?key=0/0' --outfile keypair.json"));
+
+        // The key on the sheet is the one the standard derivation gives
+        let seed = Mnemonic::parse_in(Language::English, &mnemonic)
+            .unwrap()
+            .to_seed("");
+        assert_eq!(keypair.secret_bytes(), &derive_solana_seed(&seed));
+    }
+
+    #[test]
+    fn test_verify_candidate_rejects_wrong_matches() {
+        let config = SearchConfig::new(VanityPattern::Prefix("1".to_string()), true, 1);
+        let wallet = search_seeded(VanityPattern::Prefix("1".to_string()), true, 7)
+            .wallet
+            .unwrap();
+        let seed: [u8; 32] = wallet.keypair.secret_bytes()[..].try_into().unwrap();
+        let genuine: Candidate = (wallet.mnemonic.clone(), seed, wallet.public_key.clone());
+        assert!(verify_candidate(&genuine, &config));
+
+        // An address the seed's keypair doesn't own
+        let mut wrong_key = genuine.clone();
+        wrong_key.2 = format!("1{}", &address_of(&Keypair::new())[1..]);
+        assert!(!verify_candidate(&wrong_key, &config));
+
+        // A mnemonic that doesn't derive to the seed, as a mismatched derivation would give
+        let (other_mnemonic, _) = generate_keypair(true).unwrap();
+        let wrong_mnemonic: Candidate = (other_mnemonic, seed, wallet.public_key.clone());
+        assert!(!verify_candidate(&wrong_mnemonic, &config));
+
+        let other_path = SearchConfig {
+            derivation_path: "m/44'/501'/1'/0'".parse().unwrap(),
+            ..config.clone()
+        };
+        assert!(!verify_candidate(&genuine, &other_path));
+
+        // A genuine keypair whose address doesn't satisfy the pattern
+        let other_prefix = SearchConfig::new(VanityPattern::Prefix("zz".to_string()), true, 1);
+        assert!(!verify_candidate(&genuine, &other_prefix));
+    }
+
+    #[test]
+    fn test_scheme_addresses_are_distinct() {
+        let (mnemonic, keypair) = generate_keypair(true).unwrap();
+        let addresses = scheme_addresses(&mnemonic.unwrap()).unwrap();
+        assert_eq!(addresses.len(), DERIVATION_SCHEMES.len());
+
+        let unique: std::collections::HashSet<&str> =
+            addresses.iter().map(|a| a.address.as_str()).collect();
+        assert_eq!(unique.len(), addresses.len());
+
+        // The wallets this tool makes are listed under the standard path
+        let standard = addresses
+            .iter()
+            .find(|a| a.path == Some(SOLANA_DERIVATION_PATH))
+            .unwrap();
+        assert_eq!(standard.address, address_of(&keypair));
+
+        assert!(scheme_addresses("not a mnemonic").is_err());
+    }
+
+    #[test]
+    fn test_detect_scheme_finds_the_derivation() {
+        let (mnemonic, keypair) = generate_keypair(true).unwrap();
+        let mnemonic = mnemonic.unwrap();
+        let detect = |address: &str| detect_scheme(&mnemonic, address).unwrap();
+
+        // Raw seed: the first 32 bytes of the BIP39 seed, as plain solana-keygen uses
+        let seed = Mnemonic::parse_in(Language::English, &mnemonic)
+            .unwrap()
+            .to_seed("");
+        let raw = address_of(&keypair_from_seed(&seed[..32]).unwrap());
+        let found = detect(&raw).expect("the raw seed is a known scheme");
+        assert_eq!(found.path, None);
+        assert!(found.scheme.starts_with("Raw seed"));
+
+        // BIP44 at the wallet apps' path, as this tool generates
+        let found = detect(&address_of(&keypair)).unwrap();
+        assert_eq!(found.path, Some(SOLANA_DERIVATION_PATH));
+
+        // BIP44 at solana-keygen's ?key=0, with stray whitespace from a paste
+        let path: DerivationPath = "m/44'/501'/0'".parse().unwrap();
+        let account = derive_with_passphrase(&mnemonic, "", &path).unwrap();
+        let address = address_of(&keypair_from_seed(&account).unwrap());
+        let found = detect(&format!(" {address}\n")).unwrap();
+        assert_eq!(found.path, Some("m/44'/501'/0'"));
+        assert_eq!(found.address, address);
+
+        // Another wallet's address, or a deeper account, is no match
+        let (_, other) = generate_keypair(false).unwrap();
+        assert_eq!(detect(&address_of(&other)), None);
+        let account_5 =
+            derive_with_passphrase(&mnemonic, "", &DerivationPath::for_account(5).unwrap())
+                .unwrap();
+        assert_eq!(
+            detect(&address_of(&keypair_from_seed(&account_5).unwrap())),
+            None
+        );
+
+        assert!(detect_scheme("not a mnemonic", &raw).is_err());
+    }
+
+    #[test]
+    fn test_account_addresses_for_a_range() {
+        let (mnemonic, keypair) = generate_keypair(true).unwrap();
+        let mnemonic = mnemonic.unwrap();
+        let range: AccountRange = "0..5".parse().unwrap();
+        let accounts = account_addresses(&mnemonic, range).unwrap();
+
+        assert_eq!(accounts.len(), 5);
+        let unique: std::collections::HashSet<&str> =
+            accounts.iter().map(|a| a.address.as_str()).collect();
+        assert_eq!(unique.len(), 5);
+        assert_eq!(account_addresses(&mnemonic, range).unwrap(), accounts);
+
+        // Account 0 is the wallet itself, and every account matches a one-off derivation
+        assert_eq!(accounts[0].address, address_of(&keypair));
+        for entry in &accounts {
+            let path = DerivationPath::for_account(entry.account).unwrap();
+            assert_eq!(entry.derivation_path, path.to_string());
+            let seed = derive_with_passphrase(&mnemonic, "", &path).unwrap();
+            assert_eq!(
+                entry.address,
+                address_of(&keypair_from_seed(&seed).unwrap())
+            );
+        }
+        assert_eq!(accounts[1].derivation_path, "m/44'/501'/1'/0'");
+
+        let parse = |text: &str| text.parse::<AccountRange>();
+        assert_eq!(parse("3..4"), Ok(AccountRange { start: 3, end: 4 }));
+        assert_eq!(parse("5..5"), Err(AccountRangeError::Empty));
+        assert_eq!(parse("0..1001"), Err(AccountRangeError::TooLarge(1001)));
+        assert!(matches!(parse("0-5"), Err(AccountRangeError::Malformed(_))));
+        assert!(matches!(
+            parse("2147483647..2147483649"),
+            Err(AccountRangeError::OutOfRange(_))
+        ));
+        assert!(account_addresses("not a mnemonic", range).is_err());
+    }
+
+    #[test]
+    fn test_keypair_matches_pubkey() {
+        let (_, keypair) = generate_keypair(false).unwrap();
+        let pubkey = address_of(&keypair);
+        assert!(keypair_matches_pubkey(&keypair, &pubkey));
 
-    // Derive the key using SLIP10 (BIP32 for Ed25519)
-    let derived_key = derive_key_from_path(seed, slip10::Curve::Ed25519, &path).unwrap();
+        let (_, other) = generate_keypair(false).unwrap();
+        let other_pubkey = address_of(&other);
+        assert!(!keypair_matches_pubkey(&keypair, &other_pubkey));
+        assert!(!keypair_matches_pubkey(&keypair, ""));
+    }
 
-    // Return the private key bytes
-    derived_key.key
-}
+    #[test]
+    fn test_generate_many_returns_distinct_keypairs() {
+        let wallets = generate_many(64, false);
+        assert_eq!(wallets.len(), 64);
+        assert!(wallets.iter().all(|(mnemonic, _)| mnemonic.is_none()));
+        let addresses: std::collections::HashSet<String> =
+            wallets.iter().map(|(_, kp)| address_of(kp)).collect();
+        assert_eq!(addresses.len(), 64);
 
-/// Generate a keypair with optional mnemonic
-pub fn generate_keypair(with_mnemonic: bool) -> (Option<String>, Keypair) {
-    use rand::rngs::OsRng;
-    use rand::RngCore;
+        let wallets = generate_many(4, true);
+        assert_eq!(wallets.len(), 4);
+        for (mnemonic, keypair) in &wallets {
+            let mnemonic = mnemonic
+                .as_deref()
+                .expect("mnemonic mode returns the phrase");
+            let seed = derive_with_passphrase(mnemonic, "", &DerivationPath::default()).unwrap();
+            assert_eq!(secret_seed_bytes(keypair), seed);
+        }
+        assert!(generate_many(0, false).is_empty());
+    }
 
-    let mut rng = OsRng;
+    #[test]
+    fn test_address_of_matches_manual_encoding() {
+        let (_, keypair) = generate_keypair(false).unwrap();
+        let manual = bs58::encode(keypair.pubkey().to_bytes()).into_string();
+        assert_eq!(address_of(&keypair), manual);
+        assert_eq!(address_of(&keypair), keypair.pubkey().to_string());
+        assert_eq!(encode_address(&keypair.pubkey().to_bytes()), manual);
+    }
 
-    if with_mnemonic {
-        // Generate mnemonic and derive keypair (compatible with wallets)
-        let mut entropy = [0u8; 16];
-        rng.fill_bytes(&mut entropy);
-        let mnemonic = Mnemonic::from_entropy_in(Language::English, &entropy).unwrap();
+    #[test]
+    fn test_with_timeout_takes_the_tighter_limit() {
+        let config = SearchConfig::new(VanityPattern::Prefix("A".to_string()), false, 1);
+        assert_eq!(config.clone().with_timeout(None).max_duration, None);
 
-        // Generate keypair from the mnemonic using proper Solana BIP44 derivation
-        let seed = mnemonic.to_seed("");
-        let derived_seed = derive_solana_seed(&seed);
-        let keypair = Keypair::from_seed(&derived_seed).unwrap();
+        let timed = config.with_timeout(Some(Duration::from_secs(10)));
+        assert_eq!(timed.max_duration, Some(Duration::from_secs(10)));
+        assert_eq!(
+            timed
+                .clone()
+                .with_timeout(Some(Duration::from_secs(5)))
+                .max_duration,
+            Some(Duration::from_secs(5))
+        );
+        assert_eq!(
+            timed
+                .with_timeout(Some(Duration::from_secs(60)))
+                .max_duration,
+            Some(Duration::from_secs(10))
+        );
+    }
 
-        (Some(mnemonic.to_string()), keypair)
-    } else {
-        // Fast mode: Generate keypair directly from random seed
-        let mut seed = [0u8; 32];
-        rng.fill_bytes(&mut seed);
-        let keypair = Keypair::from_seed(&seed).unwrap();
+    #[test]
+    fn test_timeout_per_wallet_resets_for_each_search() {
+        // A hard prefix with a short timeout: every wallet in the batch times out on its own clock
+        let timeout = Duration::from_millis(100);
+        let config = SearchConfig::new(VanityPattern::Prefix("z".repeat(10)), false, 1)
+            .with_timeout(Some(timeout));
 
-        (None, keypair)
+        for _ in 0..3 {
+            let result = search_vanity(&config, &SearchProgress::default());
+            assert!(result.wallet.is_none());
+            assert!(result.stats.elapsed >= timeout);
+            assert!(result.stats.elapsed < timeout * 10);
+        }
     }
-}
 
-pub fn is_valid_base58_prefix(prefix: &str) -> bool {
-    // Base58 alphabet: 123456789ABCDEFGHJKLMNPQRSTUVWXYZabcdefghijkmnopqrstuvwxyz
-    // Notable exclusions: 0, O, I, l (to avoid confusion)
-    const BASE58_ALPHABET: &str = "123456789ABCDEFGHJKLMNPQRSTUVWXYZabcdefghijkmnopqrstuvwxyz";
+    #[test]
+    fn test_paused_time_is_left_out_of_the_clock() {
+        let progress = SearchProgress::default();
+        let start = Instant::now();
+        progress.set_paused(true);
+        // Pausing again while paused doesn't restart the pause
+        std::thread::sleep(Duration::from_millis(100));
+        progress.set_paused(true);
+        std::thread::sleep(Duration::from_millis(100));
+        assert!(progress.paused_time() >= Duration::from_millis(200));
+        progress.set_paused(false);
+        let paused = progress.paused_time();
+        std::thread::sleep(Duration::from_millis(50));
+        assert_eq!(progress.paused_time(), paused);
+        assert!(progress.active_elapsed(start) <= start.elapsed() - paused);
 
-    if prefix.is_empty() {
-        return false;
+        // A search paused at the start still gets its whole time limit once resumed
+        let limit = Duration::from_millis(200);
+        let config = SearchConfig::new(VanityPattern::Prefix("z".repeat(10)), false, 1)
+            .with_timeout(Some(limit));
+        let progress = Arc::new(SearchProgress::default());
+        progress.set_paused(true);
+        let resumer = {
+            let progress = Arc::clone(&progress);
+            std::thread::spawn(move || {
+                std::thread::sleep(Duration::from_millis(400));
+                progress.set_paused(false);
+            })
+        };
+        let start = Instant::now();
+        let result = search_vanity(&config, &progress);
+        resumer.join().unwrap();
+        assert!(start.elapsed() >= Duration::from_millis(400) + limit);
+        assert!(result.stats.elapsed >= limit);
+        assert!(result.stats.elapsed < Duration::from_millis(400));
     }
 
-    prefix.chars().all(|c| BASE58_ALPHABET.contains(c))
-}
+    #[test]
+    fn test_common_prefix_len() {
+        assert_eq!(common_prefix_len("Solana", "Sol"), 3);
+        assert_eq!(common_prefix_len("Sopwith", "Sol"), 2);
+        assert_eq!(common_prefix_len("abc", "xyz"), 0);
+        assert_eq!(common_prefix_len("", "Sol"), 0);
+        assert_eq!(common_prefix_len("Sol", "Sol"), 3);
+        // Case matters: base58 is case sensitive
+        assert_eq!(common_prefix_len("sol", "Sol"), 0);
+    }
 
-pub fn calculate_expected_iterations(prefix: &str) -> u64 {
-    // Base58 alphabet has 58 characters
-    // Expected iterations = 58^(prefix_length) / 2 (on average)
-    let base: u64 = 58;
-    let length = prefix.len() as u32;
-    base.pow(length) / 2
-}
+    #[test]
+    fn test_prefix_bonus() {
+        let bonus = |prefix: &str, address: &str, extension: Option<&str>| {
+            PrefixBonus::measure(prefix, address, extension).map(|found| found.bonus)
+        };
+        // Against the prefix repeated
+        assert_eq!(bonus("AAA", "AAAAx9", None), Some(1));
+        assert_eq!(bonus("AAA", "AAAAAAAx", None), Some(4));
+        assert_eq!(bonus("Sun", "SunSuQ", None), Some(2));
+        assert_eq!(bonus("Sun", "Sunny", None), Some(0));
+        // Against a longer target, when there is one that starts with the prefix
+        assert_eq!(bonus("Sun", "SunRiX", Some("SunRise")), Some(2));
+        assert_eq!(bonus("Sun", "SunRise9", Some("SunRise")), Some(4));
+        assert_eq!(bonus("Sun", "SunSuQ", Some("MoonRise")), Some(2));
+        // A word-like continuation is only a bonus once it's named as the target
+        assert_eq!(bonus("Sol", "Sole4x", None), Some(0));
+        assert_eq!(bonus("Sol", "Sole4x", Some("Solend")), Some(1));
+        // Nothing to report for an address without the prefix
+        assert_eq!(bonus("Sun", "sunny", None), None);
+        assert_eq!(bonus("", "Sunny", None), None);
 
-pub fn generate_solana_keypair() -> (String, Vec<u8>) {
-    let keypair = Keypair::new();
-    let pubkey = bs58::encode(keypair.pubkey().to_bytes()).into_string();
-    let keypair_bytes = keypair.to_bytes().to_vec();
-    (pubkey, keypair_bytes)
-}
+        let found = PrefixBonus::measure("Sun", "Sunee4", Some("Sunees")).unwrap();
+        assert_eq!(found.matched, 5);
+        assert_eq!(found.to_string(), "matched 5 chars (2 bonus)");
+    }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+    #[test]
+    fn test_common_suffix_len() {
+        assert_eq!(common_suffix_len("xyzSol", "Sol"), 3);
+        assert_eq!(common_suffix_len("xyzSpl", "Sol"), 1);
+        assert_eq!(common_suffix_len("abc", "xyz"), 0);
+    }
 
     #[test]
-    fn test_valid_base58_prefixes() {
-        assert!(is_valid_base58_prefix("A"));
-        assert!(is_valid_base58_prefix("1"));
-        assert!(is_valid_base58_prefix("ABC"));
-        assert!(is_valid_base58_prefix("Sov")); // 'l' is not valid in base58
-        assert!(is_valid_base58_prefix("123"));
-        assert!(is_valid_base58_prefix("JAZZ"));
-        assert!(is_valid_base58_prefix("MyWavvet"));
+    fn test_pattern_closeness() {
+        let prefix = VanityPattern::Prefix("Sol".to_string());
+        assert_eq!(prefix.closeness("SoXabc"), 2);
+        assert_eq!(prefix.closeness("abcSol"), 0);
+
+        let either = VanityPattern::EitherEnd("Sol".to_string());
+        assert_eq!(either.closeness("SoXabc"), 2);
+        assert_eq!(either.closeness("abcXol"), 2);
     }
 
     #[test]
-    fn test_invalid_base58_prefixes() {
-        assert!(!is_valid_base58_prefix(""));
-        assert!(!is_valid_base58_prefix("0"));
-        assert!(!is_valid_base58_prefix("O"));
-        assert!(!is_valid_base58_prefix("I"));
-        assert!(!is_valid_base58_prefix("l"));
-        assert!(!is_valid_base58_prefix("_"));
-        assert!(!is_valid_base58_prefix("Test0"));
-        assert!(!is_valid_base58_prefix("ABC_"));
-        assert!(!is_valid_base58_prefix("Sol+"));
+    fn test_best_effort_search_reports_closest() {
+        // A 10-character prefix is out of reach, so the iteration limit ends the search
+        let target = "z".repeat(10);
+        let mut config = SearchConfig::new(VanityPattern::Prefix(target.clone()), false, 2);
+        config.max_iterations = Some(5_000);
+        config.track_best = true;
+        let progress = SearchProgress::default();
+
+        let result = search_vanity(&config, &progress);
+
+        assert!(result.wallet.is_none());
+        assert!(result.stats.iterations >= 5_000);
+        let closest = result.closest.unwrap();
+        assert_eq!(
+            closest.matched_chars,
+            common_prefix_len(&closest.wallet.public_key, &target)
+        );
+        assert_eq!(
+            closest.wallet.public_key,
+            address_of(&closest.wallet.keypair)
+        );
     }
 
     #[test]
-    fn test_expected_iterations_calculation() {
-        assert_eq!(calculate_expected_iterations("A"), 29); // 58/2
-        assert_eq!(calculate_expected_iterations("AB"), 1682); // 58^2/2
-        assert_eq!(calculate_expected_iterations("ABC"), 97556); // 58^3/2
+    fn test_search_without_best_effort_returns_nothing_on_limit() {
+        let mut config = SearchConfig::new(VanityPattern::Prefix("z".repeat(10)), false, 1);
+        config.max_duration = Some(Duration::from_millis(50));
+        let result = search_vanity(&config, &SearchProgress::default());
+
+        assert!(result.wallet.is_none());
+        assert!(result.closest.is_none());
     }
 
     #[test]
-    fn test_solana_keypair_generation() {
-        let (pubkey, keypair_bytes) = generate_solana_keypair();
+    fn test_windowed_rate() {
+        assert_eq!(windowed_rate(&[]), 0.0);
+        assert_eq!(windowed_rate(&[(1.0, 100)]), 0.0);
+        assert_eq!(windowed_rate(&[(1.0, 100), (3.0, 500)]), 200.0);
+        // Only the endpoints matter, so a noisy sample in the middle is smoothed out
+        assert_eq!(windowed_rate(&[(0.0, 0), (1.0, 900), (2.0, 1000)]), 500.0);
+    }
 
-        // Test that public key is valid Base58
-        assert!(bs58::decode(&pubkey).into_vec().is_ok());
+    #[test]
+    fn test_rate_window_slides() {
+        let mut window = RateWindow::new(2);
 
-        // Test that keypair bytes are correct length (64 bytes)
-        assert_eq!(keypair_bytes.len(), 64);
+        assert_eq!(window.push(0.0, 0), 0.0);
+        assert_eq!(window.push(1.0, 100), 100.0);
+        assert_eq!(window.push(2.0, 400), 200.0);
+        // The (0.0, 0) sample has dropped out, leaving (1.0, 100)..(3.0, 500)
+        assert_eq!(window.push(3.0, 500), 200.0);
+        assert_eq!(window.push(4.0, 500), 50.0);
+    }
 
-        // Test that public key is not empty
-        assert!(!pubkey.is_empty());
+    #[test]
+    fn test_rate_window_minimum_size() {
+        let mut window = RateWindow::new(0);
 
-        // Test that public key is reasonable length (typically 32-44 characters)
-        assert!(pubkey.len() >= 32 && pubkey.len() <= 44);
+        window.push(0.0, 0);
+        assert_eq!(window.push(1.0, 10), 10.0);
+        assert_eq!(window.push(2.0, 50), 40.0);
     }
 
     #[test]
-    fn test_keypair_consistency() {
-        // Generate multiple keypairs and ensure they're different
-        let (pubkey1, _) = generate_solana_keypair();
-        let (pubkey2, _) = generate_solana_keypair();
+    fn test_either_end_pattern_matches_both_ends() {
+        let pattern = VanityPattern::EitherEnd("Sol".to_string());
 
-        assert_ne!(pubkey1, pubkey2);
+        assert!(pattern.matches("SolXyz123"));
+        assert!(pattern.matches("Xyz123Sol"));
+        assert!(!pattern.matches("XySolz123"));
+
+        let prefix = VanityPattern::Prefix("Sol".to_string());
+        assert!(prefix.matches("SolXyz123"));
+        assert!(!prefix.matches("Xyz123Sol"));
     }
 
     #[test]
-    fn test_base58_alphabet_completeness() {
-        // Test that all expected characters are considered valid
-        let valid_chars = "123456789ABCDEFGHJKLMNPQRSTUVWXYZabcdefghijkmnopqrstuvwxyz";
+    fn test_either_end_pattern_difficulty() {
+        let prefix = VanityPattern::Prefix("AB".to_string());
+        let either = VanityPattern::EitherEnd("AB".to_string());
 
-        for c in valid_chars.chars() {
-            assert!(
-                is_valid_base58_prefix(&c.to_string()),
-                "Character '{c}' should be valid"
-            );
+        assert_eq!(prefix.expected_iterations(), 1682);
+        assert_eq!(either.expected_iterations(), 841);
+        assert_eq!(
+            VanityPattern::EitherEnd("A".to_string()).expected_iterations(),
+            14
+        );
+    }
+
+    #[test]
+    fn test_associated_token_address_derivation() {
+        let (_, keypair) = generate_keypair(false).unwrap();
+        let wallet = keypair.pubkey();
+        let usdc = Pubkey::from_str("EPjFWdd5AufqSSqeM2qN1xzybapC8G4wEGGkZwyTDt1v").unwrap();
+        let other_mint = Pubkey::new_unique();
+
+        let ata = associated_token_address(&wallet, &usdc);
+
+        // Deterministic, off the ed25519 curve, and specific to the mint
+        assert_eq!(ata, associated_token_address(&wallet, &usdc));
+        assert!(!ata.is_on_curve());
+        assert_ne!(ata, wallet);
+        assert_ne!(ata, associated_token_address(&wallet, &other_mint));
+    }
+
+    /// Fresh scratch directory under the system temp dir for file-based tests
+    fn test_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!(
+            "solana-vanity-wallet-{}-{name}",
+            std::process::id()
+        ));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn test_ensure_output_dir_creates_nested_path() {
+        let dir = test_dir("nested-output");
+        let nested = dir.join("a").join("b").join("output");
+
+        ensure_output_dir(&nested).unwrap();
+        assert!(nested.is_dir());
+
+        // Calling it again on an existing directory is not an error
+        ensure_output_dir(&nested).unwrap();
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_ensure_output_dir_rejects_existing_file() {
+        let dir = test_dir("output-is-file");
+        let file_path = dir.join("output");
+        fs::write(&file_path, b"not a directory").unwrap();
+
+        assert!(ensure_output_dir(&file_path).is_err());
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_sha256_hex_known_vector() {
+        assert_eq!(
+            sha256_hex(b"abc"),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+    }
+
+    #[test]
+    fn test_checksum_round_trip() {
+        let dir = test_dir("checksum");
+        let path = dir.join("wallet_output.json");
+        let contents = b"{\"public_key\": \"abc\"}";
+        fs::write(&path, contents).unwrap();
+
+        let sidecar = write_checksum_file(&path, contents).unwrap();
+        assert_eq!(sidecar, dir.join("wallet_output.json.sha256"));
+        assert!(fs::read_to_string(&sidecar)
+            .unwrap()
+            .ends_with("  wallet_output.json\n"));
+        assert!(verify_checksum_file(&path).unwrap());
+
+        // Any change to the file must be detected
+        fs::write(&path, b"{\"public_key\": \"abd\"}").unwrap();
+        assert!(!verify_checksum_file(&path).unwrap());
+
+        fs::write(checksum_path(&path), "not-a-digest").unwrap();
+        assert!(verify_checksum_file(&path).is_err());
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_paper_backup_sheet_recovers_mnemonic() {
+        let (mnemonic, keypair) = generate_keypair(true).unwrap();
+        let mnemonic = mnemonic.unwrap();
+        let public_key = address_of(&keypair);
+        let params = encryption::KdfParams {
+            memory_kib: 1024,
+            iterations: 1,
+            parallelism: 1,
+        };
+        let blob = encryption::encrypt(mnemonic.as_bytes(), "paper", params).unwrap();
+
+        let sheet = paper_backup_sheet(&public_key, &blob).unwrap();
+        assert!(sheet.contains(&format!("Public Key: {public_key}")));
+        assert!(sheet.contains("memory 1024 KiB, iterations 1, parallelism 1"));
+        assert!(!sheet.contains(&mnemonic));
+
+        // The encrypted line on the sheet is enough to get the exact mnemonic back
+        let line = sheet.lines().find(|line| line.starts_with('{')).unwrap();
+        let parsed: EncryptedBlob = serde_json::from_str(line).unwrap();
+        let recovered = encryption::decrypt(&parsed, "paper").unwrap();
+        assert_eq!(String::from_utf8(recovered).unwrap(), mnemonic);
+    }
+
+    fn sample_record() -> WalletRecord {
+        let (mnemonic, keypair) = generate_keypair(true).unwrap();
+        let wallet = VanityWallet {
+            mnemonic,
+            public_key: address_of(&keypair),
+            keypair,
+        };
+        let stats = SearchStats::new(10, Duration::from_millis(5), 29, 1);
+        WalletRecord::new(&wallet, &stats)
+    }
+
+    #[test]
+    fn test_saved_matches_count_toward_a_resumed_batch() {
+        let dir = test_dir("saved-matches");
+        let pattern = VanityPattern::Prefix("A".to_string());
+        let count = 5;
+
+        // Three wallets from an interrupted batch, one of them saved in both formats
+        let records: Vec<WalletRecord> = (0..3)
+            .map(|seed| {
+                let result = search_seeded(pattern.clone(), false, seed);
+                WalletRecord::new(&result.wallet.unwrap(), &result.stats)
+            })
+            .collect();
+        for record in &records {
+            let name = wallet_file_name(&record.public_key, "output.txt");
+            fs::write(dir.join(name), record.to_text_file(None)).unwrap();
         }
+        let name = wallet_file_name(&records[0].public_key, "output.json");
+        fs::write(dir.join(name), serde_json::to_string(&records[0]).unwrap()).unwrap();
+
+        // Neither another prefix's wallet nor other files count
+        let other = search_seeded(VanityPattern::Prefix("B".to_string()), false, 0);
+        let other = WalletRecord::new(&other.wallet.unwrap(), &other.stats);
+        fs::write(
+            dir.join(wallet_file_name(&other.public_key, "output.txt")),
+            other.to_text_file(None),
+        )
+        .unwrap();
+        fs::write(dir.join("notes_output.txt"), "not a wallet").unwrap();
+        fs::write(dir.join("manifest.json"), "[]").unwrap();
+
+        let saved = saved_matches(&dir, &pattern).unwrap();
+        assert_eq!(saved.len(), 3);
+        assert_eq!(count - saved.len(), 2);
+        assert!(records
+            .iter()
+            .all(|record| saved.contains(&record.public_key)));
+
+        fs::remove_dir_all(&dir).unwrap();
+        assert!(saved_matches(&dir, &pattern).unwrap().is_empty());
     }
 
     #[test]
-    fn test_edge_cases() {
-        // Test edge cases for prefix validation
-        assert!(!is_valid_base58_prefix(" "));
-        assert!(!is_valid_base58_prefix("\n"));
-        assert!(!is_valid_base58_prefix("\t"));
-        assert!(!is_valid_base58_prefix("A B"));
-        assert!(!is_valid_base58_prefix("A\n"));
+    fn test_convert_secret_key_representations() {
+        // The wallet from seed [7; 32], GmaDrppBC7P5ARKV8g3djiwP89vz1jLK23V2GBjuAEGB
+        let base58 = "99eUso3aSbE9tqGSTXzo3TLfKb9RkMTURrHKQ1K7Zh3StnzFNUx8FKCPPPPpR479qsw5zv2WNBKmgiz7WqgAJfM";
+        let json = "[7,7,7,7,7,7,7,7,7,7,7,7,7,7,7,7,7,7,7,7,7,7,7,7,7,7,7,7,7,7,7,7,\
+                    234,74,108,99,226,156,82,10,190,245,80,123,19,46,197,249,\
+                    149,71,118,174,190,190,123,146,66,30,234,105,20,70,210,44]";
+
+        let bytes = secret_base58_to_json_array(base58).unwrap();
+        assert_eq!(
+            serde_json::to_string(&bytes).unwrap(),
+            json.replace(' ', "")
+        );
+        assert_eq!(bytes, keypair_from_seed(&[7; 32]).unwrap().to_bytes());
+        assert_eq!(secret_json_array_to_base58(&bytes).unwrap(), base58);
+        // Surrounding whitespace, as from a file or a paste, is ignored
+        assert_eq!(
+            secret_base58_to_json_array(&format!("  {base58}\n")).unwrap(),
+            bytes
+        );
+
+        assert_eq!(
+            secret_base58_to_json_array("not base58: 0OIl"),
+            Err(SecretKeyError::Unrecognized)
+        );
+        assert_eq!(
+            secret_base58_to_json_array(&bs58::encode([7u8; 32]).into_string()),
+            Err(SecretKeyError::WrongLength(32))
+        );
+        assert_eq!(
+            secret_json_array_to_base58(&bytes[..63]),
+            Err(SecretKeyError::WrongLength(63))
+        );
+        let mut tampered = bytes.clone();
+        tampered[63] ^= 1;
+        assert_eq!(
+            secret_json_array_to_base58(&tampered),
+            Err(SecretKeyError::PublicKeyMismatch)
+        );
+    }
+
+    #[test]
+    fn test_filter_secret_keys_reports_matching_lines() {
+        // Seed [7; 32] is the wallet GmaDrppBC7P5ARKV8g3djiwP89vz1jLK23V2GBjuAEGB
+        let wanted = keypair_from_seed(&[7; 32]).unwrap();
+        let other = keypair_from_seed(&[8; 32]).unwrap();
+        let json = |keypair: &Keypair| serde_json::to_string(&keypair.to_bytes().to_vec()).unwrap();
+        let fixture = [
+            "# pool of pre-generated wallets".to_string(),
+            wanted.to_base58_string(),
+            String::new(),
+            json(&other),
+            "not a key".to_string(),
+            serde_json::to_string(&[7u8; 32]).unwrap(),
+            format!("  {}  ", json(&wanted)),
+        ]
+        .join("\n");
+
+        let scan = filter_secret_keys(&fixture, &VanityPattern::Prefix("Gma".to_string()));
+        assert_eq!(scan.keys, 3);
+        let lines: Vec<usize> = scan.matches.iter().map(|found| found.line).collect();
+        assert_eq!(lines, [2, 7]);
+        assert!(scan
+            .matches
+            .iter()
+            .all(|found| found.public_key == "GmaDrppBC7P5ARKV8g3djiwP89vz1jLK23V2GBjuAEGB"));
+        assert_eq!(
+            scan.unreadable,
+            [
+                (5, SecretKeyError::Unrecognized),
+                (6, SecretKeyError::WrongLength(32))
+            ]
+        );
+
+        let by_suffix = filter_secret_keys(&fixture, &VanityPattern::Suffix("AEGB".to_string()));
+        assert_eq!(by_suffix.matches, scan.matches);
+
+        // A key whose stored public half was swapped for another wallet's
+        let mut tampered = wanted.to_bytes();
+        tampered[32..].copy_from_slice(&other.pubkey().to_bytes());
+        assert_eq!(
+            parse_secret_key(&bs58::encode(tampered).into_string()).err(),
+            Some(SecretKeyError::PublicKeyMismatch)
+        );
+    }
+
+    /// Temporary files [`write_temp`] left in `dir`
+    fn temp_files(dir: &Path) -> Vec<PathBuf> {
+        let mut files: Vec<PathBuf> = fs::read_dir(dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().path())
+            .filter(|path| path.extension().is_some_and(|ext| ext == "tmp"))
+            .collect();
+        files.sort();
+        files
+    }
+
+    #[test]
+    fn test_write_atomic_replaces_contents() {
+        let dir = test_dir("write-atomic");
+        let path = dir.join("wallet.json");
+
+        write_atomic(&path, b"first").unwrap();
+        assert_eq!(fs::read(&path).unwrap(), b"first");
+
+        write_atomic(&path, b"second").unwrap();
+        assert_eq!(fs::read(&path).unwrap(), b"second");
+
+        // The temporary file must not be left behind after the rename
+        assert!(temp_files(&dir).is_empty());
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_write_atomic_failure_keeps_the_original() {
+        let dir = test_dir("write-atomic-crash");
+        let path = dir.join("wallet.json");
+        write_atomic(&path, b"original").unwrap();
+
+        // A crash after the new contents are written but before the rename
+        let crashed = write_temp(&path, b"replacement, cut sh").unwrap();
+        assert_eq!(fs::read(&path).unwrap(), b"original");
+
+        // The same for a file that didn't exist yet: there's still no such file
+        let fresh = dir.join("fresh.json");
+        let crashed_fresh = write_temp(&fresh, b"half a wal").unwrap();
+        assert!(!fresh.exists());
+        let mut crashed = vec![crashed, crashed_fresh];
+        crashed.sort();
+
+        // A rename that fails, here onto a directory in the way, cleans up after itself
+        let blocked = dir.join("blocked");
+        fs::create_dir(&blocked).unwrap();
+        fs::write(blocked.join("keep"), b"kept").unwrap();
+        assert!(write_atomic(&blocked, b"wallet").is_err());
+        assert_eq!(temp_files(&dir), crashed);
+        assert_eq!(fs::read(blocked.join("keep")).unwrap(), b"kept");
+
+        // A stale temporary file doesn't get in the way of the next write
+        write_atomic(&path, b"replacement").unwrap();
+        assert_eq!(fs::read(&path).unwrap(), b"replacement");
+        assert_eq!(temp_files(&dir), crashed);
+
+        // Checksum sidecars are written the same way
+        let sidecar = write_checksum_file(&path, b"replacement").unwrap();
+        assert!(verify_checksum_file(&path).unwrap());
+        assert_eq!(temp_files(&dir), crashed);
+        assert_eq!(sidecar, checksum_path(&path));
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_write_atomic_writers_never_share_a_temp_file() {
+        let dir = test_dir("write-atomic-concurrent");
+        let path = dir.join("manifest.json");
+        let contents: Vec<Vec<u8>> = (0..8u8).map(|n| vec![b'a' + n; 64 * 1024]).collect();
+
+        std::thread::scope(|scope| {
+            for bytes in &contents {
+                let path = &path;
+                scope.spawn(move || {
+                    for _ in 0..10 {
+                        write_atomic(path, bytes).unwrap();
+                    }
+                });
+            }
+        });
+        // Whichever write landed last, it landed whole
+        assert!(contents.contains(&fs::read(&path).unwrap()));
+        assert!(temp_files(&dir).is_empty());
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_write_atomic_permissions() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = test_dir("write-atomic-permissions");
+        let mode = |path: &Path| fs::metadata(path).unwrap().permissions().mode() & 0o777;
+
+        // New files are the owner's alone
+        let path = dir.join("wallet.txt");
+        write_atomic(&path, b"first").unwrap();
+        assert_eq!(mode(&path), 0o600);
+
+        // A rewrite keeps whatever the file was given since
+        for chosen in [0o640, 0o600] {
+            fs::set_permissions(&path, fs::Permissions::from_mode(chosen)).unwrap();
+            write_atomic(&path, b"again").unwrap();
+            assert_eq!(mode(&path), chosen);
+        }
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_write_new_keeps_an_existing_file() {
+        let dir = test_dir("write-new");
+        let path = dir.join("id.json");
+        write_new(&path, b"first wallet", false).unwrap();
+
+        let err = write_new(&path, b"second wallet", false).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::AlreadyExists);
+        assert_eq!(fs::read(&path).unwrap(), b"first wallet");
+
+        write_new(&path, b"second wallet", true).unwrap();
+        assert_eq!(fs::read(&path).unwrap(), b"second wallet");
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_wallet_record_without_stats_omits_statistics() {
+        let (mnemonic, keypair) = generate_keypair(false).unwrap();
+        let wallet = VanityWallet {
+            mnemonic,
+            public_key: address_of(&keypair),
+            keypair,
+        };
+
+        let record = WalletRecord::from_wallet(&wallet);
+        let json = serde_json::to_value(&record).unwrap();
+        assert!(json.get("statistics").is_none());
+        assert_eq!(json["public_key"], wallet.public_key);
+
+        let with_stats = WalletRecord::new(&wallet, &SearchStats::new(1, Duration::ZERO, 29, 1));
+        assert!(serde_json::to_value(&with_stats).unwrap()["statistics"].is_object());
+    }
+
+    #[test]
+    fn test_records_carry_provenance() {
+        let record = sample_record();
+        let generated_at = record
+            .generated_at
+            .as_deref()
+            .expect("set on every new record");
+        let parsed = chrono::DateTime::parse_from_rfc3339(generated_at).unwrap();
+        assert!((chrono::Utc::now() - parsed.to_utc()).num_seconds().abs() < 60);
+        assert!(generated_at.ends_with('Z'));
+        assert_eq!(
+            record.tool_version.as_deref(),
+            Some(env!("CARGO_PKG_VERSION"))
+        );
+
+        let json = serde_json::to_value(&record).unwrap();
+        assert_eq!(json["generated_at"], generated_at);
+        assert_eq!(json["tool_version"], TOOL_VERSION);
+
+        let text = record.to_text_file(None);
+        assert!(text.contains(&format!("Generated At: {generated_at}\n")));
+        assert!(text.contains(&format!("Tool Version: {TOOL_VERSION}\n")));
+        let reread = parse_saved_wallet(&text).unwrap();
+        assert_eq!(reread.generated_at, record.generated_at);
+        assert_eq!(reread.tool_version, record.tool_version);
+
+        // Files from before these fields existed still load
+        let old: WalletRecord = serde_json::from_str(
+            r#"{"mnemonic":null,"public_key":"A","secret_key":"B","keypair_json":[]}"#,
+        )
+        .unwrap();
+        assert_eq!((old.generated_at, old.tool_version), (None, None));
+    }
+
+    /// `--format backpack` output for the keypair with seed `[7; 32]`, whose address is
+    /// `GmaDrppBC7P5ARKV8g3djiwP89vz1jLK23V2GBjuAEGB`
+    const BACKPACK_FIXTURE: &str =
+        "99eUso3aSbE9tqGSTXzo3TLfKb9RkMTURrHKQ1K7Zh3StnzFNUx8FKCPPPPpR479qsw5zv2WNBKmgiz7WqgAJfM";
+
+    #[test]
+    fn test_backpack_import_format() {
+        let keypair = Keypair::from_seed(&[7u8; 32]).unwrap();
+        let wallet = VanityWallet {
+            mnemonic: None,
+            public_key: address_of(&keypair),
+            keypair,
+        };
+        let record = WalletRecord::from_wallet(&wallet);
+        assert_eq!(record.secret_key, BACKPACK_FIXTURE);
+        assert_eq!(
+            record.public_key,
+            "GmaDrppBC7P5ARKV8g3djiwP89vz1jLK23V2GBjuAEGB"
+        );
+        // The private key is the full 64-byte keypair, which rebuilds the wallet
+        let secret = bs58::decode(&record.secret_key).into_vec().unwrap();
+        assert_eq!(secret, wallet.keypair.to_bytes());
+    }
+
+    #[test]
+    fn test_env_exports() {
+        let record = sample_record();
+        let generated_at = record.generated_at.clone().unwrap();
+        let provenance = format!(
+            "export SOLANA_GENERATED_AT='{generated_at}'\nexport SOLANA_TOOL_VERSION='{TOOL_VERSION}'\n"
+        );
+        assert_eq!(
+            record.to_env_exports(false),
+            format!("export SOLANA_PUBKEY='{}'\n{provenance}", record.public_key)
+        );
+
+        let exports = record.to_env_exports(true);
+        let lines: Vec<&str> = exports.lines().collect();
+        assert_eq!(
+            lines,
+            [
+                format!("export SOLANA_PUBKEY='{}'", record.public_key),
+                format!("export SOLANA_GENERATED_AT='{generated_at}'"),
+                format!("export SOLANA_TOOL_VERSION='{TOOL_VERSION}'"),
+                format!("export SOLANA_SECRET='{}'", record.secret_key),
+            ]
+        );
+        assert!(record.secret_key.chars().all(|c| c.is_ascii_alphanumeric()));
+    }
+
+    #[test]
+    fn test_append_to_manifest_accumulates_records() {
+        let dir = test_dir("manifest");
+        let path = dir.join("wallets.json");
+        let first = sample_record();
+        let second = sample_record();
+
+        assert_eq!(append_to_manifest(&path, first.clone()).unwrap(), 1);
+        assert_eq!(append_to_manifest(&path, second.clone()).unwrap(), 2);
+
+        let records: Vec<WalletRecord> =
+            serde_json::from_str(&fs::read_to_string(&path).unwrap()).unwrap();
+        assert_eq!(records, vec![first, second]);
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_pubkey_list_and_secret_bundle() {
+        const N: usize = 3;
+        let records: Vec<WalletRecord> = (0..N).map(|_| sample_record()).collect();
+
+        let list = pubkey_list(&records);
+        let lines: Vec<&str> = list.lines().collect();
+        assert_eq!(lines.len(), N);
+        for (line, record) in lines.iter().zip(&records) {
+            let bytes = bs58::decode(line).into_vec().unwrap();
+            assert_eq!(bytes.len(), 32);
+            assert_eq!(*line, record.public_key);
+        }
+        assert!(!list.contains(&records[0].secret_key));
+
+        let params = KdfParams {
+            memory_kib: 1024,
+            iterations: 1,
+            parallelism: 1,
+        };
+        let bundle = encrypt_wallet_bundle(&records, "pw", params).unwrap();
+        assert!(!serde_json::to_string(&bundle)
+            .unwrap()
+            .contains(&records[0].public_key));
+        assert_eq!(decrypt_wallet_bundle(&bundle, "pw").unwrap(), records);
+        assert_eq!(
+            decrypt_wallet_bundle(&bundle, "wrong"),
+            Err(EncryptionError::Decryption)
+        );
+    }
+
+    #[test]
+    fn test_encrypted_stream_round_trip() {
+        let records: Vec<WalletRecord> = (0..2).map(|_| sample_record()).collect();
+        let params = KdfParams {
+            memory_kib: 1024,
+            iterations: 1,
+            parallelism: 1,
+        };
+        let mut stream = Vec::new();
+        write_encrypted_bundle(&records, "pw", params, &mut stream).unwrap();
+
+        // One line of base64 and no plaintext secret anywhere in it
+        let text = String::from_utf8(stream).unwrap();
+        assert_eq!(text.lines().count(), 1);
+        assert!(text
+            .trim_end()
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "+/=".contains(c)));
+        for record in &records {
+            assert!(!text.contains(&record.secret_key));
+            assert!(!text.contains(&record.public_key));
+            assert!(!text.contains(record.mnemonic.as_deref().unwrap()));
+        }
+
+        let blob = parse_encrypted_blob(&text).unwrap();
+        assert_eq!(decrypt_wallet_bundle(&blob, "pw").unwrap(), records);
+        // The JSON form still parses, and garbage doesn't
+        let json = serde_json::to_string(&blob).unwrap();
+        assert_eq!(parse_encrypted_blob(&json).unwrap(), blob);
+        assert!(matches!(
+            parse_encrypted_blob("not base64!"),
+            Err(EncryptionError::Malformed(_))
+        ));
     }
 
     // === COMPREHENSIVE KEYPAIR VALIDATION TESTS ===
@@ -161,7 +7469,7 @@ mod tests {
     #[test]
     fn test_fast_mode_generates_valid_keypairs() {
         // Test that fast mode generates valid keypairs
-        let (mnemonic, keypair) = generate_keypair(false);
+        let (mnemonic, keypair) = generate_keypair(false).unwrap();
 
         // Should not have mnemonic
         assert!(mnemonic.is_none());
@@ -176,7 +7484,7 @@ mod tests {
         assert!(signature.verify(pubkey.as_ref(), message));
 
         // Public key should be valid Base58
-        let pubkey_str = bs58::encode(pubkey.to_bytes()).into_string();
+        let pubkey_str = address_of(&keypair);
         assert!(pubkey_str.len() >= 32); // Base58 encoding should be reasonable length
 
         // Secret key should be 64 bytes
@@ -187,7 +7495,7 @@ mod tests {
     #[test]
     fn test_mnemonic_mode_generates_valid_keypairs() {
         // Test that mnemonic mode generates valid keypairs
-        let (mnemonic, keypair) = generate_keypair(true);
+        let (mnemonic, keypair) = generate_keypair(true).unwrap();
 
         // Should have mnemonic
         assert!(mnemonic.is_some());
@@ -207,7 +7515,7 @@ mod tests {
         assert!(signature.verify(pubkey.as_ref(), message));
 
         // Public key should be valid Base58
-        let pubkey_str = bs58::encode(pubkey.to_bytes()).into_string();
+        let pubkey_str = address_of(&keypair);
         assert!(pubkey_str.len() >= 32);
 
         // Secret key should be 64 bytes
@@ -239,7 +7547,7 @@ mod tests {
         let mut keypairs = HashSet::new();
 
         for _ in 0..10 {
-            let (mnemonic, keypair) = generate_keypair(true);
+            let (mnemonic, keypair) = generate_keypair(true).unwrap();
             assert!(mnemonic.is_some());
 
             let pubkey = keypair.pubkey();
@@ -255,7 +7563,7 @@ mod tests {
         let mut keypairs = HashSet::new();
 
         for _ in 0..10 {
-            let (mnemonic, keypair) = generate_keypair(false);
+            let (mnemonic, keypair) = generate_keypair(false).unwrap();
             assert!(mnemonic.is_none());
 
             let pubkey = keypair.pubkey();
@@ -276,7 +7584,7 @@ mod tests {
         let keypair = Keypair::from_seed(&derived_seed).unwrap();
 
         // This should produce a specific, known public key for this test mnemonic
-        let pubkey_str = bs58::encode(keypair.pubkey().to_bytes()).into_string();
+        let pubkey_str = address_of(&keypair);
 
         // The actual value depends on the SLIP10 implementation, but it should be deterministic
         assert!(!pubkey_str.is_empty());
@@ -292,7 +7600,7 @@ mod tests {
     fn test_keypair_serialization() {
         // Test both modes for proper serialization
         for with_mnemonic in [false, true] {
-            let (mnemonic_opt, keypair) = generate_keypair(with_mnemonic);
+            let (mnemonic_opt, keypair) = generate_keypair(with_mnemonic).unwrap();
 
             // Test public key serialization
             let pubkey_bytes = keypair.pubkey().to_bytes();
@@ -325,7 +7633,7 @@ mod tests {
         let test_message = b"Hello, Solana!";
 
         for with_mnemonic in [false, true] {
-            let (_, keypair) = generate_keypair(with_mnemonic);
+            let (_, keypair) = generate_keypair(with_mnemonic).unwrap();
 
             // Sign the message
             let signature = keypair.sign_message(test_message);
@@ -343,9 +7651,9 @@ mod tests {
     fn test_base58_encoding_validity() {
         // Test that all generated addresses are valid Base58
         for with_mnemonic in [false, true] {
-            let (_, keypair) = generate_keypair(with_mnemonic);
+            let (_, keypair) = generate_keypair(with_mnemonic).unwrap();
 
-            let pubkey_str = bs58::encode(keypair.pubkey().to_bytes()).into_string();
+            let pubkey_str = address_of(&keypair);
             let secret_str = bs58::encode(keypair.to_bytes()).into_string();
 
             // Should be able to decode back
@@ -383,12 +7691,12 @@ mod tests {
         assert!(signature.verify(keypair.pubkey().as_ref(), message));
 
         // The public key should be deterministic for this mnemonic
-        let pubkey_str = bs58::encode(keypair.pubkey().to_bytes()).into_string();
+        let pubkey_str = address_of(&keypair);
 
         // Generate again to ensure determinism
         let derived_seed2 = derive_solana_seed(&seed);
         let keypair2 = Keypair::from_seed(&derived_seed2).unwrap();
-        let pubkey_str2 = bs58::encode(keypair2.pubkey().to_bytes()).into_string();
+        let pubkey_str2 = address_of(&keypair2);
 
         assert_eq!(pubkey_str, pubkey_str2);
 
@@ -404,8 +7712,8 @@ mod tests {
 
         // Try up to 1000 iterations to find a keypair with the desired prefix
         for _ in 0..1000 {
-            let (_, keypair) = generate_keypair(false);
-            let pubkey_str = bs58::encode(keypair.pubkey().to_bytes()).into_string();
+            let (_, keypair) = generate_keypair(false).unwrap();
+            let pubkey_str = address_of(&keypair);
 
             if pubkey_str.starts_with(target_prefix) {
                 found_count += 1;
@@ -427,3 +7735,94 @@ mod tests {
         );
     }
 }
+
+/// Property tests over many mnemonics, paths and salts, kept apart for their own imports
+#[cfg(test)]
+mod round_trip_tests {
+    use super::*;
+    use proptest::prelude::*;
+
+    /// The paths of [`DERIVATION_SCHEMES`], plus arbitrary accounts and coin types
+    fn derivation_paths() -> impl Strategy<Value = DerivationPath> {
+        let schemes: Vec<DerivationPath> = DERIVATION_SCHEMES
+            .iter()
+            .filter_map(|(_, path)| path.map(|path| path.parse().unwrap()))
+            .collect();
+        prop_oneof![
+            prop::sample::select(schemes),
+            (0..slip10::HARDEND, 0..slip10::HARDEND).prop_map(|(account, change)| {
+                format!("m/44'/501'/{account}'/{change}'").parse().unwrap()
+            }),
+            (0..slip10::HARDEND).prop_map(|coin| DerivationPath::for_coin_type(coin).unwrap()),
+        ]
+    }
+
+    /// Standard BIP39, a BIP39 passphrase, or an experimental salt
+    fn seed_salts() -> impl Strategy<Value = SeedSalt> {
+        prop_oneof![
+            Just(SeedSalt::default()),
+            "[ -~]{1,16}".prop_map(SeedSalt::Bip39),
+            "[ -~]{1,16}".prop_map(SeedSalt::Experimental),
+        ]
+    }
+
+    proptest! {
+        // Every case runs PBKDF2 a few times, so keep the count modest for CI
+        #![proptest_config(ProptestConfig::with_cases(32))]
+
+        #[test]
+        fn test_saved_wallets_round_trip_across_derivation_schemes(
+            entropy in prop_oneof![Just(16), Just(32)]
+                .prop_flat_map(|len| prop::collection::vec(any::<u8>(), len)),
+            path in derivation_paths(),
+            salt in seed_salts(),
+        ) {
+            let mnemonic = Mnemonic::from_entropy(&entropy).unwrap().to_string();
+            let seed = derive_with_salt(&mnemonic, &salt, &path).unwrap();
+            let keypair = keypair_from_seed(&seed).unwrap();
+            let wallet = VanityWallet {
+                mnemonic: Some(mnemonic.clone()),
+                public_key: address_of(&keypair),
+                keypair,
+            };
+
+            // The schemes offered for identifying a wallet agree with the derivation that made it
+            if salt == SeedSalt::default() {
+                let listed = scheme_addresses(&mnemonic).unwrap();
+                let path_text = path.to_string();
+                if let Some(scheme) = listed.iter().find(|scheme| scheme.path == Some(&path_text)) {
+                    prop_assert_eq!(&scheme.address, &wallet.public_key);
+                }
+            }
+
+            let record = WalletRecord::from_wallet(&wallet)
+                .with_derivation_path(&path)
+                .with_seed_salt(&salt);
+            for contents in [record.to_text_file(None), serde_json::to_string(&record).unwrap()] {
+                let parsed = parse_saved_wallet(&contents).unwrap();
+                let secret = bs58::decode(&parsed.secret_key).into_vec().unwrap();
+                prop_assert_eq!(secret, wallet.keypair.to_bytes().to_vec());
+
+                // A BIP39 passphrase is never saved; its owner types it in again
+                let restored_salt = match (&parsed.experimental_salt, &salt) {
+                    (Some(saved), _) => SeedSalt::Experimental(saved.clone()),
+                    (None, SeedSalt::Bip39(passphrase)) => SeedSalt::Bip39(passphrase.clone()),
+                    (None, SeedSalt::Experimental(_)) => SeedSalt::default(),
+                };
+                let restored_path: DerivationPath =
+                    parsed.derivation_path.as_deref().unwrap().parse().unwrap();
+                let restored = derive_with_salt(
+                    parsed.mnemonic.as_deref().unwrap(),
+                    &restored_salt,
+                    &restored_path,
+                )
+                .unwrap();
+                prop_assert_eq!(restored, seed);
+
+                if !matches!(&salt, SeedSalt::Bip39(passphrase) if !passphrase.is_empty()) {
+                    prop_assert_eq!(verify_saved_wallet(&contents, &wallet.keypair), Ok(()));
+                }
+            }
+        }
+    }
+}
@@ -1,70 +1,624 @@
-use bip39::{Language, Mnemonic};
-use clap::Parser;
-use rand::rngs::OsRng;
-use rand::RngCore;
-use rayon::prelude::*;
-use serde_json::json;
-use slip10::{derive_key_from_path, BIP32Path};
-use solana_sdk::signature::{Keypair, SeedDerivable, Signer};
+use clap::{Args, Parser, Subcommand};
+use owo_colors::{OwoColorize, Style};
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::Signer;
+use solana_vanity_wallet::encryption::{self, KdfParams};
+use solana_vanity_wallet::entropy::{
+    looks_low_entropy, rng_health_check, EntropySource, HardwareRng, HARDWARE_ENTROPY_PATH,
+};
+use solana_vanity_wallet::job::{parse_jobs, JobResult, JOB_RESULTS_FILE};
+use solana_vanity_wallet::metrics::SearchMetrics;
+use solana_vanity_wallet::pause::{read_line_hidden, KeyListener, PauseState};
+use solana_vanity_wallet::repl::{parse_command, ReplCommand, Setting, HELP as REPL_HELP};
+use solana_vanity_wallet::{
+    account_addresses, address_of, append_to_manifest, associated_token_address, bench_patterns,
+    bench_rates, bits_as_base58_chars, case_variants, check_writable, cost_estimate,
+    derive_with_passphrase, detect_scheme, difficulty_breakdown, difficulty_comparison,
+    encrypt_wallet_bundle, ensure_output_dir, estimate_wall_clock, explain_derivation,
+    extend_search, filter_secret_keys, format_duration, format_number, format_percent,
+    generate_keypair_from, is_affirmative, is_valid_base58_prefix, iterations_for_probability,
+    keygen_prompt_uri, keypair_from_seed, luckiest_search, measure_rate, mnemonic_entropy_hex,
+    needs_confirmation, numbered_mnemonic, paper_backup_sheet, paper_wallet_sheet,
+    parse_encrypted_blob, parse_secret_key, prefix_difficulty_bits, prefix_overlaps,
+    prefix_probability, probability_found_by, profile_keygen, pubkey_list, save_or_dump,
+    saved_matches, scheme_addresses, search_bits_per_second, search_vanity, search_with_reporter,
+    secret_base58_to_json_array, secret_json_array_to_base58, secrets_on_stdout, unused_stem,
+    validate_mask, validate_prefix, verify_checksum_file, verify_saved_wallet, write_atomic,
+    write_checksum_file, write_encrypted_bundle, write_new, AccountRange, Banner, BenchResult,
+    CaseVariantMatcher, CharsetMatcher, ColorMode, ConstantTimePrefixMatcher, DerivationPath,
+    FirstCharSample, GiveUpAdvisor, Matcher, MatcherRegistry, NameFields, NameTemplate,
+    NormalizedMatch, PreferRarest, PrefixBonus, PrefixError, RateDropGuard, RateUnit, RateWindow,
+    SearchConfig, SearchProgress, SearchStats, SecretKeyError, SeedSalt, SeenSet, TargetWallet,
+    VanityPattern, VanityWallet, WalletRecord, BENCH_WINDOW, DEFAULT_RATE_DROP_SECONDS,
+    DEFAULT_RATE_DROP_SHARE, DERIVATION_SCHEMES, LONG_RUN_THRESHOLD, OUTPUT_DIR,
+    RAREST_GRACE_WINDOW, REPORTER_JOIN_TIMEOUT, SOLANA_DERIVATION_PATH, SUSPICIOUS_PROBABILITY,
+};
 use std::fs;
-use std::io::Write;
-use std::path::Path;
+use std::io::{self, IsTerminal, Write};
+use std::ops::Range;
+use std::path::{Path, PathBuf};
 use std::str::FromStr;
-use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
-use std::sync::Arc;
+use std::sync::atomic::Ordering;
+use std::sync::{Arc, OnceLock};
 use std::thread;
 use std::time::{Duration, Instant};
 
+// Running without a subcommand is the same as `search`, so `solana-vanity-wallet Sol` keeps working
 #[derive(Parser)]
-struct Args {
-    /// Desired prefix for the wallet
+#[command(args_conflicts_with_subcommands = true, subcommand_negates_reqs = true)]
+struct Cli {
+    #[command(subcommand)]
+    command: Option<Command>,
+    #[command(flatten)]
+    search: SearchArgs,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Mine a wallet whose address matches a prefix
+    Search(Box<SearchArgs>),
+    /// Generate one random wallet without any vanity search
+    Generate(GenerateArgs),
+    /// Show how many candidates a prefix needs for a given chance of success
+    Difficulty(DifficultyArgs),
+    /// Measure peak and sustained candidates per second for each mode and match type
+    Bench(BenchArgs),
+    /// List the secret keys in a file whose addresses match a pattern
+    FilterKeys(FilterKeysArgs),
+    /// Turn a Base58 secret key into a solana-keygen JSON byte array, or the other way round
+    Convert(ConvertArgs),
+    /// Work out which derivation scheme gives a known address from a mnemonic read from stdin
+    Detect(DetectArgs),
+}
+
+#[derive(Args)]
+struct DetectArgs {
+    /// Address the wallet shows for the mnemonic
+    address: String,
+}
+
+#[derive(Args)]
+struct ConvertArgs {
+    /// File holding one secret key, Base58 or a JSON byte array; read from stdin when omitted
+    input: Option<PathBuf>,
+    /// Write the converted key to this file instead of stdout
+    #[arg(long, short, value_name = "PATH")]
+    outfile: Option<PathBuf>,
+    /// Replace the --outfile if it already exists
+    #[arg(long, requires = "outfile")]
+    force: bool,
+    /// Print the converted key even when stdout is a pipe or file rather than a terminal
+    #[arg(long, conflicts_with = "outfile")]
+    allow_secret_stdout: bool,
+}
+
+#[derive(Args)]
+struct FilterKeysArgs {
+    /// File of secret keys, one per line, each Base58 or a JSON byte array
+    keys: PathBuf,
+    /// Text to look for, or the argument for --matcher
+    pattern: String,
+    /// Named matcher to apply to each address, as for search --matcher
+    #[arg(long, value_name = "NAME", default_value = "prefix")]
+    matcher: String,
+    /// Output format (text or json)
+    #[arg(long, short, default_value = "text")]
+    format: String,
+}
+
+#[derive(Args)]
+struct BenchArgs {
+    /// Seconds to run each mode and match type
+    #[arg(long, default_value_t = 5)]
+    seconds: u64,
+    /// Output format (text or json)
+    #[arg(long, short, default_value = "text")]
+    format: String,
+}
+
+#[derive(Args)]
+struct DifficultyArgs {
+    /// Prefix to estimate
     prefix: String,
-    /// Output format (json or text)
+    /// Desired chance of finding a match, between 0 and 1
+    #[arg(long, default_value_t = 0.9)]
+    probability: f64,
+    /// Candidates per second, to turn the iteration count into a time estimate
+    #[arg(long, value_name = "PER_SECOND", value_parser = positive_rate)]
+    rate: Option<f64>,
+    /// Also estimate the time and energy for a machine drawing this many watts (measures the
+    /// rate on this machine unless --rate is given)
+    #[arg(long, value_name = "WATTS")]
+    estimate_cost: Option<f64>,
+}
+
+#[derive(Args)]
+struct GenerateArgs {
+    /// Output format (json, text, env or backpack)
+    #[arg(long, short, default_value = "text")]
+    format: String,
+    /// With --format env, also export SOLANA_SECRET (insecure: other processes can read it);
+    /// implies --allow-secret-stdout, since the exports are meant for `eval "$(...)"`
+    #[arg(long)]
+    export_secret: bool,
+    /// Print the secret key and mnemonic even when stdout is a pipe or file rather than a terminal
+    #[arg(long)]
+    allow_secret_stdout: bool,
+    /// Generate mnemonic seed phrase (recoverable in standard wallets)
+    #[arg(long)]
+    with_mnemonic: bool,
+    /// Make a raw keypair with no recovery phrase (the default; states it explicitly)
+    #[arg(long, conflicts_with = "with_mnemonic")]
+    no_mnemonic: bool,
+    /// Name result files from this template, e.g. "{prefix}-{date}-{pubkey8}" (placeholders:
+    /// {prefix}, {date}, {pubkey}, {pubkeyN})
+    #[arg(long, value_name = "TEMPLATE")]
+    name_template: Option<NameTemplate>,
+    /// Read key entropy from the blocking OS entropy device instead of the userspace CSPRNG
+    #[arg(long)]
+    hardware_entropy: bool,
+    /// Sample the random number generator first and refuse to continue if it looks broken
+    #[arg(long)]
+    check_rng: bool,
+    /// Also print the raw BIP39 entropy behind the mnemonic, in hex (as sensitive as the mnemonic)
+    #[arg(long, requires = "with_mnemonic")]
+    show_entropy: bool,
+    /// Also print the mnemonic one numbered word per line, for transcribing by hand
+    #[arg(long, requires = "with_mnemonic")]
+    numbered_mnemonic: bool,
+    /// Derive the keypair at this hardened BIP32 path instead of m/44'/501'/0'/0'
+    #[arg(long, value_name = "PATH", requires = "with_mnemonic")]
+    derivation_path: Option<DerivationPath>,
+    /// Derive at m/44'/N'/0'/0' for this SLIP-0044 coin type instead of Solana's 501
+    #[arg(
+        long = "coin-type",
+        value_name = "N",
+        value_parser = coin_type_path,
+        requires = "with_mnemonic",
+        conflicts_with = "derivation_path"
+    )]
+    coin_type_path: Option<DerivationPath>,
+    /// Derive the way this wallet app does (phantom, solflare or backpack) and say how to
+    /// import the mnemonic there
+    #[arg(
+        long,
+        value_name = "WALLET",
+        requires = "with_mnemonic",
+        conflicts_with_all = ["derivation_path", "coin_type_path"]
+    )]
+    target_wallet: Option<TargetWallet>,
+    /// Also write a plain-text paper wallet that `solana-keygen recover` accepts (unencrypted)
+    #[arg(long, requires = "with_mnemonic")]
+    paper_wallet: bool,
+    /// Read the result file back and check it recovers exactly this keypair
+    #[arg(long)]
+    self_verify: bool,
+}
+
+#[derive(Args)]
+struct SearchArgs {
+    /// Desired prefix for the wallet
+    #[arg(required_unless_present_any = [
+        "verify_file",
+        "decrypt_backup",
+        "mask",
+        "matcher",
+        "prefix_env",
+        "charset_at",
+        "serve",
+        "compare_wallets",
+        "derive_only_account_range",
+        "profile",
+        "pda_prefix",
+        "job",
+        "explain",
+        "interactive"
+    ])]
+    prefix: Option<String>,
+    /// Read the prefix from this environment variable instead, keeping it out of process lists
+    #[arg(long, value_name = "VAR", conflicts_with_all = ["prefix", "mask", "matcher"])]
+    prefix_env: Option<String>,
+    /// Match a pattern at the start of the address, with ? for any character (e.g. So?a??)
+    #[arg(
+        long,
+        value_name = "MASK",
+        conflicts_with_all = ["prefix", "anywhere_anchor", "extend_time"]
+    )]
+    mask: Option<String>,
+    /// Restrict one address position to a set of characters, e.g. 0=ABC (repeatable)
+    #[arg(
+        long,
+        value_name = "POS=CHARS",
+        conflicts_with_all = ["prefix", "mask", "prefix_env", "anywhere_anchor", "extend_time"]
+    )]
+    charset_at: Vec<String>,
+    /// Use a named matcher (prefix, suffix, either, mask, regex, charset, any-prefix or
+    /// smart-case) with --pattern
+    #[arg(
+        long,
+        value_name = "NAME",
+        requires = "pattern",
+        conflicts_with_all = ["prefix", "mask", "charset_at", "anywhere_anchor", "extend_time"]
+    )]
+    matcher: Option<String>,
+    /// Argument for --matcher, such as the prefix text or regular expression
+    #[arg(long, value_name = "ARG", requires = "matcher")]
+    pattern: Option<String>,
+    /// Mine a wallet whose program-derived address starts with this prefix (experimental, needs
+    /// the pda feature)
+    #[arg(
+        long,
+        value_name = "PREFIX",
+        requires = "program_id",
+        conflicts_with_all = [
+            "prefix",
+            "mask",
+            "prefix_env",
+            "charset_at",
+            "matcher",
+            "anywhere_anchor",
+            "extend_time"
+        ]
+    )]
+    pda_prefix: Option<String>,
+    /// Program that derives the address for --pda-prefix
+    #[arg(long, value_name = "ID", requires = "pda_prefix")]
+    program_id: Option<String>,
+    /// Seed placed after the wallet's public key when deriving the address for --pda-prefix
+    #[arg(long, value_name = "SEED", default_value = "", requires = "pda_prefix")]
+    pda_seed: String,
+    /// Output format (json, text, env or backpack)
     #[arg(long, short, default_value = "text")]
     format: String,
+    /// With --format env, also export SOLANA_SECRET (insecure: other processes can read it);
+    /// implies --allow-secret-stdout, since the exports are meant for `eval "$(...)"`
+    #[arg(long)]
+    export_secret: bool,
+    /// Print the secret key and mnemonic even when stdout is a pipe or file rather than a terminal
+    #[arg(long)]
+    allow_secret_stdout: bool,
     /// Test first character distribution
     #[arg(long)]
     test_chars: bool,
     /// Generate mnemonic seed phrase (slower but recoverable)
     #[arg(long)]
     with_mnemonic: bool,
+    /// Mine raw keypairs with no recovery phrase, the fastest mode (the default; states it
+    /// explicitly)
+    #[arg(long, conflicts_with = "with_mnemonic")]
+    no_mnemonic: bool,
+    /// Name result files from this template, e.g. "{prefix}-{date}-{pubkey8}" (placeholders:
+    /// {prefix}, {date}, {pubkey}, {pubkeyN})
+    #[arg(long, value_name = "TEMPLATE")]
+    name_template: Option<NameTemplate>,
+    /// Accept the prefix at either the start or the end of the address
+    #[arg(long)]
+    anywhere_anchor: bool,
+    /// Also accept the capitalized spelling of an all-letters prefix, e.g. sun finds sun or Sun
+    #[arg(
+        long,
+        requires = "prefix",
+        conflicts_with_all = ["anywhere_anchor", "extend_target", "prefer_rarest"]
+    )]
+    smart_case: bool,
+    /// Compare the whole prefix for every candidate, so timing can't show how close it came
+    /// (a little slower)
+    #[arg(
+        long,
+        requires = "prefix",
+        conflicts_with_all = ["anywhere_anchor", "smart_case", "extend_target", "prefer_rarest"]
+    )]
+    constant_time_match: bool,
+    /// Refuse a prefix whose first character leads at most this share of sampled addresses, e.g.
+    /// 0.005 (every character leads some, so nothing is refused without this)
+    #[arg(long, value_name = "SHARE")]
+    feasibility_threshold: Option<f64>,
+    /// Also show the associated token account address for this mint
+    #[arg(long, value_name = "MINT")]
+    show_ata: Option<String>,
+    /// Number of one-second samples averaged for the live rate display
+    #[arg(long, value_name = "SAMPLES", default_value_t = 5)]
+    rate_window: usize,
+    /// Show rates in a fixed unit instead of scaling them: keys, K, M or SUFFIX=DIVISOR such as kH=1000
+    #[arg(long, value_name = "UNIT", default_value = "auto")]
+    rate_unit: RateUnit,
+    /// Check a result file against its .sha256 sidecar and exit
+    #[arg(long, value_name = "PATH")]
+    verify_file: Option<PathBuf>,
+    /// Write a printable sheet with the mnemonic encrypted under a passphrase (needs --with-mnemonic)
+    #[arg(long, requires = "with_mnemonic")]
+    paper_backup: bool,
+    /// Environment variable holding the backup passphrase
+    #[arg(long, value_name = "VAR", default_value = "VANITY_WALLET_PASSPHRASE")]
+    passphrase_env: String,
+    /// Decrypt a paper backup's encrypted line or a secrets bundle and print it, then exit
+    #[arg(long, value_name = "PATH")]
+    decrypt_backup: Option<PathBuf>,
+    /// Write only the public keys here, one per line, and the secrets to an encrypted
+    /// PATH.secrets.json bundle instead of per-wallet result files
+    #[arg(
+        long,
+        value_name = "PATH",
+        conflicts_with_all = [
+            "paper_backup",
+            "paper_wallet",
+            "append_to_single_json",
+            "show_entropy",
+            "numbered_mnemonic"
+        ]
+    )]
+    output_pubkey_list: Option<PathBuf>,
+    /// Write the found wallets only to stdout, as one base64 line of encrypted bundle, with
+    /// nothing saved to disk and all status on stderr
+    #[arg(
+        long,
+        conflicts_with_all = [
+            "output_pubkey_list",
+            "paper_backup",
+            "paper_wallet",
+            "append_to_single_json",
+            "show_entropy",
+            "numbered_mnemonic",
+            "self_verify",
+            "extend_time",
+            "best_effort"
+        ]
+    )]
+    encrypted_stdout: bool,
+    /// Also append the result to a single JSON array manifest at this path
+    #[arg(long, value_name = "PATH")]
+    append_to_single_json: Option<PathBuf>,
+    /// Stop searching after this many candidates
+    #[arg(long, value_name = "N")]
+    max_iterations: Option<u64>,
+    /// Stop searching after this many seconds
+    #[arg(long, value_name = "SECS")]
+    max_seconds: Option<u64>,
+    /// If no exact match is found within the limits, save the closest address instead
+    #[arg(long)]
+    best_effort: bool,
+    /// Count how many other candidates shared the first 1, 2 and 3 target characters and add
+    /// the histogram to the statistics
+    #[arg(long)]
+    collect_near_misses: bool,
+    /// Number of wallets to find, one after another
+    #[arg(
+        long,
+        value_name = "N",
+        default_value_t = 1,
+        value_parser = clap::builder::RangedU64ValueParser::<usize>::new().range(1..)
+    )]
+    count: usize,
+    /// Keep finding matches for this many seconds and save only the one with the best luck factor
+    #[arg(
+        long,
+        value_name = "SECONDS",
+        conflicts_with_all = [
+            "count",
+            "best_effort",
+            "extend_time",
+            "output_pubkey_list",
+            "encrypted_stdout"
+        ]
+    )]
+    luckiest: Option<u64>,
+    /// Count the wallets matching the pattern already in the output directory toward --count and only
+    /// find the rest, so an interrupted batch can be restarted
+    #[arg(
+        long,
+        conflicts_with_all = ["luckiest", "output_pubkey_list", "encrypted_stdout", "job"]
+    )]
+    resume: bool,
+    /// Pass over addresses listed in this file, one per line, and append every new find to it,
+    /// so repeated runs never produce the same address twice
+    #[arg(long, value_name = "PATH", conflicts_with_all = ["encrypted_stdout", "job"])]
+    seen_set: Option<PathBuf>,
+    /// In --count mode, give up on any one wallet after this many seconds and move on
+    #[arg(long, value_name = "SECONDS")]
+    timeout_per_wallet: Option<u64>,
+    /// Stop with a warning if the rate stays far below its peak, as when the machine throttles
+    #[arg(long, conflicts_with_all = ["luckiest", "job"])]
+    stop_on_rate_drop: bool,
+    /// With --stop-on-rate-drop, the share of the peak rate that counts as a drop
+    #[arg(
+        long,
+        value_name = "SHARE",
+        default_value_t = DEFAULT_RATE_DROP_SHARE,
+        value_parser = rate_drop_share,
+        requires = "stop_on_rate_drop"
+    )]
+    rate_drop_share: f64,
+    /// With --stop-on-rate-drop, how long the rate has to stay below that share
+    #[arg(
+        long,
+        value_name = "SECONDS",
+        default_value_t = DEFAULT_RATE_DROP_SECONDS,
+        requires = "stop_on_rate_drop"
+    )]
+    rate_drop_seconds: u64,
+    /// Draw every candidate from the blocking OS entropy device (much slower)
+    #[arg(long)]
+    hardware_entropy: bool,
+    /// Sample the random number generator first and refuse to search if it looks broken
+    #[arg(long)]
+    check_rng: bool,
+    /// After a match, keep searching this many seconds for one closer to --extend-target
+    #[arg(long, value_name = "SECONDS", requires = "extend_target")]
+    extend_time: Option<u64>,
+    /// Longer address start to aim for while extending (must begin with the prefix)
+    #[arg(long, value_name = "TEXT", requires = "extend_time")]
+    extend_target: Option<String>,
+    /// Collect matches for a moment after the first and keep the one with most of this longer
+    /// address start (must begin with the prefix)
+    #[arg(
+        long,
+        value_name = "TEXT",
+        conflicts_with_all = ["mask", "charset_at", "matcher", "pda_prefix"]
+    )]
+    prefer_rarest: Option<String>,
+    /// Also print the raw BIP39 entropy behind the mnemonic, in hex (as sensitive as the mnemonic)
+    #[arg(long, requires = "with_mnemonic")]
+    show_entropy: bool,
+    /// Also print the mnemonic one numbered word per line, for transcribing by hand
+    #[arg(long, requires = "with_mnemonic")]
+    numbered_mnemonic: bool,
+    /// Derive the keypair at this hardened BIP32 path instead of m/44'/501'/0'/0'
+    #[arg(long, value_name = "PATH", requires = "with_mnemonic")]
+    derivation_path: Option<DerivationPath>,
+    /// Derive at m/44'/N'/0'/0' for this SLIP-0044 coin type instead of Solana's 501
+    #[arg(
+        long = "coin-type",
+        value_name = "N",
+        value_parser = coin_type_path,
+        requires = "with_mnemonic",
+        conflicts_with = "derivation_path"
+    )]
+    coin_type_path: Option<DerivationPath>,
+    /// Derive the way this wallet app does (phantom, solflare or backpack) and say how to
+    /// import the mnemonic there
+    #[arg(
+        long,
+        value_name = "WALLET",
+        requires = "with_mnemonic",
+        conflicts_with_all = ["derivation_path", "coin_type_path"]
+    )]
+    target_wallet: Option<TargetWallet>,
+    /// Also write a plain-text paper wallet that `solana-keygen recover` accepts (unencrypted)
+    #[arg(long, requires = "with_mnemonic")]
+    paper_wallet: bool,
+    /// Read the result file back and check it recovers exactly this keypair
+    #[arg(long)]
+    self_verify: bool,
+    /// EXPERIMENTAL and non-standard: stretch the mnemonic with this salt instead of BIP39's, so
+    /// the wallets only recover in tools that know it (not Phantom, Solflare or solana-keygen)
+    #[arg(
+        long,
+        value_name = "SALT",
+        value_parser = experimental_salt,
+        requires = "with_mnemonic",
+        conflicts_with_all = ["target_wallet", "paper_wallet", "paper_backup", "job"]
+    )]
+    experimental_salt: Option<SeedSalt>,
+    /// Don't ask for confirmation before a long search
+    #[arg(long, short)]
+    yes: bool,
+    /// Print extra detail, such as how much each prefix character adds to the difficulty
+    #[arg(long, short)]
+    verbose: bool,
+    /// Skip the startup header (pattern, mode, threads, difficulty); progress and results still
+    /// print
+    #[arg(long)]
+    no_banner: bool,
+    /// Color output: auto (only on a terminal), always or never
+    #[arg(long, value_name = "WHEN", default_value = "auto")]
+    color: ColorMode,
+    /// Start a prompt that runs search, estimate, restore and config commands until quit
+    #[arg(long, conflicts_with_all = ["prefix", "serve"])]
+    interactive: bool,
+    /// Run an HTTP service on this address instead of searching once (needs the server feature)
+    #[arg(long, value_name = "ADDR", conflicts_with = "prefix")]
+    serve: Option<String>,
+    /// Searches the HTTP service runs at once; more requests get 503
+    #[arg(long, value_name = "N", default_value_t = 2, requires = "serve")]
+    max_concurrent: usize,
+    /// Read a mnemonic from stdin and print its address under each common derivation scheme
+    #[arg(long, conflicts_with = "prefix")]
+    compare_wallets: bool,
+    /// Read a mnemonic from stdin and print the addresses of accounts START up to END, e.g. 0..10,
+    /// without searching (--format json for a JSON array)
+    #[arg(long, value_name = "START..END", conflicts_with = "prefix")]
+    derive_only_account_range: Option<AccountRange>,
+    /// Read a mnemonic from stdin and print each step of deriving its address at PATH (default
+    /// m/44'/501'/0'/0'), then exit
+    #[arg(
+        long,
+        value_name = "PATH",
+        num_args = 0..=1,
+        default_missing_value = SOLANA_DERIVATION_PATH,
+        conflicts_with = "prefix"
+    )]
+    explain: Option<DerivationPath>,
+    /// Time each step of making N wallets in fast and mnemonic mode and print a breakdown
+    #[arg(long, value_name = "N", conflicts_with = "prefix")]
+    profile: Option<u64>,
+    /// Run every search listed in this JSON job file in turn and write job_results.json
+    #[arg(
+        long,
+        value_name = "FILE",
+        conflicts_with_all = [
+            "prefix",
+            "mask",
+            "prefix_env",
+            "charset_at",
+            "matcher",
+            "pda_prefix",
+            "count",
+            "luckiest",
+            "derivation_path",
+            "coin_type_path",
+            "target_wallet",
+            "output_pubkey_list",
+            "encrypted_stdout",
+            "extend_time",
+            "prefer_rarest",
+            "best_effort"
+        ]
+    )]
+    job: Option<PathBuf>,
+    /// Expose Prometheus metrics at http://ADDR/metrics while searching (needs the metrics feature)
+    #[arg(long, value_name = "ADDR")]
+    metrics_addr: Option<String>,
 }
 
-fn calculate_expected_iterations(prefix: &str) -> u64 {
-    // Base58 alphabet has 58 characters
-    // Expected iterations = 58^(prefix_length) / 2 (on average)
-    let base: u64 = 58;
-    let length = prefix.len() as u32;
-    base.pow(length) / 2
+/// Terminal styling for the search output; every method returns plain text when color is off
+#[derive(Clone, Copy)]
+struct Palette {
+    enabled: bool,
 }
 
-fn format_duration(seconds: f64) -> String {
-    if seconds < 60.0 {
-        format!("{seconds:.1}s")
-    } else if seconds < 3600.0 {
-        format!("{:.1}m", seconds / 60.0)
-    } else if seconds < 86400.0 {
-        format!("{:.1}h", seconds / 3600.0)
-    } else {
-        format!("{:.1}d", seconds / 86400.0)
+impl Palette {
+    fn new(mode: ColorMode) -> Self {
+        let no_color = std::env::var_os("NO_COLOR").is_some_and(|value| !value.is_empty());
+        Palette {
+            enabled: mode.enabled(io::stdout().is_terminal(), no_color),
+        }
     }
-}
 
-fn format_number(num: u64) -> String {
-    if num < 1_000 {
-        format!("{num}")
-    } else if num < 1_000_000 {
-        format!("{:.1}K", num as f64 / 1_000.0)
-    } else if num < 1_000_000_000 {
-        format!("{:.1}M", num as f64 / 1_000_000.0)
-    } else if num < 1_000_000_000_000 {
-        format!("{:.1}B", num as f64 / 1_000_000_000.0)
-    } else {
-        format!("{:.1}T", num as f64 / 1_000_000_000_000.0)
+    fn paint(self, text: &str, style: Style) -> String {
+        if self.enabled {
+            text.style(style).to_string()
+        } else {
+            text.to_string()
+        }
+    }
+
+    fn title(self, text: &str) -> String {
+        self.paint(text, Style::new().bold())
+    }
+
+    fn success(self, text: &str) -> String {
+        self.paint(text, Style::new().green().bold())
+    }
+
+    fn rate(self, text: &str) -> String {
+        self.paint(text, Style::new().cyan())
+    }
+
+    /// `address` with the characters in `ranges` picked out
+    fn highlight(self, address: &str, ranges: &[Range<usize>]) -> String {
+        let mut out = String::new();
+        let mut end = 0;
+        for range in ranges {
+            out.push_str(&address[end..range.start]);
+            out.push_str(&self.paint(&address[range.clone()], Style::new().yellow().bold()));
+            end = range.end;
+        }
+        out.push_str(&address[end..]);
+        out
     }
 }
 
-fn format_json_compact_array(value: &serde_json::Value) -> String {
+fn format_json_compact_array(value: &impl serde::Serialize) -> String {
     let pretty = serde_json::to_string_pretty(value).unwrap();
 
     // Use regex to replace the keypair_json array formatting
@@ -84,307 +638,2419 @@ fn format_json_compact_array(value: &serde_json::Value) -> String {
     .to_string()
 }
 
-fn derive_solana_seed(seed: &[u8]) -> [u8; 32] {
-    // Solana BIP44 derivation path: m/44'/501'/0'/0'
-    // 501 is Solana's coin type in BIP44
-    let path = BIP32Path::from_str("m/44'/501'/0'/0'").unwrap();
-
-    // Derive the key using SLIP10 (BIP32 for Ed25519)
-    let derived_key = derive_key_from_path(seed, slip10::Curve::Ed25519, &path).unwrap();
-
-    // Return the private key bytes
-    derived_key.key
+/// Create the output directory and check it's writable, or exit with a clean error
+fn prepare_output_dir(output_dir: &Path) {
+    if let Err(err) = ensure_output_dir(output_dir) {
+        eprintln!(
+            "❌ Error: Unable to create output directory {}: {err}",
+            output_dir.display()
+        );
+        std::process::exit(1);
+    }
+    if let Err(err) = check_writable(output_dir) {
+        eprintln!(
+            "❌ Error: Output directory {} is not writable: {err}",
+            output_dir.display()
+        );
+        std::process::exit(1);
+    }
 }
 
-fn is_valid_base58_prefix(prefix: &str) -> bool {
-    // Base58 alphabet: 123456789ABCDEFGHJKLMNPQRSTUVWXYZabcdefghijkmnopqrstuvwxyz
-    // Notable exclusions: 0, O, l (to avoid confusion)
-    const BASE58_ALPHABET: &str = "123456789ABCDEFGHJKLMNPQRSTUVWXYZabcdefghijkmnopqrstuvwxyz";
+/// Which of a wallet's secrets [`emit_wallet`] may print
+#[derive(Debug, Clone, Copy)]
+struct SecretOutput {
+    /// Print the secret key and mnemonic at all; see `secrets_on_stdout`
+    shown: bool,
+    /// With --format env, also export SOLANA_SECRET
+    export: bool,
+}
 
-    if prefix.is_empty() {
-        return false;
+impl SecretOutput {
+    /// --export-secret counts as allowing secrets on stdout: its whole point is a piped `eval`
+    fn new(allow_secret_stdout: bool, export_secret: bool) -> Self {
+        SecretOutput {
+            shown: secrets_on_stdout(stdout_is_terminal(), allow_secret_stdout || export_secret),
+            export: export_secret,
+        }
     }
 
-    prefix.chars().all(|c| BASE58_ALPHABET.contains(c))
+    /// Put `dump`, a secret-bearing file that couldn't be saved, where it won't be lost
+    ///
+    /// Stdout when secrets may be printed there, stderr if a person is watching it. Otherwise a
+    /// redirected stderr is just another log file, so it goes to a rescue file in the temp dir;
+    /// stderr is still the last resort, as losing the wallet is worse. Returns where it went.
+    fn rescue(self, dump: &[u8]) -> String {
+        if self.shown {
+            let _ = io::stdout().write_all(dump);
+            return "its contents are printed on stdout above".to_string();
+        }
+        if !io::stderr().is_terminal() {
+            match write_rescue_file(dump) {
+                Ok(path) => return format!("its contents are in {}", path.display()),
+                Err(err) => eprintln!("⚠️  Warning: Unable to write a rescue file: {err}"),
+            }
+        }
+        let _ = io::stderr().write_all(dump);
+        "its contents are printed on stderr above".to_string()
+    }
 }
 
-fn main() {
-    let args = Args::parse();
-
-    // Validate the prefix contains only valid Base58 characters
-    if !is_valid_base58_prefix(&args.prefix) {
-        eprintln!("❌ Error: Invalid prefix '{}'", args.prefix);
-        eprintln!();
-        eprintln!("Valid Base58 characters are:");
-        eprintln!("  Numbers: 1-9 (excludes 0)");
-        eprintln!("  Uppercase: A-Z (excludes O)");
-        eprintln!("  Lowercase: a-z (excludes l)");
-        eprintln!();
-        eprintln!("Examples of valid prefixes: ABC, Sol, 123, MyWallet, IJKL");
-        eprintln!("Examples of invalid prefixes: 0, O, l, _, +, =, /");
-        std::process::exit(1);
-    }
+/// Write `dump` to a new file in the temp dir that only the owner can read, returning its path
+fn write_rescue_file(dump: &[u8]) -> io::Result<PathBuf> {
+    let path = std::env::temp_dir().join(format!(
+        "solana-vanity-wallet-rescue-{}-{}.txt",
+        std::process::id(),
+        chrono::Utc::now().format("%Y%m%d%H%M%S%f")
+    ));
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+    let mut file = options.open(&path)?;
+    file.write_all(dump)?;
+    file.sync_all()?;
+    Ok(path)
+}
 
-    let found = Arc::new(AtomicBool::new(false));
-    let total_iterations = Arc::new(AtomicU64::new(0));
-    let cpu_count = num_cpus::get();
+/// Print `record` in `format` and save the same content (plus `stats_section`) to `output_dir`
+///
+/// Text output shows `shown_key` in place of the public key when given, so it can carry color
+/// codes that stay out of the saved file. The env format prints only `export` lines and the
+/// backpack format only the Base58 secret key; both save the text file. Unless `secrets.shown`,
+/// only the public key is printed and the secrets are left to the file. Returns the file
+/// written; if saving fails, the content is rescued with [`SecretOutput::rescue`] and the
+/// process exits with an error, since the wallet exists nowhere else.
+fn emit_wallet(
+    record: &WalletRecord,
+    format: &str,
+    secrets: SecretOutput,
+    output_dir: &Path,
+    stats_section: Option<&str>,
+    shown_key: Option<&str>,
+    file_stem: &str,
+) -> PathBuf {
+    let pubkey = &record.public_key;
 
-    let expected_iterations = calculate_expected_iterations(&args.prefix);
+    let (file_path, file_output) = if format == "json" {
+        // JSON format: print and save as JSON
+        // Serialized directly: a JSON Value can't hold the u128 counts of a very long search
+        let output_string = format_json_compact_array(record);
+        if secrets.shown {
+            println!("{output_string}");
+        } else {
+            println!("{}", serde_json::json!({ "public_key": pubkey }));
+        }
 
-    println!("🚀 Solana Vanity Wallet Generator");
-    println!("==================================");
-    println!("Prefix: {}", args.prefix);
-    println!(
-        "Mode: {}",
-        if args.with_mnemonic {
-            "With mnemonic (slower, wallet-compatible)"
+        (
+            output_dir.join(format!("{file_stem}_output.json")),
+            output_string,
+        )
+    } else {
+        if format == "env" {
+            // Env format: stdout is for eval, so only the exports go there
+            print_exports(&record.to_env_exports(secrets.export && secrets.shown));
+        } else if format == "backpack" {
+            // Backpack's private key import takes the bare key, so stdout is just that line
+            if secrets.shown {
+                println!("{}", record.secret_key);
+            }
+            eprintln!("Public Key: {pubkey}");
+        } else if secrets.shown {
+            // Text format: print formatted text, save as text file
+            println!("{}", record.details(shown_key.unwrap_or(pubkey)));
         } else {
-            "Fast mode (no mnemonic)"
+            println!("Public Key: {}", shown_key.unwrap_or(pubkey));
         }
-    );
-    println!("Threads: {cpu_count}");
-    println!(
-        "Expected iterations: {}",
-        format_number(expected_iterations)
-    );
-    println!(
-        "Estimated difficulty: 1 in {}",
-        format_number(expected_iterations * 2)
-    );
-    println!();
 
-    let start_time = Instant::now();
-    let stats_counter = Arc::clone(&total_iterations);
-    let stats_found = Arc::clone(&found);
+        (
+            output_dir.join(format!("{file_stem}_output.txt")),
+            record.to_text_file(stats_section),
+        )
+    };
+    let mut dump = Vec::new();
+    if save_or_dump(&file_path, &file_output, &mut dump).is_err() {
+        let rescued = secrets.rescue(&dump);
+        eprintln!(
+            "❌ Error: Unable to save {}, so the secret key was NOT saved; {rescued}",
+            file_path.display()
+        );
+        std::process::exit(1);
+    }
+    if !secrets.shown {
+        eprintln!(
+            "🔒 Secrets not printed because stdout isn't a terminal; they're in {} \
+             (--allow-secret-stdout prints them)",
+            file_path.display()
+        );
+    }
+    match write_checksum_file(&file_path, file_output.as_bytes()) {
+        // Keep stdout to what the format promises
+        Ok(checksum_path) if format == "env" || format == "backpack" => {
+            eprintln!("🔒 Checksum written to {}", checksum_path.display())
+        }
+        Ok(checksum_path) => println!("🔒 Checksum written to {}", checksum_path.display()),
+        Err(err) => eprintln!("⚠️  Warning: Unable to write checksum file: {err}"),
+    }
 
-    // Statistics thread
-    let stats_thread = thread::spawn(move || {
-        let mut last_count = 0;
-        let mut last_time = Instant::now();
+    file_path
+}
 
-        while !stats_found.load(Ordering::Relaxed) {
-            thread::sleep(Duration::from_secs(1));
+/// Parse `--coin-type` straight into the derivation path it stands for
+fn coin_type_path(value: &str) -> Result<DerivationPath, String> {
+    let coin_type: u32 = value
+        .parse()
+        .map_err(|_| format!("'{value}' is not a coin type number"))?;
+    DerivationPath::for_coin_type(coin_type).map_err(|err| err.to_string())
+}
 
-            let current_count = stats_counter.load(Ordering::Relaxed);
-            let current_time = Instant::now();
-            let elapsed = current_time.duration_since(last_time).as_secs_f64();
+/// Parse `--experimental-salt`; it's saved as a line of the result file, so it must fit on one
+fn experimental_salt(value: &str) -> Result<SeedSalt, String> {
+    if value.is_empty() {
+        return Err("the salt is empty".to_string());
+    }
+    if value.chars().any(char::is_control) {
+        return Err("the salt can't contain control characters or line breaks".to_string());
+    }
+    Ok(SeedSalt::Experimental(value.to_string()))
+}
 
-            if elapsed >= 1.0 {
-                let iterations_per_second = ((current_count - last_count) as f64) / elapsed;
-                let total_elapsed = current_time.duration_since(start_time).as_secs_f64();
-                let overall_rate = current_count as f64 / total_elapsed;
+/// Parse `--rate`, candidates per second for an estimate, which has to be above zero
+fn positive_rate(value: &str) -> Result<f64, String> {
+    match value.parse::<f64>() {
+        Ok(rate) if rate > 0.0 && rate.is_finite() => Ok(rate),
+        _ => Err(format!("'{value}' is not a rate above zero, e.g. 40000")),
+    }
+}
 
-                let progress = (current_count as f64 / expected_iterations as f64) * 100.0;
-                let eta_seconds = if overall_rate > 0.0 {
-                    (expected_iterations as f64 - current_count as f64) / overall_rate
-                } else {
-                    0.0
-                };
+/// Parse `--rate-drop-share`, a fraction of the peak rate strictly between 0 and 1
+fn rate_drop_share(value: &str) -> Result<f64, String> {
+    match value.parse::<f64>() {
+        Ok(share) if share > 0.0 && share < 1.0 => Ok(share),
+        _ => Err(format!(
+            "'{value}' is not a share between 0 and 1, e.g. 0.4"
+        )),
+    }
+}
 
-                print!(
-                    "\r🔍 Iterations: {} | Rate: {}/s | Progress: {:.2}% | ETA: {} | Elapsed: {}",
-                    format_number(current_count),
-                    format_number(iterations_per_second as u64),
-                    progress.min(100.0),
-                    format_duration(eta_seconds),
-                    format_duration(total_elapsed)
+/// How far a search that hit its limits got, and what another attempt would take
+fn no_match_summary(iterations: u128, expected_iterations: Option<u128>) -> Vec<String> {
+    // The banner's difficulty, 1 in this many, is the average number of candidates per match
+    let Some(average) = expected_iterations.map(|expected| expected.saturating_mul(2)) else {
+        return vec!["This matcher can't estimate its odds, so there's no more to report".into()];
+    };
+    let chance = probability_found_by(iterations, average as f64);
+    let mut lines = vec![
+        format!(
+            "Iterations done: {} ({:.2}x the {} a match takes on average)",
+            format_number(iterations),
+            iterations as f64 / average as f64,
+            format_number(average)
+        ),
+        format!(
+            "Chance of a match by now: {:.1}%{}",
+            chance * 100.0,
+            if chance > 0.9 {
+                ", so this was an unlucky run"
+            } else {
+                ""
+            }
+        ),
+    ];
+    // Candidates are independent, so the work already done doesn't count toward the next run
+    if let Some(more) = iterations_for_probability(1.0 / average as f64, 0.9) {
+        lines.push(format!(
+            "A fresh run needs about {} iterations for a 90% chance",
+            format_number(more)
+        ));
+    }
+    lines
+}
+
+/// Search for `count` wallets and write them to stdout as one encrypted bundle
+///
+/// Status goes to stderr and no file is written, so stdout carries nothing but ciphertext.
+fn search_to_encrypted_stdout(
+    config: &SearchConfig,
+    count: usize,
+    passphrase: &str,
+    target_wallet: Option<TargetWallet>,
+) {
+    let mut records = Vec::new();
+    for number in 1..=count {
+        let result = search_vanity(config, &SearchProgress::default());
+        match result.wallet {
+            Some(wallet) => {
+                eprintln!(
+                    "🔑 Wallet {number} of {count}: {} after {} iterations",
+                    wallet.public_key,
+                    format_number(result.stats.iterations)
                 );
+                records.push(
+                    WalletRecord::new(&wallet, &result.stats)
+                        .with_derivation_path(&config.derivation_path)
+                        .with_target_wallet(target_wallet)
+                        .with_seed_salt(&config.seed_salt),
+                );
+            }
+            None => eprintln!(
+                "⏭️  Wallet {number} of {count} not found within limits after {} iterations",
+                format_number(result.stats.iterations)
+            ),
+        }
+    }
 
-                use std::io::{self, Write};
-                io::stdout().flush().unwrap();
+    if records.is_empty() {
+        eprintln!("❌ No wallets found; nothing written");
+        std::process::exit(1);
+    }
+    if let Err(err) = write_encrypted_bundle(
+        &records,
+        passphrase,
+        KdfParams::default(),
+        &mut io::stdout(),
+    ) {
+        eprintln!("❌ Error: Unable to write the encrypted bundle: {err}");
+        std::process::exit(1);
+    }
+    eprintln!("🔒 {} wallets written to stdout, encrypted", records.len());
+}
 
-                last_count = current_count;
-                last_time = current_time;
-            }
+/// Read the saved result file back and exit if it doesn't recover `wallet`'s keypair
+fn self_verify(path: &Path, wallet: &VanityWallet) {
+    let verified = fs::read_to_string(path)
+        .map_err(|err| err.to_string())
+        .and_then(|contents| {
+            verify_saved_wallet(&contents, &wallet.keypair).map_err(|err| err.to_string())
+        });
+    match verified {
+        Ok(()) => println!("✅ Self-verify: {} recovers this keypair", path.display()),
+        Err(err) => {
+            eprintln!("❌ Error: Self-verify failed for {}: {err}", path.display());
+            eprintln!("   Don't rely on that file; copy the keys shown above instead.");
+            std::process::exit(1);
         }
-    });
+    }
+}
 
-    // Result storage
-    let result_data = Arc::new(parking_lot::Mutex::new(
-        None::<(Option<String>, String, String, Vec<u8>, u64, f64)>,
-    ));
+/// Point out that a fast-mode wallet has no recovery phrase and how to import it instead
+///
+/// Goes to stderr so it never ends up in `eval "$(... --format env)"`.
+fn note_no_recovery_phrase(wallet: &VanityWallet) {
+    if wallet.mnemonic.is_none() {
+        eprintln!("ℹ️  No recovery phrase: the Secret Key (or keypair file) is the only backup.");
+        eprintln!("   Import it in Phantom with \"Import Private Key\", or in the CLI with the keypair JSON.");
+    }
+}
 
-    // Worker threads
-    (0..cpu_count).into_par_iter().for_each(|_| {
-        let local_found = Arc::clone(&found);
-        let local_counter = Arc::clone(&total_iterations);
-        let local_result = Arc::clone(&result_data);
-        let use_mnemonic = args.with_mnemonic;
-        let mut rng = OsRng;
-        let mut local_iterations = 0u64;
-
-        while !local_found.load(Ordering::Relaxed) {
-            let (mnemonic_opt, keypair) = if use_mnemonic {
-                // Slow mode: Generate mnemonic and derive keypair (compatible with wallets)
-                let mut entropy = [0u8; 16];
-                rng.fill_bytes(&mut entropy);
-                let mnemonic = Mnemonic::from_entropy_in(Language::English, &entropy).unwrap();
-
-                // Generate keypair from the mnemonic using proper Solana BIP44 derivation
-                let seed = mnemonic.to_seed("");
-                let derived_seed = derive_solana_seed(&seed);
-                let keypair = Keypair::from_seed(&derived_seed).unwrap();
-
-                (Some(mnemonic.to_string()), keypair)
-            } else {
-                // Fast mode: Generate keypair directly from random seed
-                let mut seed = [0u8; 32];
-                rng.fill_bytes(&mut seed);
-                let keypair = Keypair::from_seed(&seed).unwrap();
+/// The real stdout once [`reserve_stdout_for_exports`] has sent everything else to stderr
+static EXPORT_STDOUT: OnceLock<fs::File> = OnceLock::new();
 
-                (None, keypair)
-            };
+/// With --format env, send stdout to stderr so only the exports reach `eval "$(...)"`
+///
+/// The banner, progress, notes and prompts all print to stdout. Rather than reroute each of
+/// them, the process keeps a copy of the real stdout for [`print_exports`] and points its own
+/// stdout at stderr. Unix only; elsewhere the other lines stay on stdout.
+fn reserve_stdout_for_exports(format: &str) {
+    if format != "env" {
+        return;
+    }
+    #[cfg(unix)]
+    {
+        use std::os::fd::FromRawFd;
 
-            let pubkey = bs58::encode(keypair.pubkey().to_bytes()).into_string();
+        let _ = io::stdout().flush();
+        // SAFETY: dup only reads the descriptor table and returns a descriptor nobody else owns
+        let saved = unsafe { libc::dup(libc::STDOUT_FILENO) };
+        if saved < 0 {
+            return;
+        }
+        // SAFETY: both descriptors stay open for the life of the process
+        if unsafe { libc::dup2(libc::STDERR_FILENO, libc::STDOUT_FILENO) } < 0 {
+            // SAFETY: `saved` came from dup above and hasn't been handed to anything
+            unsafe { libc::close(saved) };
+            return;
+        }
+        // SAFETY: as above; the File takes sole ownership of `saved`
+        let _ = EXPORT_STDOUT.set(unsafe { fs::File::from_raw_fd(saved) });
+    }
+}
 
-            local_iterations += 1;
+/// Write `exports` to the real stdout, wherever the rest of the output has gone
+fn print_exports(exports: &str) {
+    match EXPORT_STDOUT.get() {
+        Some(mut stdout) => {
+            let _ = stdout.write_all(exports.as_bytes());
+        }
+        None => print!("{exports}"),
+    }
+}
 
-            // Update global counter every 1000 iterations to reduce contention
-            if local_iterations.is_multiple_of(1000) {
-                local_counter.fetch_add(1000, Ordering::Relaxed);
-            }
+/// Whether the real stdout is a terminal, even after [`reserve_stdout_for_exports`]
+fn stdout_is_terminal() -> bool {
+    EXPORT_STDOUT
+        .get()
+        .map_or_else(|| io::stdout().is_terminal(), IsTerminal::is_terminal)
+}
 
-            if pubkey.starts_with(&args.prefix) {
-                local_found.store(true, Ordering::Relaxed);
-                local_counter.fetch_add(local_iterations % 1000, Ordering::Relaxed);
-
-                let secret_key = bs58::encode(keypair.to_bytes()).into_string();
-                let keypair_bytes = keypair.to_bytes().to_vec();
-                let final_iterations = local_counter.load(Ordering::Relaxed);
-                let elapsed_time = start_time.elapsed().as_secs_f64();
-
-                *local_result.lock() = Some((
-                    mnemonic_opt,
-                    pubkey,
-                    secret_key,
-                    keypair_bytes,
-                    final_iterations,
-                    elapsed_time,
-                ));
-                break;
-            }
+/// Reject --export-secret outside the env format, and warn about what it does
+fn check_export_secret(format: &str, export_secret: bool) {
+    if !export_secret {
+        return;
+    }
+    if format != "env" {
+        eprintln!("❌ Error: --export-secret only applies to --format env");
+        std::process::exit(1);
+    }
+    eprintln!("⚠️  Warning: SOLANA_SECRET puts the secret key in the environment, where child");
+    eprintln!(
+        "   processes, shell history and /proc/<pid>/environ can expose it. Dev wallets only."
+    );
+}
+
+/// Name the experimental seed salt and warn that standard wallets can't recover its mnemonics
+fn warn_experimental_salt(salt: &str) {
+    println!("Seed salt: {salt} (EXPERIMENTAL, non-standard)");
+    println!("⚠️  Warning: These mnemonics won't import into Phantom, Solflare, solana-keygen or");
+    println!("   any standard wallet. Keep the salt with them; without it they recover nothing.");
+}
+
+/// Warn loudly if the wallet's key material is too regular to be safe, e.g. all zeros
+fn warn_if_low_entropy(wallet: &VanityWallet) {
+    if looks_low_entropy(&wallet.key_entropy()) {
+        eprintln!("⚠️  Warning: This key was made from low-entropy input (e.g. all zeros).");
+        eprintln!("   Anyone can guess it. Never send funds to this address.");
+    }
+}
+
+/// The encrypted secrets bundle that goes with the public key list at `list_path`
+fn bundle_path(list_path: &Path) -> PathBuf {
+    let mut bundle_path = list_path.as_os_str().to_owned();
+    bundle_path.push(".secrets.json");
+    PathBuf::from(bundle_path)
+}
+
+/// Write the public keys to `list_path` and the full records, encrypted, next to it
+///
+/// Called after every find, so a crash part way through a batch keeps the wallets found so far.
+/// If a file can't be written its contents are printed instead, as for result files; the bundle
+/// is encrypted, so it is safe on stdout. Returns whether both files were saved.
+fn write_pubkey_list(records: &[WalletRecord], list_path: &Path, passphrase: &str) -> bool {
+    let bundle_path = bundle_path(list_path);
+    let json = match encrypt_wallet_bundle(records, passphrase, KdfParams::default()) {
+        Ok(blob) => serde_json::to_string_pretty(&blob).expect("encrypted blob always serializes"),
+        Err(err) => {
+            eprintln!("❌ Error: Unable to encrypt the secrets bundle: {err}");
+            return false;
         }
-    });
+    };
+
+    // The secrets go first so a public list never exists without them
+    if save_or_dump(&bundle_path, &json, &mut io::stdout()).is_err() {
+        eprintln!("❌ Error: Unable to save {}", bundle_path.display());
+        return false;
+    }
+    if let Err(err) = write_checksum_file(&bundle_path, json.as_bytes()) {
+        eprintln!("⚠️  Warning: Unable to write checksum file: {err}");
+    }
+    if save_or_dump(list_path, &pubkey_list(records), &mut io::stdout()).is_err() {
+        eprintln!("❌ Error: Unable to save {}", list_path.display());
+        return false;
+    }
+    true
+}
 
-    // Wait for statistics thread to finish
-    stats_thread.join().unwrap();
+/// Say where `write_pubkey_list` put `count` public keys and their secrets
+fn report_pubkey_list(count: usize, list_path: &Path) {
+    let bundle_path = bundle_path(list_path);
+    println!("📋 {count} public keys written to {}", list_path.display());
+    println!("🔒 Secrets encrypted to {}", bundle_path.display());
+    println!(
+        "   Decrypt with: solana-vanity-wallet --decrypt-backup {}",
+        bundle_path.display()
+    );
+}
 
-    // Print final results
-    let result = result_data.lock().take();
+/// The entropy source asked for, exiting up front if the device behind --hardware-entropy
+/// can't be opened rather than quietly using another one
+fn entropy_source(hardware_entropy: bool) -> EntropySource {
+    if !hardware_entropy {
+        return EntropySource::Os;
+    }
+    if let Err(err) = HardwareRng::open() {
+        eprintln!("❌ Error: --hardware-entropy can't open {HARDWARE_ENTROPY_PATH}: {err}");
+        eprintln!(
+            "   Run without --hardware-entropy to use the OS generator. Nothing was generated."
+        );
+        std::process::exit(1);
+    }
+    EntropySource::Hardware
+}
 
-    if let Some((mnemonic, pubkey, secret_key, keypair_bytes, final_iterations, elapsed_time)) =
-        result
-    {
-        println!("\n");
-        println!("🎉 SUCCESS! Vanity wallet generated!");
-        println!("====================================");
-        println!("Total iterations: {}", format_number(final_iterations));
-        println!("Time elapsed: {}", format_duration(elapsed_time));
-        println!(
-            "Average rate: {}/s",
-            format_number((final_iterations as f64 / elapsed_time) as u64)
+/// Exit before any key is made if `entropy` fails the health check
+fn check_rng(entropy: EntropySource) {
+    if let Err(err) = rng_health_check(&mut *entropy.rng()) {
+        eprintln!("❌ Error: Random number generator failed its self-check: {err}");
+        eprintln!("   Keys made from it could be predictable. Nothing was generated.");
+        std::process::exit(1);
+    }
+}
+
+/// The stem shared by a wallet's files: `template` expanded, or the first key characters,
+/// numbered if another wallet in `output_dir` already uses it
+fn file_stem(
+    template: Option<&NameTemplate>,
+    output_dir: &Path,
+    public_key: &str,
+    prefix: Option<&str>,
+) -> String {
+    let stem = template
+        .cloned()
+        .unwrap_or_default()
+        .render(&NameFields::new(public_key, prefix));
+    unused_stem(output_dir, &stem)
+}
+
+/// Write the unencrypted paper wallet for `wallet` next to its result file
+fn write_paper_wallet(
+    wallet: &VanityWallet,
+    path: &DerivationPath,
+    output_dir: &Path,
+    file_stem: &str,
+    secrets: SecretOutput,
+) {
+    let Some(mnemonic) = &wallet.mnemonic else {
+        return;
+    };
+    let sheet = paper_wallet_sheet(&wallet.public_key, mnemonic, path);
+    let sheet_path = output_dir.join(format!("{file_stem}_paper_wallet.txt"));
+    let mut dump = Vec::new();
+    if save_or_dump(&sheet_path, &sheet, &mut dump).is_err() {
+        // The result file already holds the mnemonic, so this is no reason to stop
+        let rescued = secrets.rescue(&dump);
+        eprintln!(
+            "❌ Error: Unable to save the paper wallet {}; {rescued}",
+            sheet_path.display()
         );
+        return;
+    }
+    println!("📄 Paper wallet written to {}", sheet_path.display());
+    println!(
+        "   Recover with: solana-keygen recover '{}'",
+        keygen_prompt_uri(path)
+    );
+}
+
+fn run_generate(mut args: GenerateArgs) {
+    // --coin-type is shorthand for a derivation path; clap keeps the two exclusive
+    if let Some(path) = args.coin_type_path.take() {
+        args.derivation_path = Some(path);
+    }
+    if let Some(target) = args.target_wallet {
+        args.derivation_path = Some(target.derivation_path());
+    }
+    check_export_secret(&args.format, args.export_secret);
+    reserve_stdout_for_exports(&args.format);
+    let entropy = entropy_source(args.hardware_entropy);
+    if args.check_rng {
+        check_rng(entropy);
+    }
+    let derivation_path = args.derivation_path.unwrap_or_default();
+    let (mnemonic, keypair) =
+        generate_keypair_from(&mut *entropy.rng(), args.with_mnemonic, &derivation_path)
+            .unwrap_or_else(|err| {
+                eprintln!("❌ Error: {err}");
+                std::process::exit(1);
+            });
+    let wallet = VanityWallet {
+        mnemonic,
+        public_key: address_of(&keypair),
+        keypair,
+    };
+
+    let output_dir = Path::new(OUTPUT_DIR);
+    prepare_output_dir(output_dir);
+    let stem = file_stem(
+        args.name_template.as_ref(),
+        output_dir,
+        &wallet.public_key,
+        None,
+    );
+    let secrets = SecretOutput::new(args.allow_secret_stdout, args.export_secret);
+    let saved = emit_wallet(
+        &WalletRecord::from_wallet(&wallet)
+            .with_derivation_path(&derivation_path)
+            .with_target_wallet(args.target_wallet),
+        &args.format,
+        secrets,
+        output_dir,
+        None,
+        None,
+        &stem,
+    );
+    if args.self_verify {
+        self_verify(&saved, &wallet);
+    }
+    warn_if_low_entropy(&wallet);
+    note_no_recovery_phrase(&wallet);
+    print_import_hint(args.target_wallet);
+    if args.paper_wallet {
+        write_paper_wallet(&wallet, &derivation_path, output_dir, &stem, secrets);
+    }
+    if args.show_entropy && secrets.shown {
+        print_entropy(wallet.mnemonic.as_deref());
+    }
+    if args.numbered_mnemonic && secrets.shown {
+        print_numbered_mnemonic(wallet.mnemonic.as_deref());
+    }
+}
+
+/// Spend `seconds` more looking for an address closer to `target`, keeping the best one
+fn extend_match(
+    wallet: VanityWallet,
+    config: &SearchConfig,
+    prefix: &str,
+    target: &str,
+    seconds: u64,
+    anywhere_anchor: bool,
+) -> VanityWallet {
+    println!("⏳ Extending search for {seconds}s toward {target}...");
+    let pattern = if anywhere_anchor {
+        VanityPattern::EitherEnd(target.to_string())
+    } else {
+        VanityPattern::Prefix(target.to_string())
+    };
+    let extend_config = SearchConfig {
+        pattern: Arc::new(pattern),
+        ..config.clone()
+    };
+    let (best, stats) = extend_search(
+        wallet,
+        &*config.pattern,
+        &extend_config,
+        Duration::from_secs(seconds),
+        &SearchProgress::default(),
+    );
+
+    let extra = best.matched_chars.saturating_sub(prefix.chars().count());
+    println!(
+        "Longest match: {} ({} extra character{} after {} more iterations)",
+        best.wallet.public_key,
+        extra,
+        if extra == 1 { "" } else { "s" },
+        format_number(stats.iterations)
+    );
+    println!();
+    best.wallet
+}
+
+/// Settings an --interactive session starts with, changed by its `config` command
+struct ReplSession {
+    threads: usize,
+    with_mnemonic: bool,
+    derivation_path: DerivationPath,
+    /// Candidates per second at these settings, measured by the first `estimate`
+    rate: Option<f64>,
+}
+
+impl ReplSession {
+    fn apply(&mut self, setting: Setting) {
+        match setting {
+            Setting::Threads(threads) => self.threads = threads,
+            Setting::Mnemonic(with_mnemonic) => self.with_mnemonic = with_mnemonic,
+            Setting::Path(path) => self.derivation_path = path,
+        }
+        // Threads and mode both change the rate; measure again when it's next needed
+        self.rate = None;
+        self.print();
+    }
+
+    fn print(&self) {
+        println!("threads   {}", self.threads);
         println!(
-            "Luck factor: {:.2}x {} than expected",
-            expected_iterations as f64 / final_iterations as f64,
-            if final_iterations < expected_iterations {
-                "better"
-            } else {
-                "worse"
-            }
+            "mnemonic  {}",
+            if self.with_mnemonic { "on" } else { "off" }
         );
-        println!();
+        println!("path      {}", self.derivation_path);
+    }
 
-        // Prepare output data
-        let output_json = json!({
-            "mnemonic": mnemonic,
-            "public_key": pubkey,
-            "secret_key": secret_key,
-            "keypair_json": keypair_bytes,
-            "statistics": {
-                "iterations": final_iterations,
-                "elapsed_seconds": elapsed_time,
-                "iterations_per_second": final_iterations as f64 / elapsed_time,
-                "expected_iterations": expected_iterations,
-                "luck_factor": expected_iterations as f64 / final_iterations as f64
-            }
-        });
+    fn search_config(&self, prefix: &str) -> SearchConfig {
+        SearchConfig {
+            derivation_path: self.derivation_path.clone(),
+            ..SearchConfig::new(
+                VanityPattern::Prefix(prefix.to_string()),
+                self.with_mnemonic,
+                self.threads,
+            )
+        }
+    }
+}
 
-        // Determine log file path
-        let output_dir = Path::new("output");
-        if !output_dir.exists() {
-            fs::create_dir(output_dir).expect("Unable to create output directory");
+/// Run commands typed on stdin until `quit` or the end of input; the syntax is in `repl`
+fn run_interactive(args: &SearchArgs) {
+    let mut session = ReplSession {
+        threads: num_cpus::get(),
+        with_mnemonic: args.with_mnemonic,
+        derivation_path: args.derivation_path.clone().unwrap_or_default(),
+        rate: None,
+    };
+    println!("🧪 Interactive mode: type help for the commands, quit to leave");
+    let mut line = String::new();
+    loop {
+        print!("vanity> ");
+        let _ = io::stdout().flush();
+        line.clear();
+        match io::stdin().read_line(&mut line) {
+            Ok(0) => {
+                println!();
+                break;
+            }
+            Ok(_) => {}
+            Err(err) => {
+                eprintln!("❌ Error: Unable to read input: {err}");
+                std::process::exit(1);
+            }
         }
-        let wallet_prefix = &pubkey[..10.min(pubkey.len())];
+        let command = match parse_command(&line) {
+            Ok(Some(command)) => command,
+            Ok(None) => continue,
+            Err(err) => {
+                eprintln!("❌ {err}");
+                continue;
+            }
+        };
+        match command {
+            ReplCommand::Search(prefix) => repl_search(&session, &prefix, args),
+            ReplCommand::Estimate(prefix) => repl_estimate(&mut session, &prefix),
+            ReplCommand::Restore(mnemonic) => repl_restore(&session, &mnemonic),
+            ReplCommand::Config(setting) => session.apply(setting),
+            ReplCommand::ShowConfig => session.print(),
+            ReplCommand::Help => println!("{REPL_HELP}"),
+            ReplCommand::Quit => break,
+        }
+    }
+}
 
-        if args.format == "json" {
-            // JSON format: print and save as JSON
-            let output_string = format_json_compact_array(&output_json);
-            println!("{output_string}");
+/// `search` at the prompt: find one wallet, print it and save it to the output directory
+fn repl_search(session: &ReplSession, prefix: &str, args: &SearchArgs) {
+    let config = session.search_config(prefix);
+    let progress = Arc::new(SearchProgress::default());
+    let stats_thread = spawn_stats_thread(
+        Arc::clone(&progress),
+        config.pattern.expected_iterations(),
+        args.rate_window,
+        args.rate_unit.clone(),
+        Palette::new(args.color),
+        None,
+        None,
+    );
+    let (result, reporter) =
+        search_with_reporter(&config, &progress, stats_thread, REPORTER_JOIN_TIMEOUT);
+    if let Err(err) = reporter {
+        eprintln!("\n⚠️  Progress display {err}; continuing without it");
+    }
+    println!("\n");
+    let Some(wallet) = result.wallet else {
+        eprintln!("❌ No match found");
+        return;
+    };
 
-            let file_name = format!("{wallet_prefix}_output.json");
-            let file_path = output_dir.join(file_name);
-            let mut file = fs::File::create(file_path).expect("Unable to create log file");
-            file.write_all(output_string.as_bytes())
-                .expect("Unable to write data");
-        } else {
-            // Text format: print formatted text, save as text file
-            let mnemonic_display = mnemonic
-                .as_deref()
-                .unwrap_or("[Not generated - use --with-mnemonic flag]");
-            let console_output = format!(
-                "Mnemonic: {}\nPublic Key: {}\nSecret Key: {}\nKeypair JSON: [{}]",
-                mnemonic_display,
-                pubkey,
-                secret_key,
-                keypair_bytes
-                    .iter()
-                    .map(|b| b.to_string())
-                    .collect::<Vec<_>>()
-                    .join(", ")
-            );
-            println!("{console_output}");
-
-            let file_output = format!(
-                "Solana Vanity Wallet Generated\n\
-                ==============================\n\
-                Mnemonic: {}\n\
-                Public Key: {}\n\
-                Secret Key: {}\n\
-                Keypair JSON: [{}]\n\
-                \n\
-                Statistics:\n\
-                -----------\n\
-                Total iterations: {}\n\
-                Time elapsed: {}\n\
-                Average rate: {}/s\n\
-                Expected iterations: {}\n\
-                Luck factor: {:.2}x {} than expected\n",
-                mnemonic_display,
-                pubkey,
-                secret_key,
-                keypair_bytes
-                    .iter()
-                    .map(|b| b.to_string())
-                    .collect::<Vec<_>>()
-                    .join(", "),
-                format_number(final_iterations),
-                format_duration(elapsed_time),
-                format_number((final_iterations as f64 / elapsed_time) as u64),
-                format_number(expected_iterations),
-                expected_iterations as f64 / final_iterations as f64,
-                if final_iterations < expected_iterations {
-                    "better"
-                } else {
-                    "worse"
-                }
-            );
+    let output_dir = Path::new(OUTPUT_DIR);
+    prepare_output_dir(output_dir);
+    let record =
+        WalletRecord::new(&wallet, &result.stats).with_derivation_path(&session.derivation_path);
+    let saved = emit_wallet(
+        &record,
+        "text",
+        SecretOutput::new(args.allow_secret_stdout, false),
+        output_dir,
+        None,
+        None,
+        &file_stem(
+            args.name_template.as_ref(),
+            output_dir,
+            &wallet.public_key,
+            Some(prefix),
+        ),
+    );
+    println!("💾 Saved to {}", saved.display());
+}
 
-            let file_name = format!("{wallet_prefix}_output.txt");
-            let file_path = output_dir.join(file_name);
-            let mut file = fs::File::create(file_path).expect("Unable to create log file");
-            file.write_all(file_output.as_bytes())
-                .expect("Unable to write data");
+/// `estimate` at the prompt: the odds for a prefix and the time they take at the session's rate
+fn repl_estimate(session: &mut ReplSession, prefix: &str) {
+    let chance = prefix_probability(prefix);
+    println!(
+        "Chance per candidate: 1 in {}",
+        format_number((1.0 / chance) as u128)
+    );
+    let rate = match session.rate {
+        Some(rate) => rate,
+        None => {
+            let rate = measure_rate(&session.search_config(prefix), Duration::from_secs(1));
+            println!("Measured rate: {}/s", format_number(rate as u128));
+            *session.rate.insert(rate)
         }
+    };
+    for probability in [0.5, 0.9] {
+        if let Some(iterations) = iterations_for_probability(chance, probability) {
+            println!(
+                "{:.0}% chance: {} iterations, about {}",
+                probability * 100.0,
+                format_number(iterations),
+                format_duration(iterations as f64 / rate.max(f64::MIN_POSITIVE))
+            );
+        }
+    }
+}
+
+/// `restore` at the prompt: the address a recovery phrase derives at the session's path
+fn repl_restore(session: &ReplSession, mnemonic: &str) {
+    match derive_with_passphrase(mnemonic, "", &session.derivation_path) {
+        Ok(seed) => match keypair_from_seed(&seed) {
+            Ok(keypair) => println!(
+                "Address at {}: {}",
+                session.derivation_path,
+                address_of(&keypair)
+            ),
+            Err(err) => eprintln!("❌ Error: {err}"),
+        },
+        Err(err) => eprintln!("❌ Invalid mnemonic: {err}"),
+    }
+}
+
+fn run_difficulty(args: DifficultyArgs) {
+    if let Err(err) = validate_prefix(&args.prefix) {
+        eprintln!("❌ Error: Invalid prefix '{}': {err}", args.prefix);
+        std::process::exit(1);
+    }
+
+    let chance = prefix_probability(&args.prefix);
+    let Some(iterations) = iterations_for_probability(chance, args.probability) else {
+        eprintln!(
+            "❌ Error: A {} chance can't be reached for '{}'; use a probability between 0 and 1",
+            args.probability, args.prefix
+        );
+        std::process::exit(1);
+    };
+
+    println!("Prefix: {}", args.prefix);
+    println!(
+        "Chance per candidate: 1 in {}",
+        format_number((1.0 / chance) as u128)
+    );
+    println!(
+        "Iterations for a {} chance: {}",
+        format_percent(args.probability),
+        format_number(iterations)
+    );
+    if let Some(rate) = args.rate {
+        println!(
+            "Estimated time at {}/s: {}",
+            format_number(rate as u128),
+            format_duration(iterations as f64 / rate)
+        );
+    }
+
+    if let Some(watts) = args.estimate_cost {
+        let rate = args.rate.unwrap_or_else(|| {
+            let config = SearchConfig::new(
+                VanityPattern::Prefix(args.prefix.clone()),
+                false,
+                num_cpus::get(),
+            );
+            let rate = measure_rate(&config, Duration::from_secs(1));
+            println!("Measured rate: {}/s", format_number(rate as u128));
+            rate
+        });
+        let (time, kwh) = cost_estimate(&args.prefix, rate, watts);
+        if time == Duration::MAX {
+            println!("Estimated cost: never finishes at {rate}/s");
+        } else {
+            println!(
+                "Estimated cost at {watts} W: {} and {kwh:.3} kWh on average",
+                format_duration(time.as_secs_f64())
+            );
+        }
+    }
+}
+
+fn run_bench(args: BenchArgs) {
+    let cpu_count = num_cpus::get();
+    let duration = Duration::from_secs(args.seconds.max(1));
+    let mut results = Vec::new();
+    for with_mnemonic in [false, true] {
+        for (matcher, pattern) in bench_patterns() {
+            let mode = if with_mnemonic { "mnemonic" } else { "fast" };
+            // Progress goes to stderr so JSON output stays clean
+            eprintln!(
+                "⏱️  {mode} {matcher} for {}...",
+                format_duration(duration.as_secs_f64())
+            );
+            let config = SearchConfig::new(pattern, with_mnemonic, cpu_count);
+            let (peak, stats) = bench_rates(&config, duration, BENCH_WINDOW);
+            results.push(BenchResult::new(with_mnemonic, matcher, peak, &stats));
+        }
+    }
+
+    if args.format == "json" {
+        println!("{}", serde_json::to_string_pretty(&results).unwrap());
+        return;
+    }
+    println!();
+    println!("Threads: {cpu_count}");
+    println!(
+        "{:<10} {:<12} {:>12} {:>14}",
+        "Mode", "Matcher", "Peak/s", "Sustained/s"
+    );
+    for result in &results {
+        println!(
+            "{:<10} {:<12} {:>12} {:>14}",
+            result.mode,
+            result.matcher,
+            format_number(result.peak_rate as u128),
+            format_number(result.sustained_rate as u128)
+        );
+    }
+}
+
+/// Print the keys in a key file whose addresses match, with their line numbers
+fn run_filter_keys(args: FilterKeysArgs) {
+    let registry = MatcherRegistry::default();
+    let matcher = registry
+        .build(&args.matcher, &args.pattern)
+        .unwrap_or_else(|err| {
+            eprintln!("❌ Error: {err}");
+            eprintln!("Available matchers: {}", registry.names().join(", "));
+            std::process::exit(1);
+        });
+    let text = fs::read_to_string(&args.keys).unwrap_or_else(|err| {
+        eprintln!("❌ Error: Unable to read {}: {err}", args.keys.display());
+        std::process::exit(1);
+    });
+
+    let scan = filter_secret_keys(&text, matcher.as_ref());
+    // Only line numbers: a line that failed to parse may still be most of a secret key
+    for (line, err) in &scan.unreadable {
+        eprintln!("⚠️  Line {line} skipped: {err}");
+    }
+    if args.format == "json" {
+        println!("{}", serde_json::to_string_pretty(&scan.matches).unwrap());
+        return;
+    }
+    for found in &scan.matches {
+        println!("{:>6}  {}", found.line, found.public_key);
+    }
+    println!("{} of {} keys match", scan.matches.len(), scan.keys);
+}
+
+/// Convert one secret key between Base58 and a JSON byte array, whichever it isn't already
+fn run_convert(args: ConvertArgs) {
+    let text = match &args.input {
+        Some(path) => fs::read_to_string(path).unwrap_or_else(|err| {
+            eprintln!("❌ Error: Unable to read {}: {err}", path.display());
+            std::process::exit(1);
+        }),
+        None => read_secret_key(),
+    };
+    let text = text.trim();
+    let converted = if text.starts_with('[') {
+        serde_json::from_str::<Vec<u8>>(text)
+            .map_err(|_| SecretKeyError::Unrecognized)
+            .and_then(|bytes| secret_json_array_to_base58(&bytes))
+    } else {
+        // Compact, as solana-keygen writes keypair files
+        secret_base58_to_json_array(text).map(|bytes| serde_json::to_string(&bytes).unwrap())
+    };
+    let converted = converted.unwrap_or_else(|err| {
+        eprintln!("❌ Error: Unable to convert the secret key: {err}");
+        std::process::exit(1);
+    });
+    // Both forms checked out, so this is the wallet's address either way
+    let keypair = parse_secret_key(text).expect("a key that converted parses");
+    eprintln!("Public Key: {}", address_of(&keypair));
+
+    match &args.outfile {
+        Some(path) => {
+            if let Err(err) = write_new(path, converted.as_bytes(), args.force) {
+                eprintln!("❌ Error: Unable to save {}: {err}", path.display());
+                std::process::exit(1);
+            }
+            eprintln!("💾 Saved to {}", path.display());
+        }
+        None if secrets_on_stdout(io::stdout().is_terminal(), args.allow_secret_stdout) => {
+            println!("{converted}");
+        }
+        None => {
+            eprintln!(
+                "🔒 Not printing the secret key because stdout isn't a terminal; pass --outfile \
+                 to save it (--allow-secret-stdout prints it)"
+            );
+            std::process::exit(1);
+        }
+    }
+}
+
+/// Read a secret key from stdin, prompting on stderr without echo when it's a terminal
+fn read_secret_key() -> String {
+    let mut text = String::new();
+    let read = if io::stdin().is_terminal() {
+        eprint!("Secret key (hidden): ");
+        let _ = io::stderr().flush();
+        read_line_hidden().map(|line| text = line)
+    } else {
+        // A piped keypair file may spread its array over several lines
+        io::Read::read_to_string(&mut io::stdin(), &mut text).map(drop)
+    };
+    if let Err(err) = read {
+        eprintln!("❌ Error: Unable to read the secret key: {err}");
+        std::process::exit(1);
+    }
+    text
+}
+
+/// Print the BIP39 entropy behind `mnemonic`; only called when --show-entropy asks for it
+fn print_entropy(mnemonic: Option<&str>) {
+    if let Some(mnemonic) = mnemonic {
+        match mnemonic_entropy_hex(mnemonic) {
+            Ok(hex) => println!("Entropy (hex): {hex}"),
+            Err(err) => eprintln!("❌ Error: Unable to recover entropy: {err}"),
+        }
+    }
+}
+
+/// Print `mnemonic` one numbered word per line; only called when --numbered-mnemonic asks for it
+fn print_numbered_mnemonic(mnemonic: Option<&str>) {
+    if let Some(mnemonic) = mnemonic {
+        println!("Mnemonic, word by word:");
+        print!("{}", numbered_mnemonic(mnemonic));
+    }
+}
+
+/// Read a non-empty prefix from the named environment variable or exit
+fn read_prefix_env(var: &str) -> String {
+    match std::env::var(var) {
+        Ok(prefix) if !prefix.is_empty() => prefix,
+        _ => {
+            eprintln!("❌ Error: Set the {var} environment variable to the prefix");
+            std::process::exit(1);
+        }
+    }
+}
+
+/// Print the address a mnemonic read from stdin gives under each derivation scheme
+fn compare_wallets() {
+    let mnemonic = read_mnemonic();
+    let addresses = scheme_addresses(&mnemonic).unwrap_or_else(|err| {
+        eprintln!("❌ Error: Invalid mnemonic: {err}");
+        std::process::exit(1);
+    });
+
+    let width = addresses.iter().map(|a| a.scheme.len()).max().unwrap_or(0);
+    let path_width = addresses
+        .iter()
+        .map(|a| a.path.map_or(1, str::len))
+        .max()
+        .unwrap_or(0);
+    println!("{:width$}  {:path_width$}  Address", "Scheme", "Path");
+    for entry in &addresses {
+        println!(
+            "{:width$}  {:path_width$}  {}",
+            entry.scheme,
+            entry.path.unwrap_or("-"),
+            entry.address
+        );
+    }
+    println!();
+    println!("Find the address your wallet shows to see which scheme it uses.");
+}
+
+/// Report which derivation scheme turns a mnemonic read from stdin into `args.address`
+fn run_detect(args: DetectArgs) {
+    if let Err(err) = Pubkey::from_str(args.address.trim()) {
+        eprintln!(
+            "❌ Error: '{}' is not a Solana address: {err}",
+            args.address
+        );
+        std::process::exit(1);
+    }
+    let mnemonic = read_mnemonic();
+    let found = detect_scheme(&mnemonic, &args.address).unwrap_or_else(|err| {
+        eprintln!("❌ Error: Invalid mnemonic: {err}");
+        std::process::exit(1);
+    });
+
+    let Some(found) = found else {
+        println!(
+            "No match: none of the {} schemes gives {} from this mnemonic",
+            DERIVATION_SCHEMES.len(),
+            args.address.trim()
+        );
+        println!("A BIP39 passphrase or a later account could explain it; --compare-wallets lists");
+        println!("each scheme's address and --derive-only-account-range lists more accounts.");
+        std::process::exit(1);
+    };
+    println!("✅ {} comes from {}", found.address, found.scheme);
+    match found.path {
+        Some(path) => {
+            println!("   Path: {path}");
+            if path != SOLANA_DERIVATION_PATH {
+                println!("   Pass --with-mnemonic --derivation-path \"{path}\" to generate wallets the same way");
+            }
+        }
+        None => println!("   Path: none, the key is the first 32 bytes of the BIP39 seed"),
+    }
+}
+
+/// Print the addresses of the accounts in `range` for a mnemonic read from stdin
+fn derive_account_range(range: AccountRange, format: &str) {
+    let mnemonic = read_mnemonic();
+    let accounts = account_addresses(&mnemonic, range).unwrap_or_else(|err| {
+        eprintln!("❌ Error: Invalid mnemonic: {err}");
+        std::process::exit(1);
+    });
+
+    if format == "json" {
+        println!("{}", serde_json::to_string_pretty(&accounts).unwrap());
+        return;
+    }
+    let path_width = accounts
+        .iter()
+        .map(|a| a.derivation_path.len())
+        .max()
+        .unwrap_or(0);
+    println!("{:>7}  {:path_width$}  Address", "Account", "Path");
+    for entry in &accounts {
+        println!(
+            "{:>7}  {:path_width$}  {}",
+            entry.account, entry.derivation_path, entry.address
+        );
+    }
+}
+
+/// Note a pause or resume from the keyboard under the progress line
+fn announce_pause(state: PauseState) {
+    match state {
+        PauseState::Paused => println!("\n⏸️  Paused; press r to resume"),
+        PauseState::Running => println!("▶️  Resumed"),
+    }
+}
+
+/// Read a mnemonic from stdin, prompting on stderr when it's a terminal
+fn read_mnemonic() -> String {
+    if io::stdin().is_terminal() {
+        eprint!("Mnemonic: ");
+        let _ = io::stderr().flush();
+    }
+    let mut mnemonic = String::new();
+    if let Err(err) = io::stdin().read_line(&mut mnemonic) {
+        eprintln!("❌ Error: Unable to read the mnemonic: {err}");
+        std::process::exit(1);
+    }
+    mnemonic.trim().to_string()
+}
+
+/// Print every step from a mnemonic read from stdin to its address at `path`
+///
+/// The seed and keys are only shown after a yes at the prompt (or with --yes), since they give
+/// away the wallet just as the mnemonic does. With the mnemonic piped in there's no one to ask,
+/// so they stay hidden unless --yes is given.
+fn explain(path: &DerivationPath, yes: bool) {
+    let mnemonic = read_mnemonic();
+    let steps = explain_derivation(&mnemonic, "", path).unwrap_or_else(|err| {
+        eprintln!("❌ Error: Invalid mnemonic: {err}");
+        std::process::exit(1);
+    });
+    let show_secrets = yes
+        || (io::stdin().is_terminal() && {
+            eprint!("The steps include the seed and private keys in hex. Show them? [y/N] ");
+            let _ = io::stderr().flush();
+            let mut answer = String::new();
+            io::stdin().read_line(&mut answer).is_ok() && is_affirmative(&answer)
+        });
+
+    println!("Deriving the address at {path}");
+    println!();
+    for (number, step) in steps.iter().enumerate() {
+        println!("{}. {}: {}", number + 1, step.label, step.detail);
+        if step.secret && !show_secrets {
+            println!("   [hidden]");
+        } else {
+            println!("   {}", step.output);
+        }
+    }
+    if !show_secrets {
+        println!();
+        println!("Secret values are hidden; answer y at the prompt or pass --yes to show them.");
+    }
+}
+
+/// Time each step of wallet generation in both modes on one thread and print where it goes
+fn profile(iterations: u64, entropy: EntropySource, path: &DerivationPath) {
+    let iterations = iterations.max(1);
+    println!(
+        "Profiling {} wallets per mode on one thread",
+        format_number(iterations.into())
+    );
+    let mut rng = entropy.rng();
+    let fast = profile_keygen(&mut *rng, false, path, iterations);
+    let slow = profile_keygen(&mut *rng, true, path, iterations);
+
+    for (label, run) in [("Fast mode", &fast), ("Mnemonic mode", &slow)] {
+        println!();
+        println!("{label}: {:.1?} per wallet", run.per_wallet());
+        let total = run.total().as_secs_f64().max(f64::MIN_POSITIVE);
+        for (stage, time) in run.stages() {
+            println!(
+                "  {stage:<18} {:>5.1}%  {:>9.1?}",
+                100.0 * time.as_secs_f64() / total,
+                time.div_f64(iterations as f64)
+            );
+        }
+    }
+    println!();
+    println!(
+        "Mnemonic mode costs {:.0}x fast mode per wallet",
+        slow.total().as_secs_f64() / fast.total().as_secs_f64().max(f64::MIN_POSITIVE)
+    );
+}
+
+/// Read the --seen-set file or exit
+fn load_seen_set(path: &Path) -> Arc<SeenSet> {
+    let seen = SeenSet::load(path).unwrap_or_else(|err| {
+        eprintln!("❌ Error: Unable to read {}: {err}", path.display());
+        std::process::exit(1);
+    });
+    if !seen.is_empty() {
+        println!(
+            "♻️  Passing over {} addresses from earlier runs in {}",
+            seen.len(),
+            path.display()
+        );
+    }
+    Arc::new(seen)
+}
+
+/// Add a produced address to the --seen-set file, if there is one
+fn remember_seen(config: &SearchConfig, path: Option<&Path>, public_key: &str) {
+    if let (Some(seen), Some(path)) = (&config.seen, path) {
+        if let Err(err) = seen.record(path, public_key) {
+            eprintln!(
+                "⚠️  Unable to add {public_key} to {}: {err}",
+                path.display()
+            );
+        }
+    }
+}
+
+/// Read a non-empty passphrase from the named environment variable or exit
+fn read_passphrase(var: &str) -> String {
+    match std::env::var(var) {
+        Ok(passphrase) if !passphrase.is_empty() => passphrase,
+        _ => {
+            eprintln!("❌ Error: Set the {var} environment variable to the backup passphrase");
+            std::process::exit(1);
+        }
+    }
+}
+
+/// Print live iteration, rate and ETA figures once a second until `progress` is done
+fn spawn_stats_thread(
+    progress: Arc<SearchProgress>,
+    expected_iterations: Option<u128>,
+    rate_window: usize,
+    rate_unit: RateUnit,
+    palette: Palette,
+    metrics: Option<Arc<SearchMetrics>>,
+    mut rate_drop: Option<RateDropGuard>,
+) -> thread::JoinHandle<()> {
+    let start_time = Instant::now();
+    let mut rate_window = RateWindow::new(rate_window);
+    let mut advisor = expected_iterations
+        .map(|expected_iterations| GiveUpAdvisor::new(expected_iterations, SUSPICIOUS_PROBABILITY));
+
+    thread::spawn(move || {
+        let mut last_time = Instant::now();
+        rate_window.push(0.0, 0);
+        // Rates, ETAs and the rate-drop guard run on the time spent searching, leaving out pauses
+
+        while !progress.done.load(Ordering::Relaxed) {
+            thread::sleep(Duration::from_secs(1));
+            // Nothing moves while paused, and that shouldn't count as the rate dropping
+            if progress.paused.load(Ordering::Relaxed) {
+                continue;
+            }
+
+            let current_count = progress.iterations.load(Ordering::Relaxed);
+            let current_time = Instant::now();
+            let elapsed = current_time.duration_since(last_time).as_secs_f64();
+
+            if elapsed >= 1.0 {
+                let total_elapsed = progress.active_elapsed(start_time).as_secs_f64();
+                let iterations_per_second = rate_window.push(total_elapsed, current_count);
+                let overall_rate = current_count as f64 / total_elapsed;
+                if let Some(metrics) = &metrics {
+                    metrics.update(current_count, iterations_per_second);
+                }
+                if let Some(guard) = &mut rate_drop {
+                    if guard.observe(total_elapsed, iterations_per_second) {
+                        eprintln!(
+                            "\n🌡️  Rate has stayed under {:.0}% of its {}/s peak for {}; \
+                             stopping (the machine may be throttling)",
+                            guard.share * 100.0,
+                            rate_unit.format(guard.peak()),
+                            format_duration(guard.sustain.as_secs_f64())
+                        );
+                        progress.rate_dropped.store(true, Ordering::Relaxed);
+                        progress.done.store(true, Ordering::Relaxed);
+                        break;
+                    }
+                }
+                if let Some(probability) = advisor
+                    .as_mut()
+                    .and_then(|advisor| advisor.observe(current_count.into()))
+                {
+                    eprintln!(
+                        "\n🤔 Still no match, though a search this long finds one {:.1}% of the \
+                         time. Bad luck is possible, but double-check the prefix (Base58 is \
+                         case-sensitive) and any derivation settings; the search carries on.",
+                        probability * 100.0
+                    );
+                }
+
+                // Progress and ETA only make sense when the matcher knows its difficulty
+                let estimate = match expected_iterations {
+                    Some(expected_iterations) => {
+                        let progress = (current_count as f64 / expected_iterations as f64) * 100.0;
+                        let eta_seconds = if overall_rate > 0.0 {
+                            (expected_iterations as f64 - current_count as f64) / overall_rate
+                        } else {
+                            0.0
+                        };
+                        format!(
+                            " | Progress: {:.2}% | ETA: {}",
+                            progress.min(100.0),
+                            format_duration(eta_seconds)
+                        )
+                    }
+                    None => String::new(),
+                };
+
+                print!(
+                    "\r🔍 Iterations: {} | Rate: {}{} | Elapsed: {}",
+                    format_number(current_count.into()),
+                    palette.rate(&format!("{}/s", rate_unit.format(iterations_per_second))),
+                    estimate,
+                    format_duration(total_elapsed)
+                );
+
+                use std::io::{self, Write};
+                io::stdout().flush().unwrap();
+
+                last_time = current_time;
+            }
+        }
+    })
+}
+
+/// How many characters past a plain prefix the wallet happens to match; `None` for other matchers
+fn prefix_bonus(wallet: &VanityWallet, args: &SearchArgs) -> Option<PrefixBonus> {
+    let plain = args.mask.is_none()
+        && args.matcher.is_none()
+        && args.charset_at.is_empty()
+        && args.pda_prefix.is_none()
+        && !args.anywhere_anchor;
+    let extension = args
+        .extend_target
+        .as_deref()
+        .or(args.prefer_rarest.as_deref());
+    PrefixBonus::measure(
+        args.prefix.as_deref().filter(|_| plain)?,
+        &wallet.public_key,
+        extension,
+    )
+}
+
+/// Print the results for a found wallet and write its files, paper backup and manifest entry
+fn save_wallet(
+    wallet: &VanityWallet,
+    stats: &SearchStats,
+    args: &SearchArgs,
+    output_dir: &Path,
+    ata_mint: Option<&Pubkey>,
+    backup_passphrase: Option<&str>,
+    shown_key: &str,
+) {
+    let derivation_path = args.derivation_path.clone().unwrap_or_default();
+    let pattern_match = case_variant_match(wallet, args);
+    let record = WalletRecord::new(wallet, stats)
+        .with_pattern_match(pattern_match.clone())
+        .with_derivation_path(&derivation_path)
+        .with_target_wallet(args.target_wallet)
+        .with_seed_salt(&args.experimental_salt.clone().unwrap_or_default());
+    let luck_comparison = if stats.is_lucky() { "better" } else { "worse" };
+    // Matchers that can't estimate their difficulty have nothing to compare luck against
+    let luck_line = (stats.expected > 0).then(|| {
+        format!(
+            "Luck factor: {:.2}x {} than expected",
+            stats.luck_factor, luck_comparison
+        )
+    });
+    let bonus_line = prefix_bonus(wallet, args)
+        .filter(|found| found.bonus > 0)
+        .map(|found| format!("Prefix bonus: {found}"));
+
+    // Print final results
+    println!("Total iterations: {}", format_number(stats.iterations));
+    println!(
+        "Time elapsed: {}",
+        format_duration(stats.elapsed.as_secs_f64())
+    );
+    println!("Average rate: {}/s", args.rate_unit.format(stats.rate));
+    if args.verbose {
+        let bits = search_bits_per_second(stats.rate);
+        println!(
+            "Search power: {bits:.1} bits/s (a {:.1}-character Base58 prefix a second)",
+            bits_as_base58_chars(bits)
+        );
+    }
+    if let Some(luck_line) = &luck_line {
+        println!("{luck_line}");
+    }
+    if let Some(bonus_line) = &bonus_line {
+        println!("{bonus_line}");
+    }
+    if let Some(mint) = ata_mint {
+        println!(
+            "Associated token account ({mint}): {}",
+            associated_token_address(&wallet.keypair.pubkey(), mint)
+        );
+    }
+    let near_miss_lines = stats
+        .near_misses
+        .map(|near_misses| near_misses.histogram_lines())
+        .unwrap_or_default();
+    if !near_miss_lines.is_empty() {
+        println!("Near misses:");
+        for line in &near_miss_lines {
+            println!("  {line}");
+        }
+    }
+    print_pda(wallet, args);
+    if let Some(found) = &pattern_match {
+        println!("Matched variant: {}", found.matched_pattern);
+    }
+    println!();
+
+    let mut stats_section = format!(
+        "Statistics:\n\
+        -----------\n\
+        Total iterations: {}\n\
+        Time elapsed: {}\n\
+        Average rate: {}/s\n\
+        Expected iterations: {}\n\
+        {}\n",
+        format_number(stats.iterations),
+        format_duration(stats.elapsed.as_secs_f64()),
+        args.rate_unit.format(stats.rate),
+        format_number(stats.expected),
+        luck_line.as_deref().unwrap_or("Luck factor: n/a")
+    );
+    if let Some(bonus_line) = &bonus_line {
+        stats_section.push_str(&format!("{bonus_line}\n"));
+    }
+    if !near_miss_lines.is_empty() {
+        stats_section.push_str("Near misses:\n");
+        for line in &near_miss_lines {
+            stats_section.push_str(&format!("  {line}\n"));
+        }
+    }
+
+    let stem = file_stem(
+        args.name_template.as_ref(),
+        output_dir,
+        &wallet.public_key,
+        args.prefix.as_deref(),
+    );
+    let secrets = SecretOutput::new(args.allow_secret_stdout, args.export_secret);
+    let saved = emit_wallet(
+        &record,
+        &args.format,
+        secrets,
+        output_dir,
+        Some(&stats_section),
+        Some(shown_key),
+        &stem,
+    );
+    if args.self_verify {
+        self_verify(&saved, wallet);
+    }
+    warn_if_low_entropy(wallet);
+    note_no_recovery_phrase(wallet);
+    print_import_hint(args.target_wallet);
+    if args.show_entropy && secrets.shown {
+        print_entropy(wallet.mnemonic.as_deref());
+    }
+    if args.numbered_mnemonic && secrets.shown {
+        print_numbered_mnemonic(wallet.mnemonic.as_deref());
+    }
+
+    if args.paper_wallet {
+        write_paper_wallet(wallet, &derivation_path, output_dir, &stem, secrets);
+    }
+
+    let pubkey = &wallet.public_key;
+
+    if let (Some(passphrase), Some(mnemonic)) = (backup_passphrase, &wallet.mnemonic) {
+        let sheet = encryption::encrypt(mnemonic.as_bytes(), passphrase, KdfParams::default())
+            .map_err(|err| err.to_string())
+            .and_then(|blob| paper_backup_sheet(pubkey, &blob).map_err(|err| err.to_string()));
+        match sheet {
+            Ok(sheet) => {
+                let backup_path = output_dir.join(format!("{stem}_paper_backup.txt"));
+                // The sheet only holds the encrypted mnemonic, so like the bundle it may go to
+                // stdout when it can't be saved
+                if save_or_dump(&backup_path, &sheet, &mut io::stdout()).is_ok() {
+                    println!("📄 Paper backup written to {}", backup_path.display());
+                }
+            }
+            Err(err) => eprintln!("❌ Error: Unable to create paper backup: {err}"),
+        }
+    }
+
+    if let Some(manifest_path) = &args.append_to_single_json {
+        match append_to_manifest(manifest_path, record) {
+            Ok(count) => println!(
+                "📒 Appended to {} ({count} wallets)",
+                manifest_path.display()
+            ),
+            Err(err) => {
+                eprintln!(
+                    "❌ Error: Unable to update {}: {err}",
+                    manifest_path.display()
+                );
+                std::process::exit(1);
+            }
+        }
+    }
+}
+
+fn main() {
+    let cli = Cli::parse();
+
+    match cli.command {
+        Some(Command::Generate(args)) => run_generate(args),
+        Some(Command::Search(args)) => run_search(*args),
+        Some(Command::Difficulty(args)) => run_difficulty(args),
+        Some(Command::Bench(args)) => run_bench(args),
+        Some(Command::FilterKeys(args)) => run_filter_keys(args),
+        Some(Command::Convert(args)) => run_convert(args),
+        Some(Command::Detect(args)) => run_detect(args),
+        None => run_search(cli.search),
+    }
+}
+
+/// Start the `/metrics` endpoint on `addr`
+#[cfg(feature = "metrics")]
+fn start_metrics(addr: &str) -> Arc<SearchMetrics> {
+    let listener = std::net::TcpListener::bind(addr).unwrap_or_else(|err| {
+        eprintln!("❌ Error: Unable to listen on {addr}: {err}");
+        std::process::exit(1);
+    });
+    let metrics = Arc::new(SearchMetrics::default());
+    solana_vanity_wallet::metrics::serve(listener, Arc::clone(&metrics));
+    println!("📈 Metrics: http://{addr}/metrics");
+    metrics
+}
+
+#[cfg(not(feature = "metrics"))]
+fn start_metrics(_addr: &str) -> Arc<SearchMetrics> {
+    eprintln!("❌ Error: --metrics-addr needs a build with the metrics feature:");
+    eprintln!("   cargo install --path . --features metrics");
+    std::process::exit(1);
+}
+
+/// The matcher for --pda-prefix
+#[cfg(feature = "pda")]
+fn pda_matcher(prefix: &str, args: &SearchArgs) -> Box<dyn Matcher> {
+    use solana_vanity_wallet::pda::PdaMatcher;
+
+    let program_id = args.program_id.as_deref().unwrap_or_default();
+    let program_id = Pubkey::from_str(program_id).unwrap_or_else(|_| {
+        eprintln!("❌ Error: Invalid program ID '{program_id}'");
+        std::process::exit(1);
+    });
+    let matcher =
+        PdaMatcher::new(prefix, program_id, args.pda_seed.as_bytes()).unwrap_or_else(|err| {
+            eprintln!("❌ Error: {err}");
+            std::process::exit(1);
+        });
+    Box::new(matcher)
+}
+
+#[cfg(not(feature = "pda"))]
+fn pda_matcher(_prefix: &str, _args: &SearchArgs) -> Box<dyn Matcher> {
+    eprintln!("❌ Error: --pda-prefix needs a build with the pda feature:");
+    eprintln!("   cargo install --path . --features pda");
+    std::process::exit(1);
+}
+
+/// Show the program-derived address a --pda-prefix wallet was mined for
+#[cfg(feature = "pda")]
+fn print_pda(wallet: &VanityWallet, args: &SearchArgs) {
+    let Some(program_id) = args
+        .program_id
+        .as_deref()
+        .and_then(|id| Pubkey::from_str(id).ok())
+    else {
+        return;
+    };
+    let (pda, bump) = solana_vanity_wallet::pda::program_address(
+        &wallet.keypair.pubkey(),
+        args.pda_seed.as_bytes(),
+        &program_id,
+    );
+    println!(
+        "Program-derived address (seed {:?}): {pda} (bump {bump})",
+        args.pda_seed
+    );
+}
+
+#[cfg(not(feature = "pda"))]
+fn print_pda(_wallet: &VanityWallet, _args: &SearchArgs) {}
+
+/// Tell the user how to import the mnemonic into the wallet app they generated it for
+fn print_import_hint(target: Option<TargetWallet>) {
+    if let Some(target) = target {
+        println!("📲 To import into {target}: {}", target.import_hint());
+    }
+}
+
+/// The spelling a --smart-case search matched; exits rather than save a wallet that matches none
+fn case_variant_match(wallet: &VanityWallet, args: &SearchArgs) -> Option<NormalizedMatch> {
+    let (true, Some(prefix)) = (args.smart_case, &args.prefix) else {
+        return None;
+    };
+    let matcher = CaseVariantMatcher::new(prefix).ok()?;
+    let found = NormalizedMatch::for_case_variant(prefix, &matcher, &wallet.public_key);
+    if found.is_none() {
+        // Saving it would present a wallet that doesn't have the prefix as a match
+        eprintln!(
+            "❌ Error: {} doesn't start with any spelling of '{prefix}' ({}); not saving it",
+            wallet.public_key,
+            matcher.variants().join(", ")
+        );
+        std::process::exit(1);
+    }
+    found
+}
+
+#[cfg(feature = "server")]
+fn run_serve(addr: &str, args: &SearchArgs) {
+    use solana_vanity_wallet::server::{self, ServerConfig};
+
+    if args.max_concurrent == 0 {
+        eprintln!("❌ Error: --max-concurrent must be at least 1");
+        std::process::exit(1);
+    }
+    let defaults = ServerConfig::default();
+    let config = ServerConfig {
+        max_concurrent: args.max_concurrent,
+        max_seconds: args.max_seconds.unwrap_or(defaults.max_seconds),
+        ..defaults
+    };
+    let listener = std::net::TcpListener::bind(addr).unwrap_or_else(|err| {
+        eprintln!("❌ Error: Unable to listen on {addr}: {err}");
+        std::process::exit(1);
+    });
+
+    println!("🌐 Serving on http://{addr}");
+    println!("   POST /vanity    {{\"prefix\": \"Sun\", \"with_mnemonic\": false}}");
+    println!("   GET  /estimate?prefix=Sun");
+    println!(
+        "   Up to {} searches at once, {}s each, prefixes of at most {} characters",
+        config.max_concurrent, config.max_seconds, config.max_prefix_len
+    );
+    if let Err(err) = server::run(listener, config) {
+        eprintln!("❌ Error: Server stopped: {err}");
+        std::process::exit(1);
+    }
+}
+
+#[cfg(not(feature = "server"))]
+fn run_serve(_addr: &str, _args: &SearchArgs) {
+    eprintln!("❌ Error: --serve needs a build with the server feature:");
+    eprintln!("   cargo install --path . --features server");
+    std::process::exit(1);
+}
+
+fn run_search(mut args: SearchArgs) {
+    // --coin-type is shorthand for a derivation path; clap keeps the two exclusive
+    if let Some(path) = args.coin_type_path.take() {
+        args.derivation_path = Some(path);
+    }
+    if let Some(target) = args.target_wallet {
+        args.derivation_path = Some(target.derivation_path());
+    }
+    check_export_secret(&args.format, args.export_secret);
+    reserve_stdout_for_exports(&args.format);
+    if let Some(addr) = &args.serve {
+        run_serve(addr, &args);
+        return;
+    }
+    if args.interactive {
+        run_interactive(&args);
+        return;
+    }
+
+    if let Some(path) = &args.verify_file {
+        match verify_checksum_file(path) {
+            Ok(true) => println!("✅ {}: checksum OK", path.display()),
+            Ok(false) => {
+                eprintln!("❌ {}: checksum MISMATCH", path.display());
+                std::process::exit(1);
+            }
+            Err(err) => {
+                eprintln!("❌ Error: Unable to verify {}: {err}", path.display());
+                std::process::exit(1);
+            }
+        }
+        return;
+    }
+
+    if let Some(path) = &args.explain {
+        explain(path, args.yes);
+        return;
+    }
+
+    if args.compare_wallets {
+        compare_wallets();
+        return;
+    }
+
+    if let Some(range) = args.derive_only_account_range {
+        derive_account_range(range, &args.format);
+        return;
+    }
+
+    if let Some(iterations) = args.profile {
+        let entropy = entropy_source(args.hardware_entropy);
+        profile(
+            iterations,
+            entropy,
+            &args.derivation_path.clone().unwrap_or_default(),
+        );
+        return;
+    }
+
+    if let Some(path) = &args.decrypt_backup {
+        let passphrase = read_passphrase(&args.passphrase_env);
+        let blob = fs::read_to_string(path)
+            .map_err(|err| err.to_string())
+            .and_then(|text| parse_encrypted_blob(&text).map_err(|err| err.to_string()))
+            .unwrap_or_else(|err| {
+                eprintln!("❌ Error: Unable to read backup {}: {err}", path.display());
+                std::process::exit(1);
+            });
+        match encryption::decrypt(&blob, &passphrase) {
+            Ok(plaintext) => match serde_json::from_slice::<Vec<WalletRecord>>(&plaintext) {
+                Ok(records) => println!(
+                    "{}",
+                    serde_json::to_string_pretty(&records).expect("records always serialize")
+                ),
+                Err(_) => println!("Mnemonic: {}", String::from_utf8_lossy(&plaintext)),
+            },
+            Err(err) => {
+                eprintln!("❌ Error: {err}");
+                std::process::exit(1);
+            }
+        }
+        return;
+    }
+
+    if let Some(path) = args.job.clone() {
+        run_jobs(&path, args);
+        return;
+    }
+
+    let prefix = args
+        .prefix
+        .clone()
+        .or_else(|| args.mask.clone())
+        .or_else(|| args.pattern.clone())
+        .or_else(|| args.pda_prefix.clone())
+        .or_else(|| args.prefix_env.as_deref().map(read_prefix_env))
+        .or_else(|| (!args.charset_at.is_empty()).then(|| args.charset_at.join(" ")))
+        .expect("clap requires a prefix unless another mode is given");
+
+    let registry = MatcherRegistry::default();
+    if args.matcher.as_deref() == Some("any-prefix") {
+        let prefixes: Vec<&str> = prefix.split(',').map(str::trim).collect();
+        for overlap in prefix_overlaps(&prefixes) {
+            println!("⚠️  Warning: {overlap}; skipping the extra entry");
+        }
+    }
+    let mut custom_matcher = args.matcher.as_deref().map(|name| {
+        registry.build(name, &prefix).unwrap_or_else(|err| {
+            eprintln!("❌ Error: {err}");
+            eprintln!("Available matchers: {}", registry.names().join(", "));
+            std::process::exit(1);
+        })
+    });
+    let mut custom_target_len = None;
+    let mut charset_bits = None;
+    if !args.charset_at.is_empty() {
+        let matcher = CharsetMatcher::from_specs(&args.charset_at).unwrap_or_else(|err| {
+            eprintln!("❌ Error: {err}");
+            std::process::exit(1);
+        });
+        custom_target_len = Some(matcher.len());
+        charset_bits = Some(matcher.difficulty_bits());
+        custom_matcher = Some(Box::new(matcher));
+    }
+    if args.pda_prefix.is_some() {
+        custom_matcher = Some(pda_matcher(&prefix, &args));
+    }
+    if args.smart_case {
+        let matcher = CaseVariantMatcher::new(&prefix).unwrap_or_else(|err| {
+            eprintln!("❌ Error: {err}");
+            std::process::exit(1);
+        });
+        custom_target_len = Some(prefix.len());
+        custom_matcher = Some(Box::new(matcher));
+    }
+    if args.constant_time_match {
+        let matcher = ConstantTimePrefixMatcher::new(&prefix).unwrap_or_else(|err| {
+            eprintln!("❌ Error: {err}");
+            std::process::exit(1);
+        });
+        custom_target_len = Some(prefix.len());
+        custom_matcher = Some(Box::new(matcher));
+    }
+
+    // A --matcher checks its own argument when it's built
+    if custom_matcher.is_none() && args.mask.is_some() {
+        if let Err(err) = validate_mask(&prefix) {
+            eprintln!("❌ Error: Invalid mask '{prefix}': {err}");
+            eprintln!("Masks need at least one Base58 character; use ? for any character");
+            std::process::exit(1);
+        }
+    } else if custom_matcher.is_none() {
+        // Validate the prefix contains only valid Base58 characters
+        if let Err(
+            err @ (PrefixError::NonAscii(_)
+            | PrefixError::ControlChar(_)
+            | PrefixError::Empty
+            | PrefixError::TooLong(_)),
+        ) = validate_prefix(&prefix)
+        {
+            eprintln!("❌ Error: Invalid prefix: {err}");
+            std::process::exit(1);
+        }
+        if !is_valid_base58_prefix(&prefix) {
+            eprintln!("❌ Error: Invalid prefix '{prefix}'");
+            eprintln!();
+            eprintln!("Valid Base58 characters are:");
+            eprintln!("  Numbers: 1-9 (excludes 0)");
+            eprintln!("  Uppercase: A-Z (excludes O)");
+            eprintln!("  Lowercase: a-z (excludes l)");
+            eprintln!();
+            eprintln!("Examples of valid prefixes: ABC, Sol, 123, MyWallet, IJKL");
+            eprintln!("Examples of invalid prefixes: 0, O, l, _, +, =, /");
+            std::process::exit(1);
+        }
+        // Only the start of the address is skewed; --anywhere-anchor can match at the end
+        if let (Some(threshold), false) = (args.feasibility_threshold, args.anywhere_anchor) {
+            if let Err(err) = FirstCharSample::standard().check_prefix(&prefix, threshold) {
+                eprintln!("❌ Error: Won't search for '{prefix}': {err}");
+                eprintln!("Lower --feasibility-threshold to search anyway");
+                std::process::exit(1);
+            }
+        }
+    }
+
+    let ata_mint = args.show_ata.as_deref().map(|mint| {
+        Pubkey::from_str(mint).unwrap_or_else(|_| {
+            eprintln!("❌ Error: Invalid mint address '{mint}'");
+            std::process::exit(1);
+        })
+    });
+
+    for (flag, target) in [
+        ("--extend-target", &args.extend_target),
+        ("--prefer-rarest", &args.prefer_rarest),
+    ] {
+        if let Some(target) = target {
+            if !target.starts_with(prefix.as_str()) || !is_valid_base58_prefix(target) {
+                eprintln!(
+                    "❌ Error: {flag} '{target}' must be valid Base58 and start with '{prefix}'"
+                );
+                std::process::exit(1);
+            }
+        }
+    }
+
+    if args.best_effort && args.max_iterations.is_none() && args.max_seconds.is_none() {
+        eprintln!("❌ Error: --best-effort needs --max-iterations or --max-seconds");
+        std::process::exit(1);
+    }
+
+    // Read the passphrase before searching so a missing variable fails fast
+    let backup_passphrase = args
+        .paper_backup
+        .then(|| read_passphrase(&args.passphrase_env));
+    let bundle_passphrase = (args.output_pubkey_list.is_some() || args.encrypted_stdout)
+        .then(|| read_passphrase(&args.passphrase_env));
+
+    let cpu_count = num_cpus::get();
+
+    let (pattern, target_len): (Arc<dyn Matcher>, Option<usize>) = match custom_matcher {
+        Some(matcher) => (Arc::from(matcher), custom_target_len),
+        None => {
+            let pattern = if args.mask.is_some() {
+                VanityPattern::Mask(prefix.clone())
+            } else if args.anywhere_anchor {
+                VanityPattern::EitherEnd(prefix.clone())
+            } else {
+                VanityPattern::Prefix(prefix.clone())
+            };
+            let target_len = pattern.target_len();
+            (Arc::new(pattern), Some(target_len))
+        }
+    };
+    let expected_iterations = pattern.expected_iterations();
+
+    let config = SearchConfig {
+        max_iterations: args.max_iterations,
+        max_duration: args.max_seconds.map(Duration::from_secs),
+        track_best: args.best_effort,
+        collect_near_misses: args.collect_near_misses,
+        entropy: entropy_source(args.hardware_entropy),
+        derivation_path: args.derivation_path.clone().unwrap_or_default(),
+        seed_salt: args.experimental_salt.clone().unwrap_or_default(),
+        seen: args.seen_set.as_deref().map(load_seen_set),
+        prefer_rarest: args.prefer_rarest.as_ref().map(|target| PreferRarest {
+            target: Arc::new(if args.anywhere_anchor {
+                VanityPattern::EitherEnd(target.clone())
+            } else {
+                VanityPattern::Prefix(target.clone())
+            }),
+            window: RAREST_GRACE_WINDOW,
+        }),
+        ..SearchConfig::from_matcher(pattern, args.with_mnemonic, cpu_count)
+    }
+    .with_timeout(args.timeout_per_wallet.map(Duration::from_secs));
+
+    if let (true, Some(passphrase)) = (args.encrypted_stdout, &bundle_passphrase) {
+        if args.check_rng {
+            check_rng(config.entropy);
+        }
+        search_to_encrypted_stdout(&config, args.count, passphrase, args.target_wallet);
+        return;
+    }
+
+    let palette = Palette::new(args.color);
+    let salt = args
+        .experimental_salt
+        .as_ref()
+        .and_then(SeedSalt::experimental);
+    let banner = Banner::choose(args.no_banner, args.verbose);
+    if banner.is_shown() {
+        println!("{}", palette.title("🚀 Solana Vanity Wallet Generator"));
+        println!("==================================");
+        if let Some(name) = &args.matcher {
+            println!("Matcher: {name} {prefix}");
+        } else if let Some(program_id) = &args.program_id {
+            println!(
+                "PDA prefix: {prefix} (program {program_id}, seed {:?})",
+                args.pda_seed
+            );
+        } else if !args.charset_at.is_empty() {
+            println!("Charsets: {prefix}");
+        } else if args.mask.is_some() {
+            println!("Mask: {prefix}");
+        } else if let Some(var) = &args.prefix_env {
+            // Keep a prefix passed through the environment out of logs too
+            println!(
+                "Prefix: from ${var} ({} characters)",
+                prefix.chars().count()
+            );
+        } else if args.smart_case {
+            println!(
+                "Prefix: {prefix} (smart case: {})",
+                case_variants(&prefix).join(", ")
+            );
+        } else if args.constant_time_match {
+            println!("Prefix: {prefix} (constant-time match)");
+        } else {
+            println!("Prefix: {prefix}");
+        }
+        if args.anywhere_anchor {
+            println!("Anchor: start or end of address");
+        }
+        println!(
+            "Mode: {}",
+            if args.with_mnemonic {
+                "With mnemonic (slower, wallet-compatible)"
+            } else {
+                "Fast mode (no mnemonic)"
+            }
+        );
+        if let Some(target) = args.target_wallet {
+            println!("Target wallet: {target}");
+        }
+        if let Some(path) = &args.derivation_path {
+            println!("Derivation path: {path}");
+        }
+        if let Some(salt) = &salt {
+            warn_experimental_salt(salt);
+        }
+        println!("Threads: {cpu_count}");
+        if args.hardware_entropy {
+            println!(
+                "Entropy: {HARDWARE_ENTROPY_PATH} for every candidate (expect a much lower rate)"
+            );
+        }
+        match expected_iterations {
+            Some(expected_iterations) => {
+                println!(
+                    "Expected iterations: {}",
+                    format_number(expected_iterations)
+                );
+                println!(
+                    "Estimated difficulty: 1 in {}",
+                    format_number(expected_iterations.saturating_mul(2))
+                );
+            }
+            None => println!("Expected iterations: unknown for this matcher"),
+        }
+        if let (Banner::Detailed, Some(bits)) = (banner, charset_bits) {
+            // The prefix text here is the specs, so the bits come from the allowed sets
+            println!("Difficulty: {bits:.2} bits from the allowed character sets");
+            println!("That's {}", difficulty_comparison(bits));
+        } else if banner == Banner::Detailed && args.mask.is_none() && args.matcher.is_none() {
+            println!("Difficulty by character:");
+            for (c, bits) in difficulty_breakdown(&prefix) {
+                println!("  {c}  {bits:>5.2} bits");
+            }
+            println!(
+                "  total {:.2} bits (uniform characters are {:.2} bits each)",
+                prefix_difficulty_bits(&prefix),
+                58f64.log2()
+            );
+            println!(
+                "That's {}",
+                difficulty_comparison(prefix_difficulty_bits(&prefix))
+            );
+        }
+        println!();
+    } else if let Some(salt) = &salt {
+        // The header is optional; this warning isn't
+        warn_experimental_salt(salt);
+        println!();
+    }
+
+    if args.check_rng {
+        check_rng(config.entropy);
+        println!("✅ RNG self-check passed");
+    }
+
+    // Wallets an earlier run of this batch already saved are numbered first and not searched for
+    let already_saved = if args.resume {
+        let saved = saved_matches(Path::new(OUTPUT_DIR), &*config.pattern).unwrap_or_else(|err| {
+            eprintln!("❌ Error: Unable to read {OUTPUT_DIR} to resume: {err}");
+            std::process::exit(1);
+        });
+        let already_saved = saved.len().min(args.count);
+        println!(
+            "♻️  Resuming: {already_saved} of {} wallets already in {OUTPUT_DIR}",
+            args.count
+        );
+        if already_saved == args.count {
+            println!("✅ Nothing left to find");
+            return;
+        }
+        println!();
+        already_saved
+    } else {
+        0
+    };
+    let remaining = args.count - already_saved;
+
+    // Only measure the rate when someone could be asked; it costs half a second
+    let interactive = io::stdin().is_terminal();
+    if let (false, true, Some(expected_iterations)) = (args.yes, interactive, expected_iterations) {
+        let rate = measure_rate(&config, Duration::from_millis(500));
+        let wall_clock =
+            estimate_wall_clock(expected_iterations.saturating_mul(remaining as u128), rate);
+        let estimated_seconds = wall_clock.map_or(f64::INFINITY, |time| time.as_secs_f64());
+        println!(
+            "Estimated time: ~{} on {cpu_count} threads at {}/s each",
+            format_duration(estimated_seconds),
+            format_number((rate / cpu_count as f64) as u128)
+        );
+        println!();
+        if needs_confirmation(estimated_seconds, LONG_RUN_THRESHOLD, args.yes, interactive) {
+            print!(
+                "This may take ~{}. Continue? [y/N] ",
+                format_duration(estimated_seconds)
+            );
+            io::stdout().flush().unwrap();
+            let mut answer = String::new();
+            if io::stdin().read_line(&mut answer).is_err() || !is_affirmative(&answer) {
+                println!("Aborted.");
+                std::process::exit(1);
+            }
+            println!();
+        }
+    }
+
+    let output_dir = Path::new(OUTPUT_DIR);
+    prepare_output_dir(output_dir);
+
+    if let Some(seconds) = args.luckiest {
+        println!(
+            "🍀 Finding as many matches as possible in {seconds}s and keeping the luckiest..."
+        );
+        let (best, finds) = luckiest_search(&config, Duration::from_secs(seconds));
+        let Some(best) = best else {
+            eprintln!("❌ No match found in {seconds}s");
+            std::process::exit(1);
+        };
+        let kept = finds
+            .iter()
+            .position(|stats| stats.luck_factor == best.stats.luck_factor)
+            .map_or(0, |index| index + 1);
+        println!();
+        println!(
+            "{}",
+            palette.success(&format!(
+                "🎉 Kept find {kept} of {}: {} iterations, {:.2}x luck",
+                finds.len(),
+                format_number(best.stats.iterations),
+                best.stats.luck_factor
+            ))
+        );
+        println!("====================================");
+        remember_seen(&config, args.seen_set.as_deref(), &best.wallet.public_key);
+        let shown_key = palette.highlight(
+            &best.wallet.public_key,
+            &config.pattern.match_ranges(&best.wallet.public_key),
+        );
+        save_wallet(
+            &best.wallet,
+            &best.stats,
+            &args,
+            output_dir,
+            ata_mint.as_ref(),
+            backup_passphrase.as_deref(),
+            &shown_key,
+        );
+        return;
+    }
+
+    let metrics = args.metrics_addr.as_deref().map(start_metrics);
+
+    // Each wallet is written out and dropped before the next search starts, so memory stays flat
+    // however large the batch; only the numbers of wallets that weren't found are kept, plus the
+    // records for the secrets bundle, which --output-pubkey-list rewrites after every find
+    let mut not_found = Vec::new();
+    let mut bundle = Vec::new();
+    let mut bundle_saved = false;
+    let mut rate_dropped = false;
+    if cfg!(unix) && io::stdin().is_terminal() {
+        println!("⌨️  Press p to pause and r to resume");
+        println!();
+    }
+    for number in already_saved + 1..=args.count {
+        // Starting the next search would only keep a throttling machine hot
+        if rate_dropped {
+            not_found.push(number);
+            continue;
+        }
+        if args.count > 1 {
+            println!("📦 Wallet {number} of {}", args.count);
+        }
+
+        let progress = Arc::new(SearchProgress::default());
+        let stats_thread = spawn_stats_thread(
+            Arc::clone(&progress),
+            expected_iterations,
+            args.rate_window,
+            args.rate_unit.clone(),
+            palette,
+            metrics.clone(),
+            args.stop_on_rate_drop.then(|| {
+                RateDropGuard::new(
+                    args.rate_drop_share,
+                    Duration::from_secs(args.rate_drop_seconds),
+                )
+            }),
+        );
+        let keys = KeyListener::start(Arc::clone(&progress), announce_pause);
+        // Wait for statistics thread to finish, but never let it hold up the result
+        let (result, reporter) =
+            search_with_reporter(&config, &progress, stats_thread, REPORTER_JOIN_TIMEOUT);
+        // Give the terminal back before anything else reads from it
+        drop(keys);
+
+        if let Err(err) = reporter {
+            eprintln!("\n⚠️  Progress display {err}; continuing without it");
+        }
+        if let Some(metrics) = &metrics {
+            metrics.finish_search(result.stats.iterations, result.wallet.is_some());
+        }
+        rate_dropped = progress.rate_dropped.load(Ordering::Relaxed);
+
+        let skipped = progress.skipped.load(Ordering::Relaxed);
+        if skipped > 0 {
+            eprintln!("\n⚠️  Skipped {skipped} candidates whose seed couldn't make a keypair");
+        }
+        let rejected = progress.rejected.load(Ordering::Relaxed);
+        if rejected > 0 {
+            eprintln!("\n⚠️  Discarded {rejected} apparent matches that failed re-verification");
+        }
+        let duplicates = progress.duplicates.load(Ordering::Relaxed);
+        if duplicates > 0 {
+            eprintln!("\n♻️  Passed over {duplicates} matches already in the seen set");
+        }
+
+        let stats = result.stats;
+        let wallet = match (result.wallet, result.closest) {
+            (Some(wallet), _) => {
+                println!("\n");
+                println!(
+                    "{}",
+                    palette.success("🎉 SUCCESS! Vanity wallet generated!")
+                );
+                println!("====================================");
+                match (args.extend_time, &args.extend_target) {
+                    (Some(seconds), Some(target)) => extend_match(
+                        wallet,
+                        &config,
+                        &prefix,
+                        target,
+                        seconds,
+                        args.anywhere_anchor,
+                    ),
+                    _ => wallet,
+                }
+            }
+            (None, Some(closest)) => {
+                println!("\n");
+                let closeness = match target_len {
+                    Some(len) => format!("matches {} of {len} characters", closest.matched_chars),
+                    None => format!("has {} target characters", closest.matched_chars),
+                };
+                println!("⚠️  No exact match within limits; closest address {closeness}");
+                for line in no_match_summary(stats.iterations, expected_iterations) {
+                    println!("   {line}");
+                }
+                println!("====================================");
+                closest.wallet
+            }
+            (None, None) if args.count > 1 => {
+                println!("\n");
+                println!(
+                    "⏭️  Wallet {number} not found within limits after {} iterations, skipping",
+                    format_number(stats.iterations)
+                );
+                for line in no_match_summary(stats.iterations, expected_iterations) {
+                    println!("   {line}");
+                }
+                println!();
+                not_found.push(number);
+                continue;
+            }
+            (None, None) => {
+                println!("\n");
+                eprintln!(
+                    "❌ No match found within limits after {} iterations",
+                    format_number(stats.iterations)
+                );
+                for line in no_match_summary(stats.iterations, expected_iterations) {
+                    eprintln!("   {line}");
+                }
+                std::process::exit(1);
+            }
+        };
+
+        remember_seen(&config, args.seen_set.as_deref(), &wallet.public_key);
+        let shown_key = palette.highlight(
+            &wallet.public_key,
+            &config.pattern.match_ranges(&wallet.public_key),
+        );
+        if args.output_pubkey_list.is_some() {
+            println!("🔑 {shown_key}");
+            println!();
+            warn_if_low_entropy(&wallet);
+            bundle.push(
+                WalletRecord::new(&wallet, &stats)
+                    .with_derivation_path(&config.derivation_path)
+                    .with_target_wallet(args.target_wallet)
+                    .with_seed_salt(&config.seed_salt),
+            );
+            if let (Some(list_path), Some(passphrase)) =
+                (&args.output_pubkey_list, &bundle_passphrase)
+            {
+                bundle_saved = write_pubkey_list(&bundle, list_path, passphrase);
+            }
+        } else {
+            save_wallet(
+                &wallet,
+                &stats,
+                &args,
+                output_dir,
+                ata_mint.as_ref(),
+                backup_passphrase.as_deref(),
+                &shown_key,
+            );
+        }
+    }
+
+    if let (Some(list_path), true) = (&args.output_pubkey_list, bundle_saved) {
+        report_pubkey_list(bundle.len(), list_path);
+    }
+
+    if args.count > 1 {
+        let found = args.count - not_found.len();
+        println!("📦 Batch complete: {found} of {} wallets found", args.count);
+        if already_saved > 0 {
+            println!("   {already_saved} of them were saved by an earlier run");
+        }
+        if rate_dropped {
+            println!("   Stopped early because the search rate dropped");
+        }
+        if !not_found.is_empty() {
+            let numbers: Vec<String> = not_found.iter().map(|n| n.to_string()).collect();
+            println!("   Not found: wallet {}", numbers.join(", "));
+        }
+        if found == 0 {
+            std::process::exit(1);
+        }
+    }
+}
+
+/// Run each job in a job file in turn, then write a results file listing every wallet
+fn run_jobs(path: &Path, mut args: SearchArgs) {
+    // Every job is validated before the first search starts
+    let jobs = fs::read_to_string(path)
+        .map_err(|err| err.to_string())
+        .and_then(|text| parse_jobs(&text).map_err(|err| err.to_string()))
+        .unwrap_or_else(|err| {
+            eprintln!("❌ Error: Unable to run {}: {err}", path.display());
+            std::process::exit(1);
+        });
+    let palette = Palette::new(args.color);
+    let cpu_count = num_cpus::get();
+    prepare_output_dir(Path::new(OUTPUT_DIR));
+
+    if !args.no_banner {
+        println!("{}", palette.title("🚀 Solana Vanity Wallet Generator"));
+        println!("==================================");
+        println!("Jobs: {} from {}", jobs.len(), path.display());
+        println!("Threads: {cpu_count}");
+        println!();
+    }
+
+    let mut results = Vec::new();
+    for (index, job) in jobs.iter().enumerate() {
+        let number = index + 1;
+        let output_dir = job
+            .output
+            .clone()
+            .unwrap_or_else(|| PathBuf::from(OUTPUT_DIR));
+        prepare_output_dir(&output_dir);
+        let config = SearchConfig {
+            max_iterations: args.max_iterations,
+            max_duration: args.max_seconds.map(Duration::from_secs),
+            entropy: entropy_source(args.hardware_entropy),
+            ..job.search_config(cpu_count)
+        };
+        // save_wallet takes the mode, path and file name prefix from the arguments, so point them at
+        // this job
+        args.with_mnemonic = config.with_mnemonic;
+        args.derivation_path = Some(config.derivation_path.clone());
+        args.prefix = job.prefix.clone();
+        let expected_iterations = config.pattern.expected_iterations();
+
+        for wallet_number in 1..=job.count {
+            println!(
+                "📋 Job {number} of {}: {} (wallet {wallet_number} of {})",
+                jobs.len(),
+                job.describe(),
+                job.count
+            );
+            let progress = Arc::new(SearchProgress::default());
+            let stats_thread = spawn_stats_thread(
+                Arc::clone(&progress),
+                expected_iterations,
+                args.rate_window,
+                args.rate_unit.clone(),
+                palette,
+                None,
+                None,
+            );
+            let (result, reporter) =
+                search_with_reporter(&config, &progress, stats_thread, REPORTER_JOIN_TIMEOUT);
+            if let Err(err) = reporter {
+                eprintln!("\n⚠️  Progress display {err}; continuing without it");
+            }
+            println!("\n");
+
+            let public_key = result
+                .wallet
+                .as_ref()
+                .map(|wallet| wallet.public_key.clone());
+            match &result.wallet {
+                Some(wallet) => {
+                    let shown_key = palette.highlight(
+                        &wallet.public_key,
+                        &config.pattern.match_ranges(&wallet.public_key),
+                    );
+                    save_wallet(
+                        wallet,
+                        &result.stats,
+                        &args,
+                        &output_dir,
+                        None,
+                        None,
+                        &shown_key,
+                    );
+                }
+                None => println!(
+                    "⏭️  Job {number}: not found within limits after {} iterations, moving on",
+                    format_number(result.stats.iterations)
+                ),
+            }
+            println!();
+            results.push(JobResult::new(
+                number,
+                job,
+                public_key,
+                output_dir.clone(),
+                &result.stats,
+            ));
+        }
+    }
+
+    let results_path = Path::new(OUTPUT_DIR).join(JOB_RESULTS_FILE);
+    let summary = serde_json::to_string_pretty(&results).expect("job results always serialize");
+    if let Err(err) = write_atomic(&results_path, summary.as_bytes()) {
+        eprintln!(
+            "❌ Error: Unable to write {}: {err}",
+            results_path.display()
+        );
+        std::process::exit(1);
+    }
+    let found = results
+        .iter()
+        .filter(|result| result.public_key.is_some())
+        .count();
+    println!(
+        "📋 Jobs complete: {found} of {} wallets found; results in {}",
+        results.len(),
+        results_path.display()
+    );
+    if found == 0 {
+        std::process::exit(1);
     }
 }
@@ -0,0 +1,257 @@
+//! Prometheus-style metrics for long searches
+//!
+//! [`SearchMetrics`] is fed by the statistics reporter and rendered in the text exposition
+//! format. The HTTP endpoint that serves it is behind the `metrics` feature.
+
+use parking_lot::Mutex;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Instant;
+
+/// Live numbers for the current job, safe to update from one thread and scrape from another
+#[derive(Debug)]
+pub struct SearchMetrics {
+    started: Instant,
+    /// Candidates from searches that have already finished; a batch can run past `u64::MAX`
+    finished_iterations: Mutex<u128>,
+    /// Candidates so far in the running search
+    current_iterations: AtomicU64,
+    /// Latest rate, stored as `f64` bits
+    rate_bits: AtomicU64,
+    wallets_found: AtomicU64,
+}
+
+impl Default for SearchMetrics {
+    fn default() -> Self {
+        SearchMetrics {
+            started: Instant::now(),
+            finished_iterations: Mutex::new(0),
+            current_iterations: AtomicU64::new(0),
+            rate_bits: AtomicU64::new(0f64.to_bits()),
+            wallets_found: AtomicU64::new(0),
+        }
+    }
+}
+
+impl SearchMetrics {
+    /// Record the running search's progress, as reported once a second
+    pub fn update(&self, iterations: u64, rate: f64) {
+        self.current_iterations.store(iterations, Ordering::Relaxed);
+        self.rate_bits.store(rate.to_bits(), Ordering::Relaxed);
+    }
+
+    /// Fold a completed search into the totals, so the iteration counter never goes backwards
+    /// across a batch
+    pub fn finish_search(&self, iterations: u128, found: bool) {
+        let mut finished = self.finished_iterations.lock();
+        *finished = finished.saturating_add(iterations);
+        drop(finished);
+        self.current_iterations.store(0, Ordering::Relaxed);
+        if found {
+            self.wallets_found.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    pub fn iterations(&self) -> u128 {
+        self.finished_iterations
+            .lock()
+            .saturating_add(self.current_iterations.load(Ordering::Relaxed).into())
+    }
+
+    /// The metrics in Prometheus text exposition format
+    pub fn render(&self) -> String {
+        let rate = f64::from_bits(self.rate_bits.load(Ordering::Relaxed));
+        let metrics = [
+            (
+                "vanity_iterations_total",
+                "counter",
+                "Candidate keypairs checked",
+                self.iterations().to_string(),
+            ),
+            (
+                "vanity_rate_per_second",
+                "gauge",
+                "Recent candidates checked per second",
+                rate.to_string(),
+            ),
+            (
+                "vanity_elapsed_seconds",
+                "gauge",
+                "Seconds since the job started",
+                self.started.elapsed().as_secs_f64().to_string(),
+            ),
+            (
+                "vanity_wallets_found_total",
+                "counter",
+                "Matching wallets found",
+                self.wallets_found.load(Ordering::Relaxed).to_string(),
+            ),
+        ];
+
+        metrics
+            .iter()
+            .map(|(name, kind, help, value)| {
+                format!("# HELP {name} {help}\n# TYPE {name} {kind}\n{name} {value}\n")
+            })
+            .collect()
+    }
+}
+
+/// How long a scraper gets to send its request, or take the response, before it's dropped
+///
+/// Requests are answered one at a time, so without this a client that connects and sends
+/// nothing would block every later scrape.
+#[cfg(feature = "metrics")]
+pub const SCRAPE_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(2);
+
+/// Answer `GET /metrics` on `listener` from a background thread for the life of the process
+#[cfg(feature = "metrics")]
+pub fn serve(
+    listener: std::net::TcpListener,
+    metrics: std::sync::Arc<SearchMetrics>,
+) -> std::thread::JoinHandle<()> {
+    use std::io::{BufRead, BufReader, Write};
+
+    std::thread::spawn(move || {
+        for stream in listener.incoming().flatten() {
+            let timeouts = stream
+                .set_read_timeout(Some(SCRAPE_TIMEOUT))
+                .and_then(|()| stream.set_write_timeout(Some(SCRAPE_TIMEOUT)));
+            if timeouts.is_err() {
+                continue;
+            }
+            // Read the whole request head so closing the socket doesn't reset the connection
+            let mut reader = BufReader::new(&stream);
+            let mut request_line = String::new();
+            if reader.read_line(&mut request_line).is_err() {
+                continue;
+            }
+            let mut header = String::new();
+            while reader.read_line(&mut header).is_ok_and(|read| read > 2) {
+                header.clear();
+            }
+            let (status, body) = match request_line.split_whitespace().nth(1) {
+                Some("/metrics") => ("200 OK", metrics.render()),
+                _ => ("404 Not Found", "not found\n".to_string()),
+            };
+            let _ = write!(
+                &stream,
+                "HTTP/1.1 {status}\r\n\
+                 Content-Type: text/plain; version=0.0.4\r\n\
+                 Content-Length: {}\r\n\
+                 Connection: close\r\n\r\n{body}",
+                body.len()
+            );
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Parse exposition text into (name, type, value) triples
+    fn parse(text: &str) -> Vec<(String, String, f64)> {
+        let mut kinds = std::collections::HashMap::new();
+        let mut samples = Vec::new();
+        for line in text.lines() {
+            if let Some(rest) = line.strip_prefix("# TYPE ") {
+                let (name, kind) = rest.split_once(' ').unwrap();
+                kinds.insert(name.to_string(), kind.to_string());
+            } else if !line.starts_with('#') && !line.is_empty() {
+                let (name, value) = line.split_once(' ').unwrap();
+                samples.push((
+                    name.to_string(),
+                    kinds[name].clone(),
+                    value.parse().unwrap(),
+                ));
+            }
+        }
+        samples
+    }
+
+    fn value(samples: &[(String, String, f64)], name: &str) -> f64 {
+        samples.iter().find(|(n, _, _)| n == name).unwrap().2
+    }
+
+    #[test]
+    fn test_render_exposition_format() {
+        let metrics = SearchMetrics::default();
+        metrics.update(1_500, 750.5);
+
+        let samples = parse(&metrics.render());
+        assert_eq!(samples.len(), 4);
+        assert_eq!(value(&samples, "vanity_iterations_total"), 1_500.0);
+        assert_eq!(value(&samples, "vanity_rate_per_second"), 750.5);
+        assert!(value(&samples, "vanity_elapsed_seconds") >= 0.0);
+        assert!(samples
+            .iter()
+            .any(|(name, kind, _)| name == "vanity_iterations_total" && kind == "counter"));
+    }
+
+    #[test]
+    fn test_iterations_accumulate_across_searches() {
+        let metrics = SearchMetrics::default();
+        metrics.update(1_000, 100.0);
+        metrics.finish_search(1_200, true);
+        metrics.update(300, 100.0);
+
+        let samples = parse(&metrics.render());
+        assert_eq!(value(&samples, "vanity_iterations_total"), 1_500.0);
+        assert_eq!(value(&samples, "vanity_wallets_found_total"), 1.0);
+
+        metrics.finish_search(400, false);
+        assert_eq!(metrics.iterations(), 1_600);
+    }
+
+    #[test]
+    fn test_iterations_total_passes_u64_max() {
+        let metrics = SearchMetrics::default();
+        metrics.finish_search(u64::MAX.into(), true);
+        metrics.update(10, 100.0);
+        assert_eq!(metrics.iterations(), u64::MAX as u128 + 10);
+        assert!(metrics
+            .render()
+            .contains("vanity_iterations_total 18446744073709551625"));
+
+        metrics.finish_search(u128::MAX, false);
+        assert_eq!(metrics.iterations(), u128::MAX);
+    }
+
+    #[cfg(feature = "metrics")]
+    #[test]
+    fn test_scrape_endpoint() {
+        use std::io::{Read, Write};
+        use std::net::{TcpListener, TcpStream};
+        use std::sync::Arc;
+
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let metrics = Arc::new(SearchMetrics::default());
+        metrics.update(42, 21.0);
+        serve(listener, Arc::clone(&metrics));
+
+        let scrape = |path: &str| {
+            let mut stream = TcpStream::connect(addr).unwrap();
+            write!(stream, "GET {path} HTTP/1.1\r\nHost: {addr}\r\n\r\n").unwrap();
+            let mut response = String::new();
+            stream.read_to_string(&mut response).unwrap();
+            response
+        };
+
+        let response = scrape("/metrics");
+        assert!(response.starts_with("HTTP/1.1 200 OK"));
+        let (_, body) = response.split_once("\r\n\r\n").unwrap();
+        let samples = parse(body);
+        assert_eq!(value(&samples, "vanity_iterations_total"), 42.0);
+        assert_eq!(value(&samples, "vanity_rate_per_second"), 21.0);
+
+        assert!(scrape("/other").starts_with("HTTP/1.1 404"));
+
+        // A client that connects and says nothing only holds things up until the timeout
+        let silent = TcpStream::connect(addr).unwrap();
+        let start = std::time::Instant::now();
+        assert!(scrape("/metrics").starts_with("HTTP/1.1 200 OK"));
+        assert!(start.elapsed() < SCRAPE_TIMEOUT * 3);
+        drop(silent);
+    }
+}
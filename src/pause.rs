@@ -0,0 +1,328 @@
+//! Pausing a foreground search from the keyboard
+//!
+//! While a search runs on a terminal, `p` pauses it and `r` resumes it, without Enter. The
+//! workers stay alive and keep their counts; they just stop drawing candidates until resumed, so
+//! the CPU is free for other work in the meantime. Only Unix terminals are supported: elsewhere
+//! [`KeyListener::start`] returns `None` and the search runs as before.
+//!
+//! The same terminal handling also backs [`read_line_hidden`], for typing a secret at a prompt.
+
+use crate::SearchProgress;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+/// How often a paused worker, and the key listener, check whether to carry on
+pub const PAUSE_POLL: Duration = Duration::from_millis(100);
+
+/// Whether a search is drawing candidates
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum PauseState {
+    #[default]
+    Running,
+    Paused,
+}
+
+impl PauseState {
+    /// The state after pressing `key`: `p` pauses, `r` resumes and any other key changes nothing
+    pub fn on_key(self, key: u8) -> Self {
+        match key.to_ascii_lowercase() {
+            b'p' => PauseState::Paused,
+            b'r' => PauseState::Running,
+            _ => self,
+        }
+    }
+
+    pub fn is_paused(self) -> bool {
+        self == PauseState::Paused
+    }
+}
+
+/// Reads single keystrokes on a side thread and pauses or resumes a search with them
+///
+/// The terminal is switched out of line mode while listening and put back when the listener is
+/// dropped, so drop it before anything else reads from stdin.
+pub struct KeyListener {
+    stop: Arc<AtomicBool>,
+    handle: Option<JoinHandle<()>>,
+    #[cfg(unix)]
+    _mode: tty::KeystrokeMode,
+}
+
+impl KeyListener {
+    /// Listen for `p` and `r` on stdin, calling `on_change` whenever the state flips
+    ///
+    /// Returns `None` when stdin isn't a terminal that can be read a key at a time.
+    #[cfg(unix)]
+    pub fn start(progress: Arc<SearchProgress>, on_change: fn(PauseState)) -> Option<Self> {
+        use std::io::IsTerminal;
+
+        if !std::io::stdin().is_terminal() {
+            return None;
+        }
+        let mode = tty::KeystrokeMode::enable()?;
+        let stop = Arc::new(AtomicBool::new(false));
+        let handle = {
+            let stop = Arc::clone(&stop);
+            thread::spawn(move || {
+                let mut state = PauseState::Running;
+                while !stop.load(Ordering::Relaxed) && !progress.done.load(Ordering::Relaxed) {
+                    let key = match tty::read_key(PAUSE_POLL) {
+                        Ok(Some(key)) => key,
+                        Ok(None) => continue,
+                        // Stdin closed or broke; the search carries on without the keys
+                        Err(_) => break,
+                    };
+                    let next = state.on_key(key);
+                    if next != state {
+                        state = next;
+                        progress.set_paused(state.is_paused());
+                        on_change(state);
+                    }
+                }
+                // Never leave the workers waiting on a listener that has gone away
+                progress.set_paused(false);
+            })
+        };
+        Some(KeyListener {
+            stop,
+            handle: Some(handle),
+            _mode: mode,
+        })
+    }
+
+    #[cfg(not(unix))]
+    pub fn start(_progress: Arc<SearchProgress>, _on_change: fn(PauseState)) -> Option<Self> {
+        None
+    }
+}
+
+impl Drop for KeyListener {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+        if let Some(handle) = self.handle.take() {
+            // It wakes up at least every PAUSE_POLL, so this is quick
+            let _ = handle.join();
+        }
+    }
+}
+
+/// Read one line from stdin with echo turned off, as for a password
+///
+/// On a Unix terminal the typed text never appears on screen or in scrollback; elsewhere, or if
+/// the terminal can't be switched, the line is read as usual.
+pub fn read_line_hidden() -> std::io::Result<String> {
+    #[cfg(unix)]
+    let _hidden = tty::HiddenInput::enable();
+    let mut line = String::new();
+    std::io::stdin().read_line(&mut line)?;
+    Ok(line)
+}
+
+#[cfg(unix)]
+mod tty {
+    use std::io;
+    use std::ptr;
+    use std::sync::atomic::{AtomicPtr, Ordering};
+    use std::time::Duration;
+
+    /// Terminal settings for the signal handler to put back, or null when there are none
+    static SIGNAL_RESTORE: AtomicPtr<libc::termios> = AtomicPtr::new(ptr::null_mut());
+
+    /// Signals that end the process without running destructors: Ctrl-C, and `kill`
+    const RESTORED_ON: [libc::c_int; 2] = [libc::SIGINT, libc::SIGTERM];
+
+    /// Put the saved terminal settings back, then die of `signal` as if it had never been caught
+    extern "C" fn restore_and_reraise(signal: libc::c_int) {
+        let saved = SIGNAL_RESTORE.load(Ordering::SeqCst);
+        // SAFETY: tcsetattr, signal and raise are async-signal-safe, and `saved` stays valid
+        // while this handler is installed
+        unsafe {
+            if !saved.is_null() {
+                libc::tcsetattr(libc::STDIN_FILENO, libc::TCSANOW, saved);
+            }
+            libc::signal(signal, libc::SIG_DFL);
+            libc::raise(signal);
+        }
+    }
+
+    /// Restores `saved` if a signal in [`RESTORED_ON`] kills the process; put back on drop
+    ///
+    /// Drop only runs on a normal exit, so without this Ctrl-C would leave the shell without echo.
+    struct RestoreOnSignal {
+        saved: Box<libc::termios>,
+        outer: *mut libc::termios,
+        previous: [libc::sigaction; RESTORED_ON.len()],
+    }
+
+    impl RestoreOnSignal {
+        fn install(saved: libc::termios) -> Self {
+            let mut saved = Box::new(saved);
+            let outer = SIGNAL_RESTORE.swap(&mut *saved, Ordering::SeqCst);
+            // SAFETY: sigaction is plain data, filled in before use
+            let mut previous: [libc::sigaction; RESTORED_ON.len()] = unsafe { std::mem::zeroed() };
+            for (signal, previous) in RESTORED_ON.iter().zip(&mut previous) {
+                // SAFETY: a zeroed sigaction with an emptied mask and a handler of the right
+                // signature; `previous` receives the disposition it replaces
+                unsafe {
+                    let mut action: libc::sigaction = std::mem::zeroed();
+                    action.sa_sigaction =
+                        restore_and_reraise as extern "C" fn(libc::c_int) as usize;
+                    libc::sigemptyset(&mut action.sa_mask);
+                    libc::sigaction(*signal, &action, previous);
+                }
+            }
+            RestoreOnSignal {
+                saved,
+                outer,
+                previous,
+            }
+        }
+    }
+
+    impl Drop for RestoreOnSignal {
+        fn drop(&mut self) {
+            for (signal, previous) in RESTORED_ON.iter().zip(&self.previous) {
+                // SAFETY: puts back the disposition sigaction reported for the same signal
+                unsafe {
+                    libc::sigaction(*signal, previous, ptr::null_mut());
+                }
+            }
+            // Hand back whatever an enclosing mode had, once the handler can no longer run
+            let _ = SIGNAL_RESTORE.compare_exchange(
+                &mut *self.saved,
+                self.outer,
+                Ordering::SeqCst,
+                Ordering::SeqCst,
+            );
+        }
+    }
+
+    /// Stdin with echo turned off but still read a line at a time; restored on drop
+    pub struct HiddenInput(RestoreOnSignal);
+
+    impl HiddenInput {
+        pub fn enable() -> Option<Self> {
+            // SAFETY: termios is plain data that tcgetattr fills in before it is read
+            let mut term: libc::termios = unsafe { std::mem::zeroed() };
+            // SAFETY: STDIN_FILENO is open for the life of the process and `term` is valid
+            if unsafe { libc::tcgetattr(libc::STDIN_FILENO, &mut term) } != 0 {
+                return None;
+            }
+            let saved = term;
+            // Keep echoing the Enter that ends the line, so the next output starts on a new one
+            term.c_lflag &= !libc::ECHO;
+            term.c_lflag |= libc::ECHONL;
+            let restore = RestoreOnSignal::install(saved);
+            // SAFETY: as above, with a termios that tcgetattr filled in
+            if unsafe { libc::tcsetattr(libc::STDIN_FILENO, libc::TCSANOW, &term) } != 0 {
+                return None;
+            }
+            Some(HiddenInput(restore))
+        }
+    }
+
+    impl Drop for HiddenInput {
+        fn drop(&mut self) {
+            // SAFETY: restores the settings tcgetattr returned for the same descriptor
+            unsafe {
+                libc::tcsetattr(libc::STDIN_FILENO, libc::TCSANOW, &*self.0.saved);
+            }
+        }
+    }
+
+    /// Stdin switched to delivering each key as it's pressed, without echo; restored on drop,
+    /// or by a signal handler if Ctrl-C or `kill` ends the process first
+    pub struct KeystrokeMode(RestoreOnSignal);
+
+    impl KeystrokeMode {
+        pub fn enable() -> Option<Self> {
+            // SAFETY: termios is plain data that tcgetattr fills in before it is read
+            let mut term: libc::termios = unsafe { std::mem::zeroed() };
+            // SAFETY: STDIN_FILENO is open for the life of the process and `term` is valid
+            if unsafe { libc::tcgetattr(libc::STDIN_FILENO, &mut term) } != 0 {
+                return None;
+            }
+            let saved = term;
+            // Output processing is left alone, so progress lines print as usual
+            term.c_lflag &= !(libc::ICANON | libc::ECHO);
+            term.c_cc[libc::VMIN] = 1;
+            term.c_cc[libc::VTIME] = 0;
+            // Installed first, so there is no moment where a signal could strand the new settings
+            let restore = RestoreOnSignal::install(saved);
+            // SAFETY: as above, with a termios that tcgetattr filled in
+            if unsafe { libc::tcsetattr(libc::STDIN_FILENO, libc::TCSANOW, &term) } != 0 {
+                return None;
+            }
+            Some(KeystrokeMode(restore))
+        }
+    }
+
+    impl Drop for KeystrokeMode {
+        fn drop(&mut self) {
+            // SAFETY: restores the settings tcgetattr returned for the same descriptor
+            unsafe {
+                libc::tcsetattr(libc::STDIN_FILENO, libc::TCSANOW, &*self.0.saved);
+            }
+        }
+    }
+
+    /// The next key pressed within `timeout`, if any; an error once stdin is closed
+    pub fn read_key(timeout: Duration) -> io::Result<Option<u8>> {
+        let mut fds = libc::pollfd {
+            fd: libc::STDIN_FILENO,
+            events: libc::POLLIN,
+            revents: 0,
+        };
+        let millis = timeout.as_millis().min(i32::MAX as u128) as i32;
+        // SAFETY: `fds` is one valid pollfd, matching the count of 1
+        match unsafe { libc::poll(&mut fds, 1, millis) } {
+            0 => return Ok(None),
+            ready if ready < 0 => {
+                let err = io::Error::last_os_error();
+                // A signal such as a terminal resize just interrupts the wait
+                return match err.kind() {
+                    io::ErrorKind::Interrupted => Ok(None),
+                    _ => Err(err),
+                };
+            }
+            _ => {}
+        }
+        let mut key = 0u8;
+        // SAFETY: reads at most one byte into `key`, which outlives the call
+        match unsafe { libc::read(libc::STDIN_FILENO, (&mut key as *mut u8).cast(), 1) } {
+            1 => Ok(Some(key)),
+            0 => Err(io::ErrorKind::UnexpectedEof.into()),
+            _ => Err(io::Error::last_os_error()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pause_state_machine() {
+        let running = PauseState::default();
+        assert!(!running.is_paused());
+
+        let paused = running.on_key(b'p');
+        assert!(paused.is_paused());
+        // Repeats and unrelated keys leave the state alone
+        assert_eq!(paused.on_key(b'p'), PauseState::Paused);
+        assert_eq!(paused.on_key(b'x'), PauseState::Paused);
+        assert_eq!(paused.on_key(b'\n'), PauseState::Paused);
+        assert_eq!(paused.on_key(b'r'), PauseState::Running);
+        assert_eq!(running.on_key(b'r'), PauseState::Running);
+
+        // Caps lock doesn't get in the way
+        assert_eq!(running.on_key(b'P'), PauseState::Paused);
+        assert_eq!(paused.on_key(b'R'), PauseState::Running);
+
+        let keys = b"xpprqrp";
+        let end = keys.iter().fold(running, |state, &key| state.on_key(key));
+        assert_eq!(end, PauseState::Paused);
+    }
+}
@@ -0,0 +1,293 @@
+//! Commands for the `--interactive` prompt
+//!
+//! Each line typed at the prompt is parsed into a [`ReplCommand`] here and run by the binary, so
+//! an exploratory session can search, estimate and change settings without restarting. Parsing
+//! is kept free of I/O so every command's syntax can be tested on its own.
+
+use crate::{validate_prefix, DerivationPath};
+use std::str::FromStr;
+
+/// One line of input at the interactive prompt
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ReplCommand {
+    /// Find a wallet whose address starts with this prefix
+    Search(String),
+    /// Show the odds and expected work for this prefix without searching
+    Estimate(String),
+    /// Show the address a mnemonic derives at the session's path, words single-spaced
+    Restore(String),
+    /// Change one setting for the rest of the session
+    Config(Setting),
+    /// Show the session's settings
+    ShowConfig,
+    Help,
+    Quit,
+}
+
+/// A session setting that `config` changes
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Setting {
+    /// Worker threads for searches and rate measurements
+    Threads(usize),
+    /// Whether searches generate a recovery phrase
+    Mnemonic(bool),
+    /// Path mnemonic wallets are derived at, for `search` and `restore`
+    Path(DerivationPath),
+}
+
+/// Summary of the commands, printed by `help`
+pub const HELP: &str = "\
+search <PREFIX>           find a wallet whose address starts with PREFIX
+estimate <PREFIX>         show the odds and expected time for PREFIX
+restore <MNEMONIC...>     show the address a recovery phrase derives
+config                    show the current settings
+config threads <N>        search on N threads
+config mnemonic on|off    generate recovery phrases (slower) or raw keypairs
+config path <PATH>        derive mnemonic wallets at PATH, e.g. m/44'/501'/1'/0'
+help                      show this list
+quit                      leave (also exit, or end of input)";
+
+/// Why a line at the prompt couldn't be understood
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ReplError {
+    UnknownCommand(String),
+    /// The command needs an argument, described here
+    MissingArgument {
+        command: String,
+        expected: String,
+    },
+    /// The command takes no more than the arguments it was given before this one
+    UnexpectedArgument {
+        command: String,
+        argument: String,
+    },
+    InvalidArgument {
+        command: String,
+        reason: String,
+    },
+}
+
+impl std::fmt::Display for ReplError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ReplError::UnknownCommand(command) => {
+                write!(f, "unknown command '{command}'; type help for the list")
+            }
+            ReplError::MissingArgument { command, expected } => {
+                write!(f, "{command} needs {expected}")
+            }
+            ReplError::UnexpectedArgument { command, argument } => {
+                write!(f, "{command} doesn't take '{argument}'")
+            }
+            ReplError::InvalidArgument { command, reason } => write!(f, "{command}: {reason}"),
+        }
+    }
+}
+
+impl std::error::Error for ReplError {}
+
+/// Parse one line typed at the prompt; blank lines and `#` comments are `None`
+///
+/// Command and setting names are case-insensitive; prefixes and mnemonics are kept as typed.
+pub fn parse_command(line: &str) -> Result<Option<ReplCommand>, ReplError> {
+    let line = line.trim();
+    if line.is_empty() || line.starts_with('#') {
+        return Ok(None);
+    }
+    let mut words = line.split_whitespace();
+    let command = words.next().unwrap_or_default().to_ascii_lowercase();
+    let args: Vec<&str> = words.collect();
+
+    let missing = |expected: &str| ReplError::MissingArgument {
+        command: command.clone(),
+        expected: expected.to_string(),
+    };
+    let invalid = |reason: String| ReplError::InvalidArgument {
+        command: command.clone(),
+        reason,
+    };
+    // Every command but restore takes a fixed number of arguments
+    let at_most = |count: usize| match args.get(count) {
+        Some(extra) => Err(ReplError::UnexpectedArgument {
+            command: command.clone(),
+            argument: extra.to_string(),
+        }),
+        None => Ok(()),
+    };
+
+    let parsed = match command.as_str() {
+        "search" | "estimate" => {
+            let prefix = args.first().ok_or_else(|| missing("a prefix"))?;
+            at_most(1)?;
+            validate_prefix(prefix).map_err(|err| invalid(format!("'{prefix}': {err}")))?;
+            if command == "search" {
+                ReplCommand::Search(prefix.to_string())
+            } else {
+                ReplCommand::Estimate(prefix.to_string())
+            }
+        }
+        "restore" => {
+            if args.is_empty() {
+                return Err(missing("the words of a recovery phrase"));
+            }
+            ReplCommand::Restore(args.join(" "))
+        }
+        "config" => match args.first().map(|name| name.to_ascii_lowercase()) {
+            None => ReplCommand::ShowConfig,
+            Some(name) => {
+                let value = args
+                    .get(1)
+                    .ok_or_else(|| missing(&format!("a {name} value")))?;
+                at_most(2)?;
+                ReplCommand::Config(parse_setting(&name, value).map_err(invalid)?)
+            }
+        },
+        "help" | "?" => {
+            at_most(0)?;
+            ReplCommand::Help
+        }
+        "quit" | "exit" => {
+            at_most(0)?;
+            ReplCommand::Quit
+        }
+        _ => return Err(ReplError::UnknownCommand(command)),
+    };
+    Ok(Some(parsed))
+}
+
+fn parse_setting(name: &str, value: &str) -> Result<Setting, String> {
+    match name {
+        "threads" => match value.parse() {
+            Ok(0) | Err(_) => Err(format!(
+                "threads must be a whole number from 1, got '{value}'"
+            )),
+            Ok(threads) => Ok(Setting::Threads(threads)),
+        },
+        "mnemonic" => match value.to_ascii_lowercase().as_str() {
+            "on" | "yes" | "true" => Ok(Setting::Mnemonic(true)),
+            "off" | "no" | "false" => Ok(Setting::Mnemonic(false)),
+            _ => Err(format!("mnemonic is on or off, got '{value}'")),
+        },
+        "path" => DerivationPath::from_str(value)
+            .map(Setting::Path)
+            .map_err(|err| err.to_string()),
+        _ => Err(format!(
+            "unknown setting '{name}'; the settings are threads, mnemonic and path"
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_repl_commands() {
+        let parse = |line: &str| parse_command(line).unwrap();
+
+        assert_eq!(
+            parse("search Pay"),
+            Some(ReplCommand::Search("Pay".to_string()))
+        );
+        assert_eq!(
+            parse("  SEARCH   Sun  "),
+            Some(ReplCommand::Search("Sun".to_string()))
+        );
+        assert_eq!(
+            parse("estimate Moon"),
+            Some(ReplCommand::Estimate("Moon".to_string()))
+        );
+        assert_eq!(
+            parse("restore  abandon abandon   about"),
+            Some(ReplCommand::Restore("abandon abandon about".to_string()))
+        );
+        assert_eq!(
+            parse("config threads 4"),
+            Some(ReplCommand::Config(Setting::Threads(4)))
+        );
+        assert_eq!(
+            parse("config Mnemonic ON"),
+            Some(ReplCommand::Config(Setting::Mnemonic(true)))
+        );
+        assert_eq!(
+            parse("config path m/44'/501'/1'/0'"),
+            Some(ReplCommand::Config(Setting::Path(
+                DerivationPath::for_account(1).unwrap()
+            )))
+        );
+        assert_eq!(parse("config"), Some(ReplCommand::ShowConfig));
+        assert_eq!(parse("help"), Some(ReplCommand::Help));
+        assert_eq!(parse("exit"), Some(ReplCommand::Quit));
+        assert_eq!(parse(""), None);
+        assert_eq!(parse("   "), None);
+        assert_eq!(parse("# a comment"), None);
+    }
+
+    #[test]
+    fn test_parse_invalid_repl_commands() {
+        let error = |line: &str| parse_command(line).unwrap_err();
+
+        assert_eq!(
+            error("serch Sun"),
+            ReplError::UnknownCommand("serch".to_string())
+        );
+        assert!(matches!(
+            error("search"),
+            ReplError::MissingArgument { command, .. } if command == "search"
+        ));
+        assert!(matches!(
+            error("search Pay Sun"),
+            ReplError::UnexpectedArgument { argument, .. } if argument == "Sun"
+        ));
+        // Prefixes are checked up front, with the same rules as the command line
+        assert!(matches!(
+            error("estimate S0n"),
+            ReplError::InvalidArgument { command, .. } if command == "estimate"
+        ));
+        // Base58 has no lowercase l
+        assert!(matches!(
+            error("search Sol"),
+            ReplError::InvalidArgument { .. }
+        ));
+        assert!(matches!(
+            error("restore"),
+            ReplError::MissingArgument { .. }
+        ));
+        assert!(matches!(
+            error("config threads"),
+            ReplError::MissingArgument { .. }
+        ));
+        assert!(matches!(
+            error("config threads 0"),
+            ReplError::InvalidArgument { .. }
+        ));
+        assert!(matches!(
+            error("config threads four"),
+            ReplError::InvalidArgument { .. }
+        ));
+        assert!(matches!(
+            error("config threads 4 8"),
+            ReplError::UnexpectedArgument { .. }
+        ));
+        assert!(matches!(
+            error("config mnemonic maybe"),
+            ReplError::InvalidArgument { .. }
+        ));
+        assert!(matches!(
+            error("config path m/44/501"),
+            ReplError::InvalidArgument { .. }
+        ));
+        assert!(matches!(
+            error("config colour on"),
+            ReplError::InvalidArgument { .. }
+        ));
+        assert!(matches!(
+            error("quit now"),
+            ReplError::UnexpectedArgument { .. }
+        ));
+        assert_eq!(
+            error("config threads 0").to_string(),
+            "config: threads must be a whole number from 1, got '0'"
+        );
+    }
+}
@@ -0,0 +1,336 @@
+//! HTTP provisioning service (`server` feature)
+//!
+//! `POST /vanity` runs a search and returns the wallet as JSON; `GET /estimate?prefix=...`
+//! reports how hard a prefix is. Keys only leave the process in the response unless the request
+//! sets `save`.
+
+use crate::{
+    ensure_output_dir, prefix_difficulty_bits, prefix_probability, search_vanity, validate_prefix,
+    wallet_file_name, write_atomic, write_checksum_file, SearchConfig, SearchProgress,
+    VanityPattern, WalletRecord, OUTPUT_DIR,
+};
+use axum::extract::{Query, State};
+use axum::http::StatusCode;
+use axum::routing::{get, post};
+use axum::{Json, Router};
+use serde::{Deserialize, Serialize};
+use std::io;
+use std::net::TcpListener;
+use std::path::PathBuf;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::Semaphore;
+
+/// Limits and defaults for the service
+#[derive(Debug, Clone)]
+pub struct ServerConfig {
+    /// Searches allowed to run at once; further requests get 503
+    pub max_concurrent: usize,
+    /// Longest prefix accepted, so one request can't occupy the machine for days
+    pub max_prefix_len: usize,
+    /// Upper bound on any one search, whatever the request asks for
+    pub max_seconds: u64,
+    /// Where results go when a request sets `save`
+    pub output_dir: PathBuf,
+}
+
+impl Default for ServerConfig {
+    fn default() -> Self {
+        ServerConfig {
+            max_concurrent: 2,
+            max_prefix_len: 6,
+            max_seconds: 300,
+            output_dir: PathBuf::from(OUTPUT_DIR),
+        }
+    }
+}
+
+/// Body of `POST /vanity`
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct VanityRequest {
+    pub prefix: String,
+    #[serde(default)]
+    pub with_mnemonic: bool,
+    /// Give up after this long, capped at [`ServerConfig::max_seconds`]
+    #[serde(default)]
+    pub max_seconds: Option<u64>,
+    /// Also write the result to the server's output directory
+    #[serde(default)]
+    pub save: bool,
+}
+
+#[derive(Debug, Deserialize)]
+struct EstimateQuery {
+    prefix: String,
+}
+
+/// Response of `GET /estimate`
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Estimate {
+    pub prefix: String,
+    pub expected_iterations: u128,
+    pub difficulty_bits: f64,
+    pub probability: f64,
+}
+
+#[derive(Debug, Serialize)]
+struct ErrorBody {
+    error: String,
+}
+
+type ApiError = (StatusCode, Json<ErrorBody>);
+
+fn error(status: StatusCode, message: impl Into<String>) -> ApiError {
+    (
+        status,
+        Json(ErrorBody {
+            error: message.into(),
+        }),
+    )
+}
+
+struct AppState {
+    config: ServerConfig,
+    permits: Arc<Semaphore>,
+    threads_per_search: usize,
+}
+
+/// Stops the search behind a request once the request goes away, e.g. the client disconnects
+struct StopOnDrop(Arc<SearchProgress>);
+
+impl Drop for StopOnDrop {
+    fn drop(&mut self) {
+        self.0.done.store(true, Ordering::Relaxed);
+    }
+}
+
+impl AppState {
+    fn check_prefix(&self, prefix: &str) -> Result<(), ApiError> {
+        validate_prefix(prefix)
+            .map_err(|err| error(StatusCode::BAD_REQUEST, format!("invalid prefix: {err}")))?;
+        if prefix.chars().count() > self.config.max_prefix_len {
+            return Err(error(
+                StatusCode::BAD_REQUEST,
+                format!(
+                    "prefixes are limited to {} characters",
+                    self.config.max_prefix_len
+                ),
+            ));
+        }
+        Ok(())
+    }
+}
+
+async fn estimate(
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<EstimateQuery>,
+) -> Result<Json<Estimate>, ApiError> {
+    state.check_prefix(&query.prefix)?;
+    Ok(Json(Estimate {
+        expected_iterations: VanityPattern::Prefix(query.prefix.clone()).expected_iterations(),
+        difficulty_bits: prefix_difficulty_bits(&query.prefix),
+        probability: prefix_probability(&query.prefix),
+        prefix: query.prefix,
+    }))
+}
+
+async fn vanity(
+    State(state): State<Arc<AppState>>,
+    Json(request): Json<VanityRequest>,
+) -> Result<Json<WalletRecord>, ApiError> {
+    state.check_prefix(&request.prefix)?;
+    // Owned, so the search keeps it even if this request is dropped before the search stops
+    let permit = state.permits.clone().try_acquire_owned().map_err(|_| {
+        error(
+            StatusCode::SERVICE_UNAVAILABLE,
+            "too many searches running; try again later",
+        )
+    })?;
+
+    let seconds = request
+        .max_seconds
+        .unwrap_or(state.config.max_seconds)
+        .min(state.config.max_seconds);
+    let config = SearchConfig {
+        max_duration: Some(Duration::from_secs(seconds)),
+        ..SearchConfig::new(
+            VanityPattern::Prefix(request.prefix.clone()),
+            request.with_mnemonic,
+            state.threads_per_search,
+        )
+    };
+    let progress = Arc::new(SearchProgress::default());
+    let _stop = StopOnDrop(Arc::clone(&progress));
+    let result = tokio::task::spawn_blocking(move || {
+        let _permit = permit;
+        search_vanity(&config, &progress)
+    })
+    .await
+    .map_err(|err| error(StatusCode::INTERNAL_SERVER_ERROR, err.to_string()))?;
+
+    let wallet = result.wallet.ok_or_else(|| {
+        error(
+            StatusCode::REQUEST_TIMEOUT,
+            format!("no match within {seconds} seconds"),
+        )
+    })?;
+    let record =
+        WalletRecord::new(&wallet, &result.stats).with_derivation_path(&Default::default());
+
+    if request.save {
+        let json = serde_json::to_string_pretty(&record)
+            .map_err(|err| error(StatusCode::INTERNAL_SERVER_ERROR, err.to_string()))?;
+        let path = state
+            .config
+            .output_dir
+            .join(wallet_file_name(&record.public_key, "output.json"));
+        ensure_output_dir(&state.config.output_dir)
+            .and_then(|()| write_atomic(&path, json.as_bytes()))
+            .and_then(|()| write_checksum_file(&path, json.as_bytes()))
+            .map_err(|err| {
+                error(
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    format!("unable to save result: {err}"),
+                )
+            })?;
+    }
+
+    Ok(Json(record))
+}
+
+/// The service's routes, sharing one concurrency limit
+pub fn router(config: ServerConfig) -> Router {
+    let threads_per_search = (num_cpus::get() / config.max_concurrent.max(1)).max(1);
+    let state = Arc::new(AppState {
+        permits: Arc::new(Semaphore::new(config.max_concurrent)),
+        threads_per_search,
+        config,
+    });
+    Router::new()
+        .route("/vanity", post(vanity))
+        .route("/estimate", get(estimate))
+        .with_state(state)
+}
+
+/// Serve requests on `listener` until the process exits
+pub fn run(listener: TcpListener, config: ServerConfig) -> io::Result<()> {
+    listener.set_nonblocking(true)?;
+    tokio::runtime::Runtime::new()?.block_on(async {
+        let listener = tokio::net::TcpListener::from_std(listener)?;
+        axum::serve(listener, router(config)).await
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::keypair_from_seed;
+    use std::io::{Read, Write};
+    use std::net::{SocketAddr, TcpStream};
+
+    fn start(config: ServerConfig) -> SocketAddr {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        std::thread::spawn(move || run(listener, config));
+        addr
+    }
+
+    /// Minimal HTTP/1.1 client: status code and body
+    fn request(addr: SocketAddr, method: &str, path: &str, body: &str) -> (u16, String) {
+        let mut stream = TcpStream::connect(addr).unwrap();
+        write!(
+            stream,
+            "{method} {path} HTTP/1.1\r\nHost: {addr}\r\nContent-Type: application/json\r\n\
+             Content-Length: {}\r\nConnection: close\r\n\r\n{body}",
+            body.len()
+        )
+        .unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+
+        let status = response[9..12].parse().unwrap();
+        let body = response
+            .split_once("\r\n\r\n")
+            .map(|(_, body)| body.to_string())
+            .unwrap_or_default();
+        (status, body)
+    }
+
+    #[test]
+    fn test_vanity_endpoint() {
+        let addr = start(ServerConfig::default());
+
+        let (status, body) = request(addr, "POST", "/vanity", r#"{"prefix": "1"}"#);
+        assert_eq!(status, 200, "{body}");
+        let record: WalletRecord = serde_json::from_str(&body).unwrap();
+        assert!(record.public_key.starts_with('1'));
+        let keypair = keypair_from_seed(&record.keypair_json[..32]).unwrap();
+        assert!(crate::keypair_matches_pubkey(&keypair, &record.public_key));
+        assert!(record.mnemonic.is_none());
+
+        let (status, body) = request(addr, "POST", "/vanity", r#"{"prefix": "0OIl"}"#);
+        assert_eq!(status, 400, "{body}");
+        assert!(body.contains("invalid prefix"));
+
+        let (status, _) = request(addr, "POST", "/vanity", r#"{"prefix": "ABCDEFGH"}"#);
+        assert_eq!(status, 400);
+
+        let (status, _) = request(addr, "POST", "/vanity", r#"{"prefix": "1", "bogus": 1}"#);
+        assert!((400..500).contains(&status));
+    }
+
+    #[test]
+    fn test_estimate_endpoint() {
+        let addr = start(ServerConfig::default());
+
+        let (status, body) = request(addr, "GET", "/estimate?prefix=Sun", "");
+        assert_eq!(status, 200, "{body}");
+        let estimate: Estimate = serde_json::from_str(&body).unwrap();
+        assert_eq!(estimate.prefix, "Sun");
+        assert_eq!(estimate.expected_iterations, 58u128.pow(3) / 2);
+
+        let (status, _) = request(addr, "GET", "/estimate?prefix=0", "");
+        assert_eq!(status, 400);
+    }
+
+    #[test]
+    fn test_concurrency_limit() {
+        let addr = start(ServerConfig {
+            max_concurrent: 0,
+            ..ServerConfig::default()
+        });
+
+        let (status, body) = request(addr, "POST", "/vanity", r#"{"prefix": "1"}"#);
+        assert_eq!(status, 503, "{body}");
+    }
+
+    #[test]
+    fn test_running_search_holds_its_permit() {
+        let addr = start(ServerConfig {
+            max_concurrent: 1,
+            ..ServerConfig::default()
+        });
+
+        // Six characters won't turn up in two seconds, so this one runs until its limit
+        let slow = std::thread::spawn(move || {
+            request(
+                addr,
+                "POST",
+                "/vanity",
+                r#"{"prefix": "zzzzzz", "max_seconds": 2}"#,
+            )
+        });
+        std::thread::sleep(Duration::from_millis(500));
+
+        let (status, body) = request(addr, "POST", "/vanity", r#"{"prefix": "1"}"#);
+        assert_eq!(status, 503, "{body}");
+
+        let (status, _) = slow.join().unwrap();
+        assert_eq!(status, 408);
+        // The permit is back once the first search has stopped
+        let (status, body) = request(addr, "POST", "/vanity", r#"{"prefix": "1"}"#);
+        assert_eq!(status, 200, "{body}");
+    }
+}
@@ -0,0 +1,109 @@
+//! Runs the CLI's `--count` mode end to end, including resuming a batch
+//!
+//! Each wallet of a batch is written out as soon as it's found and then dropped, so a large batch
+//! never holds more than one in memory. This checks that the batch still leaves one complete,
+//! recoverable result file per wallet.
+
+use solana_vanity_wallet::{address_of, parse_secret_key, verify_saved_wallet};
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// Fresh scratch directory under the system temp dir
+fn test_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!(
+        "solana-vanity-wallet-{}-{name}",
+        std::process::id()
+    ));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+/// Run the CLI in `dir` with `args`, failing the test if it doesn't succeed; returns its stdout
+fn run_in(dir: &Path, args: &[&str]) -> String {
+    let output = Command::new(env!("CARGO_BIN_EXE_solana-vanity-wallet"))
+        .args(args)
+        .current_dir(dir)
+        .output()
+        .unwrap();
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    String::from_utf8(output.stdout).unwrap()
+}
+
+/// Text result files in `dir`'s output directory
+fn result_files(dir: &Path) -> Vec<PathBuf> {
+    let mut files: Vec<PathBuf> = fs::read_dir(dir.join("output"))
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.to_string_lossy().ends_with("_output.txt"))
+        .collect();
+    files.sort();
+    files
+}
+
+/// The Secret Key line of a text result file
+fn secret_key_line(contents: &str) -> &str {
+    contents
+        .lines()
+        .find_map(|line| line.strip_prefix("Secret Key: "))
+        .expect("result files have a Secret Key line")
+}
+
+#[test]
+fn test_count_mode_writes_one_verified_file_per_wallet() {
+    let dir = test_dir("count-mode");
+    run_in(&dir, &["1", "--count", "3", "--with-mnemonic", "--yes"]);
+
+    let files = result_files(&dir);
+    assert_eq!(files.len(), 3, "{files:?}");
+
+    let mut public_keys = HashSet::new();
+    for path in &files {
+        let contents = fs::read_to_string(path).unwrap();
+        let keypair = parse_secret_key(secret_key_line(&contents)).unwrap();
+        // The saved secret key and mnemonic both rebuild this keypair
+        verify_saved_wallet(&contents, &keypair).unwrap();
+        let public_key = address_of(&keypair);
+        assert!(public_key.starts_with('1'), "{public_key}");
+        assert!(public_keys.insert(public_key));
+    }
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_resumed_batch_only_finds_the_rest() {
+    let dir = test_dir("resume");
+    run_in(&dir, &["1", "--count", "2", "--yes"]);
+    let before = result_files(&dir);
+    assert_eq!(before.len(), 2, "{before:?}");
+
+    let stdout = run_in(&dir, &["1", "--count", "5", "--resume", "--yes"]);
+    assert!(stdout.contains("Resuming: 2 of 5"), "{stdout}");
+    // Three new wallets, and the earlier two left as they were
+    let after = result_files(&dir);
+    assert_eq!(after.len(), 5, "{after:?}");
+    assert!(before.iter().all(|path| after.contains(path)));
+
+    // A complete batch finds nothing more
+    let stdout = run_in(&dir, &["1", "--count", "5", "--resume", "--yes"]);
+    assert!(stdout.contains("Nothing left to find"), "{stdout}");
+    assert_eq!(result_files(&dir).len(), 5);
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_count_zero_is_rejected() {
+    let output = Command::new(env!("CARGO_BIN_EXE_solana-vanity-wallet"))
+        .args(["1", "--count", "0", "--yes"])
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("--count"), "{stderr}");
+}
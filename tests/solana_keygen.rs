@@ -0,0 +1,178 @@
+//! Cross-checks mnemonic derivation against the Solana CLI
+//!
+//! Each case generates a wallet with a recovery phrase, feeds the phrase to
+//! `solana-keygen recover` and checks the keypair it writes is the one this crate derived. It
+//! needs the Solana CLI, so it's ignored in a normal `cargo test`; run it with
+//! `cargo test --test solana_keygen -- --ignored`. `SOLANA_KEYGEN` points it at a binary that
+//! isn't on `PATH`. When neither is found the test says so and passes.
+//!
+//! `solana-keygen` reads the seed phrase from the controlling terminal rather than stdin, so it
+//! runs on a pseudo-terminal of its own here. That's Unix-only, like the test.
+
+#![cfg(unix)]
+
+use rand::rngs::OsRng;
+use solana_sdk::signature::Signer;
+use solana_vanity_wallet::{
+    generate_keypair_from, keygen_prompt_uri, parse_secret_key, DerivationPath,
+};
+use std::fs::{self, File};
+use std::io::{self, Read, Write};
+use std::os::fd::{AsRawFd, FromRawFd};
+use std::os::unix::process::CommandExt;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output, Stdio};
+use std::time::{Duration, Instant};
+
+/// How long to wait for each `solana-keygen` prompt before giving up
+const PROMPT_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// The `solana-keygen` binary to run, or `None` if it isn't installed
+fn find_keygen() -> Option<PathBuf> {
+    let keygen = std::env::var_os("SOLANA_KEYGEN")
+        .map(PathBuf::from)
+        .unwrap_or_else(|| PathBuf::from("solana-keygen"));
+    let installed = Command::new(&keygen)
+        .arg("--version")
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .is_ok_and(|status| status.success());
+    installed.then_some(keygen)
+}
+
+/// A pseudo-terminal pair: the side we type into and the side the child gets as its terminal
+fn open_pty() -> io::Result<(File, File)> {
+    let (mut master, mut slave) = (0, 0);
+    // SAFETY: both out-pointers are valid; the name, termios and winsize arguments are optional
+    let result = unsafe {
+        libc::openpty(
+            &mut master,
+            &mut slave,
+            std::ptr::null_mut(),
+            std::ptr::null(),
+            std::ptr::null(),
+        )
+    };
+    if result != 0 {
+        return Err(io::Error::last_os_error());
+    }
+    // SAFETY: openpty just opened both descriptors and nothing else owns them
+    Ok(unsafe { (File::from_raw_fd(master), File::from_raw_fd(slave)) })
+}
+
+/// Read from the terminal until `prompt` shows up
+fn wait_for_prompt(master: &mut File, prompt: &str) -> io::Result<()> {
+    let deadline = Instant::now() + PROMPT_TIMEOUT;
+    let mut seen = String::new();
+    let mut buf = [0u8; 256];
+    while !seen.contains(prompt) {
+        let left = deadline.saturating_duration_since(Instant::now());
+        let mut fds = libc::pollfd {
+            fd: master.as_raw_fd(),
+            events: libc::POLLIN,
+            revents: 0,
+        };
+        // SAFETY: `fds` is one valid pollfd, matching the count of 1
+        let ready = unsafe { libc::poll(&mut fds, 1, left.as_millis() as i32) };
+        if ready == 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::TimedOut,
+                format!("no {prompt:?} prompt, got {seen:?}"),
+            ));
+        }
+        // Linux reports EIO here once the child has closed the terminal
+        let read = master.read(&mut buf)?;
+        if read == 0 {
+            return Err(io::ErrorKind::UnexpectedEof.into());
+        }
+        seen.push_str(&String::from_utf8_lossy(&buf[..read]));
+    }
+    Ok(())
+}
+
+/// Run `keygen` with `args` on its own terminal, typing each reply once its prompt appears
+fn run_on_pty(keygen: &Path, args: &[&str], replies: &[(&str, &str)]) -> io::Result<Output> {
+    let (mut master, slave) = open_pty()?;
+    let mut command = Command::new(keygen);
+    command
+        .args(args)
+        .stdin(Stdio::from(slave))
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped());
+    // SAFETY: only async-signal-safe calls between fork and exec
+    unsafe {
+        command.pre_exec(|| {
+            // A new session whose controlling terminal is the pty on stdin, so /dev/tty is it
+            if libc::setsid() < 0 || libc::ioctl(0, libc::TIOCSCTTY as _, 0) < 0 {
+                return Err(io::Error::last_os_error());
+            }
+            Ok(())
+        });
+    }
+    // The command holds our copy of the slave side, which goes away with it
+    let child = command.spawn()?;
+    drop(command);
+
+    for (prompt, reply) in replies {
+        wait_for_prompt(&mut master, prompt)?;
+        master.write_all(format!("{reply}\n").as_bytes())?;
+    }
+    child.wait_with_output()
+}
+
+#[test]
+#[ignore = "needs the solana CLI; run with cargo test --test solana_keygen -- --ignored"]
+fn test_solana_keygen_recovers_the_same_keypair() {
+    let Some(keygen) = find_keygen() else {
+        eprintln!(
+            "solana-keygen not found; set SOLANA_KEYGEN or install the Solana CLI to run this"
+        );
+        return;
+    };
+    let dir = std::env::temp_dir().join(format!(
+        "solana-vanity-wallet-{}-keygen",
+        std::process::id()
+    ));
+    fs::create_dir_all(&dir).unwrap();
+
+    let paths = [
+        DerivationPath::default(),
+        DerivationPath::for_account(7).unwrap(),
+        "m/44'/501'/3'".parse().unwrap(),
+        "m/44'/501'/0'/0'/2'".parse().unwrap(),
+    ];
+    for path in paths {
+        let (mnemonic, keypair) = generate_keypair_from(&mut OsRng, true, &path).unwrap();
+        let mnemonic = mnemonic.expect("mnemonic mode returns the phrase");
+        let outfile = dir.join("keypair.json");
+        let uri = keygen_prompt_uri(&path);
+        let output = run_on_pty(
+            &keygen,
+            &[
+                "recover",
+                &uri,
+                "--force",
+                "--outfile",
+                outfile.to_str().unwrap(),
+            ],
+            // An empty BIP39 passphrase, as every wallet this crate generates uses
+            &[("seed phrase:", &mnemonic), ("passphrase", "")],
+        )
+        .unwrap_or_else(|err| panic!("solana-keygen recover '{uri}' failed to run: {err}"));
+        assert!(
+            output.status.success(),
+            "solana-keygen recover '{uri}' failed: {}",
+            String::from_utf8_lossy(&output.stderr)
+        );
+
+        let recovered = parse_secret_key(&fs::read_to_string(&outfile).unwrap()).unwrap();
+        assert_eq!(
+            recovered.pubkey(),
+            keypair.pubkey(),
+            "solana-keygen derived a different address at {path}"
+        );
+        assert_eq!(recovered.to_bytes(), keypair.to_bytes());
+    }
+    let _ = fs::remove_dir_all(&dir);
+}